@@ -0,0 +1,16 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rust_bitfinex_quote_scraper::BitfinexSource;
+use scrapers_fuzz::parse_frame;
+
+// Tickers and books only parse on a channel the source has seen subscribed
+const SUBSCRIBED: &str = r#"{"event":"subscribed","channel":"ticker","chanId":1,"symbol":"tBTCUSD","pair":"BTCUSD"}"#;
+const BOOK_SUBSCRIBED: &str = r#"{"event":"subscribed","channel":"book","chanId":2,"symbol":"tBTCUSD","prec":"P0","freq":"F0","len":"25","pair":"BTCUSD"}"#;
+
+fuzz_target!(|frame: &str| {
+    let mut source = BitfinexSource::default().with_book(10);
+    parse_frame(&mut source, SUBSCRIBED);
+    parse_frame(&mut source, BOOK_SUBSCRIBED);
+    parse_frame(&mut source, frame);
+});
@@ -0,0 +1,9 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rust_bitget_quote_scraper::BitgetSource;
+use scrapers_fuzz::parse_frame;
+
+fuzz_target!(|frame: &str| {
+    parse_frame(&mut BitgetSource::default(), frame);
+});
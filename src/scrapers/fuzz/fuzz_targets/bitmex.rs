@@ -0,0 +1,9 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rust_bitmex_quote_scraper::BitmexSource;
+use scrapers_fuzz::parse_frame;
+
+fuzz_target!(|frame: &str| {
+    parse_frame(&mut BitmexSource::default(), frame);
+});
@@ -0,0 +1,14 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rust_htx_quote_scraper::HtxSource;
+use scraper_core::QuoteSource;
+use scrapers_fuzz::parse_frame;
+
+// HTX frames arrive gzipped, so start from the raw bytes
+fuzz_target!(|data: &[u8]| {
+    let mut source = HtxSource::default();
+    if let Ok(frame) = source.decode_binary(data) {
+        parse_frame(&mut source, &frame);
+    }
+});
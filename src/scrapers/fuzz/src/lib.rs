@@ -0,0 +1,48 @@
+use scraper_core::{Frame, QuoteSource};
+
+/// The path every inbound frame takes in the runner: parse it, then check each
+/// quote, set of ticker stats, trade, book or set of perpetual figures and map its symbol to an
+/// instrument and its keys, or format the error for the log.
+pub fn parse_frame<S: QuoteSource>(source: &mut S, frame: &str) {
+    match source.parse(frame) {
+        Frame::Quotes(quotes) => {
+            for quote in quotes.into_iter().filter(|quote| quote.validate().is_ok()) {
+                if let Some(instrument) = source.instrument(&quote.symbol) {
+                    source.key_prefix(&instrument);
+                }
+            }
+        }
+        Frame::Stats(tickers) => {
+            for ticker in tickers.into_iter().filter(|ticker| ticker.validate().is_ok()) {
+                if let Some(instrument) = source.instrument(&ticker.symbol) {
+                    source.stats_prefix(&instrument);
+                }
+            }
+        }
+        Frame::Trades(trades) => {
+            for trade in trades.into_iter().filter(|trade| trade.validate().is_ok()) {
+                if let Some(instrument) = source.instrument(&trade.symbol) {
+                    source.trade_prefix(&instrument);
+                }
+            }
+        }
+        Frame::Books(books) => {
+            for book in books.into_iter().filter(|book| book.validate().is_ok()) {
+                if let Some(instrument) = source.instrument(&book.symbol) {
+                    source.book_prefix(&instrument);
+                }
+            }
+        }
+        Frame::Perps(perps) => {
+            for perp in perps.into_iter().filter(|perp| perp.validate().is_ok()) {
+                if let Some(instrument) = source.instrument(&perp.symbol) {
+                    source.perp_prefix(&instrument);
+                }
+            }
+        }
+        Frame::Error(error) => {
+            let _ = error.to_string();
+        }
+        Frame::Resync { .. } | Frame::Reply(_) | Frame::Event(_) | Frame::Ignored => {}
+    }
+}
@@ -0,0 +1,296 @@
+use chrono::{DateTime, SecondsFormat};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use scraper_core::Side;
+use serde_json::{json, Value};
+use std::io::Write;
+use tokio_tungstenite::tungstenite::Message;
+
+/// Top of book as a mock venue publishes it. `ts` goes wherever the venue
+/// sends an event time.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MockQuote {
+    pub bid: f64,
+    pub bid_vol: f64,
+    pub ask: f64,
+    pub ask_vol: f64,
+    pub ts: u64,
+}
+
+/// One print on a mock venue's tape. `side` is the aggressor's; dialects
+/// whose venue reports the maker's side flip it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MockTrade {
+    pub price: f64,
+    pub size: f64,
+    pub side: Side,
+    pub id: u64,
+    pub ts: u64,
+}
+
+/// Most args Bybit spot takes in one `subscribe` or `unsubscribe`.
+const BYBIT_MAX_ARGS: usize = 10;
+
+/// Bitfinex channel ids handed out on one connection, as `(channel, symbol,
+/// chanId)`.
+pub(crate) type Channels = Vec<(String, String, i64)>;
+
+/// Which venue's protocol a [`crate::MockExchange`] speaks.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Dialect {
+    /// Combined `bookTicker` and `trade` streams, subscribed through the URL.
+    Binance,
+    /// An `info` event on connect, then `subscribed` acks handing out a
+    /// `chanId` per channel and symbol, `[chanId, [...]]` ticker arrays and
+    /// `[chanId, "te", [...]]` trades.
+    Bitfinex,
+    /// `orderbook.1` snapshots and `publicTrade` pushes, and an `op` reply to
+    /// every request that turns down more than 10 args, as Bybit spot does.
+    Bybit,
+    /// Every frame gzipped and binary, quotes as `bbo` and trades as
+    /// `trade.detail` pushes, with
+    /// `{"ping": ts}` heartbeats the client must answer with `{"pong": ts}`.
+    Htx,
+    /// A bullet-public token endpoint, a `welcome` on connect, an `ack` per
+    /// subscription and `/market/ticker` and `/market/match` messages.
+    Kucoin,
+    /// v2 `ticker` and `trade` updates, subscribe acks and `heartbeat`
+    /// channel frames.
+    Kraken,
+    /// `bookTicker.v3` and `deals.v3` pushes, a `{"id", "code": 0, "msg"}` ack per
+    /// `SUBSCRIPTION` and `PING`s the client answers with `PONG`.
+    Mexc,
+}
+
+impl Dialect {
+    /// Path clients connect to; some venues append their own query.
+    pub(crate) fn path(&self) -> &'static str {
+        match self {
+            Dialect::Binance => "/stream",
+            Dialect::Bitfinex => "/ws/2",
+            Dialect::Bybit => "/v5/public/spot",
+            Dialect::Htx => "/ws",
+            Dialect::Kucoin => "/",
+            Dialect::Kraken => "/v2",
+            Dialect::Mexc => "/ws",
+        }
+    }
+
+    /// Sent as soon as a client connects.
+    pub(crate) fn greeting(&self, connect_id: u64) -> Option<Message> {
+        match self {
+            Dialect::Bitfinex => Some(text(json!({"event": "info", "version": 2, "serverId": "mock", "platform": {"status": 1}}))),
+            Dialect::Kucoin => Some(text(json!({"id": connect_id.to_string(), "type": "welcome"}))),
+            _ => None,
+        }
+    }
+
+    /// Whatever the venue answers `subscription` with. Bitfinex channel ids
+    /// are handed out here and recorded in `channels`; they differ from one
+    /// connection to the next, as on the real venue.
+    pub(crate) fn acknowledge(&self, subscription: &str, connect_id: u64, channels: &mut Channels) -> Vec<Message> {
+        let request: Value = serde_json::from_str(subscription).unwrap_or(Value::Null);
+        match self {
+            Dialect::Binance => vec![text(json!({"result": null, "id": request["id"]}))],
+            Dialect::Bitfinex => {
+                let channel = request["channel"].as_str().unwrap_or_default().to_string();
+                let symbol = request["symbol"].as_str().unwrap_or_default().to_string();
+                let chan_id = connect_id as i64 * 1000 + channels.len() as i64;
+                channels.push((channel.clone(), symbol.clone(), chan_id));
+                let pair = symbol.strip_prefix('t').unwrap_or(&symbol).to_string();
+                vec![text(json!({"event": "subscribed", "channel": channel, "chanId": chan_id, "symbol": symbol, "pair": pair}))]
+            }
+            Dialect::Bybit => {
+                let args = request["args"].as_array().map_or(0, Vec::len);
+                let (success, ret_msg) = if args > BYBIT_MAX_ARGS { (false, "args size >10") } else { (true, "") };
+                vec![text(json!({"success": success, "ret_msg": ret_msg, "conn_id": connect_id.to_string(), "op": request["op"]}))]
+            }
+            Dialect::Htx => vec![gzip(json!({"id": request["id"], "status": "ok", "subbed": request["sub"], "ts": 1}))],
+            Dialect::Kucoin => vec![text(json!({"id": request["id"].to_string(), "type": "ack"}))],
+            Dialect::Kraken => request["params"]["symbol"]
+                .as_array()
+                .cloned()
+                .unwrap_or_default()
+                .into_iter()
+                .map(|symbol| {
+                    text(json!({
+                        "method": request["method"],
+                        "result": {"channel": request["params"]["channel"], "symbol": symbol},
+                        "success": true,
+                        "time_in": "2024-01-01T00:00:00.000000Z",
+                        "time_out": "2024-01-01T00:00:00.000001Z"
+                    }))
+                })
+                .collect(),
+            Dialect::Mexc => {
+                let params: Vec<&str> = request["params"].as_array().into_iter().flatten().filter_map(Value::as_str).collect();
+                vec![text(json!({"id": request["id"], "code": 0, "msg": params.join(",")}))]
+            }
+        }
+    }
+
+    /// `quote` for venue-native `symbol`, framed the way the venue pushes it.
+    pub(crate) fn quote(&self, symbol: &str, quote: &MockQuote, channels: &Channels) -> Message {
+        let MockQuote { bid, bid_vol, ask, ask_vol, ts } = *quote;
+        match self {
+            Dialect::Binance => text(json!({
+                "stream": format!("{}@bookTicker", symbol.to_lowercase()),
+                "data": {"u": ts, "s": symbol, "b": bid.to_string(), "B": bid_vol.to_string(), "a": ask.to_string(), "A": ask_vol.to_string()}
+            })),
+            Dialect::Bitfinex => {
+                let chan_id = chan_id(channels, "ticker", symbol);
+                text(json!([chan_id, [bid, bid_vol, ask, ask_vol, 0.0, 0.0, bid, 1000.0, ask, bid]]))
+            }
+            Dialect::Bybit => text(json!({
+                "topic": format!("orderbook.1.{}", symbol),
+                "type": "snapshot",
+                "ts": ts,
+                "data": {"s": symbol, "b": [[bid.to_string(), bid_vol.to_string()]], "a": [[ask.to_string(), ask_vol.to_string()]], "u": ts, "seq": ts}
+            })),
+            Dialect::Htx => gzip(json!({
+                "ch": format!("market.{}.bbo", symbol.to_lowercase()),
+                "ts": ts + 1,
+                "tick": {
+                    "seqId": ts, "ask": ask, "askSize": ask_vol, "bid": bid, "bidSize": bid_vol, "quoteTime": ts,
+                    "symbol": symbol.to_lowercase()
+                }
+            })),
+            Dialect::Kucoin => text(json!({
+                "type": "message",
+                "topic": format!("/market/ticker:{}", symbol),
+                "subject": "trade.ticker",
+                "data": {
+                    "sequence": ts.to_string(), "price": bid.to_string(), "size": "0.1",
+                    "bestAsk": ask.to_string(), "bestAskSize": ask_vol.to_string(),
+                    "bestBid": bid.to_string(), "bestBidSize": bid_vol.to_string(), "Time": ts
+                }
+            })),
+            Dialect::Kraken => text(json!({
+                "channel": "ticker",
+                "type": "update",
+                "data": [{
+                    "symbol": symbol, "bid": bid, "bid_qty": bid_vol, "ask": ask, "ask_qty": ask_vol, "last": bid,
+                    "volume": 1000.0, "vwap": bid, "low": bid, "high": ask, "change": 0.0, "change_pct": 0.0
+                }]
+            })),
+            Dialect::Mexc => text(json!({
+                "channel": format!("spot@public.bookTicker.v3.api@{}", symbol),
+                "publicbookticker": {
+                    "bidprice": bid.to_string(), "bidquantity": bid_vol.to_string(),
+                    "askprice": ask.to_string(), "askquantity": ask_vol.to_string()
+                },
+                "symbol": symbol,
+                "sendtime": ts
+            })),
+        }
+    }
+
+    /// `trade` for venue-native `symbol`, framed the way the venue pushes it.
+    pub(crate) fn trade(&self, symbol: &str, trade: &MockTrade, channels: &Channels) -> Message {
+        let MockTrade { price, size, side, id, ts } = *trade;
+        match self {
+            Dialect::Binance => text(json!({
+                "stream": format!("{}@trade", symbol.to_lowercase()),
+                "data": {
+                    "e": "trade", "E": ts + 1, "s": symbol, "t": id, "p": price.to_string(), "q": size.to_string(),
+                    "T": ts, "m": side == Side::Sell, "M": true
+                }
+            })),
+            Dialect::Bitfinex => {
+                let chan_id = chan_id(channels, "trades", symbol);
+                text(json!([chan_id, "te", [id, ts, size * side.sign(), price]]))
+            }
+            Dialect::Bybit => text(json!({
+                "topic": format!("publicTrade.{}", symbol),
+                "type": "snapshot",
+                "ts": ts + 1,
+                "data": [{
+                    "T": ts, "s": symbol, "S": side, "v": size.to_string(), "p": price.to_string(), "i": id.to_string(), "BT": false
+                }]
+            })),
+            Dialect::Htx => gzip(json!({
+                "ch": format!("market.{}.trade.detail", symbol.to_lowercase()),
+                "ts": ts + 1,
+                "tick": {
+                    "id": id, "ts": ts,
+                    "data": [{"id": id, "ts": ts, "tradeId": id, "amount": size, "price": price, "direction": side}]
+                }
+            })),
+            Dialect::Kucoin => text(json!({
+                "type": "message",
+                "topic": format!("/market/match:{}", symbol),
+                "subject": "trade.l3match",
+                "data": {
+                    "sequence": id.to_string(), "type": "match", "symbol": symbol, "side": side,
+                    "price": price.to_string(), "size": size.to_string(), "tradeId": id.to_string(),
+                    "takerOrderId": "taker", "makerOrderId": "maker", "time": (ts * 1_000_000).to_string()
+                }
+            })),
+            Dialect::Kraken => text(json!({
+                "channel": "trade",
+                "type": "update",
+                "data": [{
+                    "symbol": symbol, "side": side, "price": price, "qty": size, "ord_type": "market", "trade_id": id,
+                    "timestamp": DateTime::from_timestamp_millis(ts as i64)
+                        .expect("mock trade time in range")
+                        .to_rfc3339_opts(SecondsFormat::Micros, true)
+                }]
+            })),
+            Dialect::Mexc => text(json!({
+                "channel": format!("spot@public.deals.v3.api@{}", symbol),
+                "publicdeals": {
+                    "dealsList": [{
+                        "price": price.to_string(), "quantity": size.to_string(),
+                        "tradetype": if side == Side::Buy { 1 } else { 2 }, "time": ts
+                    }],
+                    "eventtype": "spot@public.deals.v3.api"
+                },
+                "symbol": symbol,
+                "sendtime": ts + 1
+            })),
+        }
+    }
+
+    /// The venue's keepalive. Binance and KuCoin, and Bitfinex before any
+    /// subscription, get a WebSocket ping.
+    pub(crate) fn heartbeat(&self, ts: u64, channels: &Channels) -> Message {
+        match (self, channels.first()) {
+            (Dialect::Bitfinex, Some((_, _, chan_id))) => text(json!([chan_id, "hb"])),
+            (Dialect::Htx, _) => gzip(json!({"ping": ts})),
+            (Dialect::Kraken, _) => text(json!({"channel": "heartbeat"})),
+            (Dialect::Mexc, _) => text(json!({"method": "PING"})),
+            _ => Message::Ping(ts.to_string().into_bytes()),
+        }
+    }
+
+    /// A frame that stops short halfway through, framed like a real one.
+    pub(crate) fn malformed(&self) -> Message {
+        let truncated = r#"{"ch":"market.btcusdt.ticker","tick":{"bid":"#;
+        match self {
+            Dialect::Htx => gzip_bytes(truncated.as_bytes()),
+            _ => Message::Text(truncated.to_string()),
+        }
+    }
+}
+
+fn chan_id(channels: &Channels, channel: &str, symbol: &str) -> i64 {
+    channels
+        .iter()
+        .find(|(subscribed, subscribed_symbol, _)| subscribed == channel && subscribed_symbol == symbol)
+        .map(|(_, _, chan_id)| *chan_id)
+        .unwrap_or_else(|| panic!("{} {} was never subscribed", channel, symbol))
+}
+
+fn text(value: Value) -> Message {
+    Message::Text(value.to_string())
+}
+
+fn gzip(value: Value) -> Message {
+    gzip_bytes(value.to_string().as_bytes())
+}
+
+fn gzip_bytes(data: &[u8]) -> Message {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(data).expect("writing to a Vec can't fail");
+    Message::Binary(encoder.finish().expect("writing to a Vec can't fail"))
+}
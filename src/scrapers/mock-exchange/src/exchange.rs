@@ -0,0 +1,317 @@
+use crate::dialect::{Channels, Dialect, MockQuote, MockTrade};
+use futures_util::{SinkExt, StreamExt};
+use serde_json::json;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio_tungstenite::tungstenite::handshake::server::{Request, Response};
+use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+use tokio_tungstenite::tungstenite::protocol::CloseFrame;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::WebSocketStream;
+
+/// How long the mock waits on the client before failing the test.
+pub(crate) const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Ways a [`Session`] can end badly.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Fault {
+    /// Drops the TCP connection without a close frame.
+    Disconnect,
+    /// A clean close frame from the server's side.
+    Close,
+    /// A frame with a reserved opcode, which no client may accept, then
+    /// the connection is dropped.
+    ProtocolError,
+}
+
+/// Response bodies by request path, without the query.
+type Routes = Arc<Mutex<HashMap<String, String>>>;
+
+/// A local WebSocket server speaking one venue's [`Dialect`]. Every client
+/// connection becomes a [`Session`] the test drives by hand. Beside it runs a
+/// plain HTTP server for the venue's REST API, answering whatever the test
+/// sets up with [`MockExchange::respond`].
+pub struct MockExchange {
+    dialect: Dialect,
+    address: SocketAddr,
+    rest_api: SocketAddr,
+    routes: Routes,
+    /// Path and query of every REST request so far.
+    requests: Arc<Mutex<Vec<String>>>,
+    sessions: mpsc::UnboundedReceiver<Session>,
+    connections: Arc<AtomicU64>,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl MockExchange {
+    pub async fn start(dialect: Dialect) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind mock exchange");
+        let address = listener.local_addr().expect("mock exchange address");
+        let (accepted, sessions) = mpsc::unbounded_channel();
+        let connections = Arc::new(AtomicU64::new(0));
+        let counter = connections.clone();
+        let mut tasks = vec![tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let connect_id = counter.fetch_add(1, Ordering::SeqCst) + 1;
+                if let Some(session) = Session::accept(dialect, stream, connect_id).await {
+                    let _ = accepted.send(session);
+                }
+            }
+        })];
+        let routes = Routes::default();
+        let requests = Arc::default();
+        let (rest_api, task) = serve_rest_api(routes.clone(), Arc::clone(&requests)).await;
+        tasks.push(task);
+        let exchange = MockExchange { dialect, address, rest_api, routes, requests, sessions, connections, tasks };
+        if dialect == Dialect::Kucoin {
+            let body = json!({
+                "code": "200000",
+                "data": {
+                    "token": "mock-token",
+                    "instanceServers": [{
+                        "endpoint": exchange.endpoint(),
+                        "encrypt": false,
+                        "protocol": "websocket",
+                        "pingInterval": 18000,
+                        "pingTimeout": 10000
+                    }]
+                }
+            });
+            exchange.respond("/api/v1/bullet-public", &body.to_string());
+        }
+        exchange
+    }
+
+    /// WebSocket URL to point a source's `with_endpoint` at.
+    pub fn endpoint(&self) -> String {
+        format!("ws://{}{}", self.address, self.dialect.path())
+    }
+
+    /// Base URL of the REST server, e.g. for Binance's `with_rest_api`.
+    pub fn rest_api(&self) -> String {
+        format!("http://{}", self.rest_api)
+    }
+
+    /// KuCoin's bullet-public, handing out this server as the instance
+    /// server. Only served by [`Dialect::Kucoin`].
+    pub fn token_api(&self) -> String {
+        assert_eq!(self.dialect, Dialect::Kucoin, "only the KuCoin dialect serves a token API");
+        format!("{}/api/v1/bullet-public", self.rest_api())
+    }
+
+    /// Answers every later request for `path`, whatever its query or method,
+    /// with `body` as JSON. Paths nothing was set up for get a 404.
+    pub fn respond(&self, path: &str, body: &str) {
+        self.routes.lock().unwrap().insert(path.to_string(), body.to_string());
+    }
+
+    /// Path and query of every REST request answered so far.
+    pub fn requests(&self) -> Vec<String> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    /// Waits until at least `count` REST requests have been answered.
+    pub async fn wait_for_requests(&self, count: usize) -> Vec<String> {
+        let started = Instant::now();
+        loop {
+            let requests = self.requests();
+            if requests.len() >= count {
+                return requests;
+            }
+            if started.elapsed() >= TIMEOUT {
+                panic!("got {} of {} REST requests: {:?}", requests.len(), count, requests);
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    }
+
+    /// WebSocket connections accepted so far.
+    pub fn connections(&self) -> u64 {
+        self.connections.load(Ordering::SeqCst)
+    }
+
+    /// The next client to connect, once its greeting has been sent.
+    pub async fn session(&mut self) -> Session {
+        tokio::time::timeout(TIMEOUT, self.sessions.recv())
+            .await
+            .expect("no client connected to the mock exchange")
+            .expect("mock exchange stopped accepting")
+    }
+}
+
+impl Drop for MockExchange {
+    fn drop(&mut self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
+}
+
+/// One client connection.
+pub struct Session {
+    dialect: Dialect,
+    socket: WebSocketStream<TcpStream>,
+    /// Path and query the client connected to, e.g.
+    /// `/stream?streams=btcusdt@bookTicker/btcusdt@trade`.
+    pub path: String,
+    connect_id: u64,
+    /// Bitfinex channel ids handed out so far, by channel and symbol.
+    channels: Channels,
+}
+
+impl Session {
+    // The callback's error type is tungstenite's
+    #[allow(clippy::result_large_err)]
+    async fn accept(dialect: Dialect, stream: TcpStream, connect_id: u64) -> Option<Self> {
+        let mut path = String::new();
+        let callback = |request: &Request, response: Response| {
+            path = request.uri().to_string();
+            Ok(response)
+        };
+        let socket = tokio_tungstenite::accept_hdr_async(stream, callback).await.ok()?;
+        let mut session = Session { dialect, socket, path, connect_id, channels: Vec::new() };
+        if let Some(greeting) = dialect.greeting(connect_id) {
+            session.send(greeting).await;
+        }
+        Some(session)
+    }
+
+    async fn send(&mut self, message: Message) {
+        self.socket.send(message).await.expect("send to client");
+    }
+
+    /// The next text frame from the client, answering WebSocket pings on the
+    /// way. `None` once the client closes.
+    pub async fn recv_text(&mut self) -> Option<String> {
+        tokio::time::timeout(TIMEOUT, async {
+            while let Some(message) = self.socket.next().await {
+                match message {
+                    Ok(Message::Text(text)) => return Some(text),
+                    Ok(Message::Close(_)) | Err(_) => return None,
+                    Ok(_) => {}
+                }
+            }
+            None
+        })
+        .await
+        .expect("client sent nothing")
+    }
+
+    /// Reads `count` subscription requests and acknowledges each the way
+    /// the venue does. Returns the requests.
+    pub async fn expect_subscriptions(&mut self, count: usize) -> Vec<String> {
+        let mut subscriptions = Vec::new();
+        for _ in 0..count {
+            let subscription = self.recv_text().await.expect("client closed before subscribing");
+            for ack in self.dialect.acknowledge(&subscription, self.connect_id, &mut self.channels) {
+                self.send(ack).await;
+            }
+            subscriptions.push(subscription);
+        }
+        subscriptions
+    }
+
+    pub async fn send_quote(&mut self, symbol: &str, quote: MockQuote) {
+        let frame = self.dialect.quote(symbol, &quote, &self.channels);
+        self.send(frame).await;
+    }
+
+    pub async fn send_trade(&mut self, symbol: &str, trade: MockTrade) {
+        let frame = self.dialect.trade(symbol, &trade, &self.channels);
+        self.send(frame).await;
+    }
+
+    /// The venue's keepalive; for HTX and MEXC the client's answer comes back
+    /// through [`Session::recv_text`].
+    pub async fn send_heartbeat(&mut self, ts: u64) {
+        let frame = self.dialect.heartbeat(ts, &self.channels);
+        self.send(frame).await;
+    }
+
+    /// A frame cut off halfway, gzipped for HTX.
+    pub async fn send_malformed(&mut self) {
+        let frame = self.dialect.malformed();
+        self.send(frame).await;
+    }
+
+    pub async fn send_text(&mut self, text: &str) {
+        self.send(Message::Text(text.to_string())).await;
+    }
+
+    /// Ends the session with `fault`.
+    pub async fn fail(mut self, fault: Fault) {
+        match fault {
+            Fault::Disconnect => {}
+            Fault::Close => {
+                let close = CloseFrame { code: CloseCode::Away, reason: "mock exchange going away".into() };
+                let _ = self.socket.close(Some(close)).await;
+            }
+            Fault::ProtocolError => {
+                // FIN with reserved opcode 0x3 and an empty payload
+                let _ = self.socket.get_mut().write_all(&[0x83, 0x00]).await;
+                let _ = self.socket.get_mut().flush().await;
+            }
+        }
+    }
+
+    /// Waits for the client to close the connection, as on a graceful
+    /// shutdown, answering its close frame. Returns the text frames it sent
+    /// first, e.g. unsubscribes.
+    pub async fn expect_close(mut self) -> Vec<String> {
+        let mut sent = Vec::new();
+        while let Some(text) = self.recv_text().await {
+            sent.push(text);
+        }
+        // tungstenite queued the close reply while reading; this flushes it
+        let _ = self.socket.flush().await;
+        sent
+    }
+}
+
+/// Serves `routes` over HTTP on a port of its own, recording each request's
+/// path and query in `requests` once it has been answered.
+async fn serve_rest_api(routes: Routes, requests: Arc<Mutex<Vec<String>>>) -> (SocketAddr, JoinHandle<()>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind mock REST API");
+    let address = listener.local_addr().expect("mock REST API address");
+    let task = tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            let routes = routes.clone();
+            let requests = requests.clone();
+            tokio::spawn(async move {
+                // Requests have no body, so the headers are all there is to read
+                let mut request = Vec::new();
+                let mut buffer = [0u8; 1024];
+                while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+                    match stream.read(&mut buffer).await {
+                        Ok(0) | Err(_) => return,
+                        Ok(read) => request.extend_from_slice(&buffer[..read]),
+                    }
+                }
+                // e.g. `GET /api/v3/depth?symbol=BTCUSDT&limit=1000 HTTP/1.1`
+                let request = String::from_utf8_lossy(&request);
+                let target = request.split_whitespace().nth(1).unwrap_or_default().to_string();
+                let path = target.split('?').next().unwrap_or_default();
+                let body = routes.lock().unwrap().get(path).cloned();
+                let response = match body {
+                    Some(body) => format!(
+                        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        body.len(),
+                        body
+                    ),
+                    None => "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string(),
+                };
+                let _ = stream.write_all(response.as_bytes()).await;
+                let _ = stream.shutdown().await;
+                requests.lock().unwrap().push(target);
+            });
+        }
+    });
+    (address, task)
+}
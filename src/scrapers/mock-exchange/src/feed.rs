@@ -0,0 +1,51 @@
+use crate::exchange::TIMEOUT;
+use crate::redis::MockRedis;
+use scraper_core::{FeedError, QuoteSource, RedisSink, RunOptions, Shutdown, ShutdownTrigger};
+use std::env;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::task::JoinHandle;
+
+/// Gives every feed in the test binary a spool of its own.
+static NEXT_FEED: AtomicU64 = AtomicU64::new(0);
+
+/// A source running under [`scraper_core::run`] on its own task, as the
+/// scraper binaries run it.
+pub struct MockFeed {
+    shutdown: ShutdownTrigger,
+    task: JoinHandle<Result<(), FeedError>>,
+}
+
+/// Runs `source` against `redis` until [`MockFeed::stop`].
+pub async fn spawn_feed<S: QuoteSource + Send + 'static>(source: S, redis: &MockRedis) -> MockFeed {
+    spawn_feed_with(source, redis, RunOptions::default()).await
+}
+
+/// [`spawn_feed`] with `options`, e.g. a short staleness window.
+pub async fn spawn_feed_with<S: QuoteSource + Send + 'static>(mut source: S, redis: &MockRedis, options: RunOptions) -> MockFeed {
+    let spool_dir = env::temp_dir()
+        .join("mock-exchange-spool")
+        .join(format!("{}-{}", std::process::id(), NEXT_FEED.fetch_add(1, Ordering::Relaxed)));
+    let sink = RedisSink::connect(redis.url()).await.expect("mock Redis URL").with_spool_dir(spool_dir);
+    let (trigger, shutdown) = Shutdown::manual();
+    let task = tokio::spawn(async move { scraper_core::run(&mut source, sink, options, shutdown).await });
+    MockFeed { shutdown: trigger, task }
+}
+
+impl MockFeed {
+    /// Shuts the feed down gracefully and returns what [`scraper_core::run`]
+    /// did.
+    pub async fn stop(self) -> Result<(), FeedError> {
+        self.shutdown.trip();
+        self.task.await.expect("feed panicked")
+    }
+
+    /// Waits for the feed to stop by itself, as it does on an error with
+    /// [`scraper_core::Policy::Abort`], and returns what
+    /// [`scraper_core::run`] did.
+    pub async fn stopped(self) -> Result<(), FeedError> {
+        tokio::time::timeout(TIMEOUT, self.task)
+            .await
+            .expect("feed kept running")
+            .expect("feed panicked")
+    }
+}
@@ -0,0 +1,136 @@
+use scraper_core::{Frame, QuoteSource};
+use serde_json::{json, Value};
+use std::fs;
+use std::path::Path;
+
+/// The frames of a fixture file, one per line, in the order the venue sent
+/// them.
+pub fn fixture_frames(path: &Path) -> Vec<String> {
+    fs::read_to_string(path)
+        .unwrap_or_else(|e| panic!("can't read fixture {}: {}", path.display(), e))
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Parses `frames` in order on one source, as a connection would.
+pub fn parse_frames<S: QuoteSource>(source: &mut S, frames: &[String]) -> Vec<Frame> {
+    frames.iter().map(|frame| source.parse(frame)).collect()
+}
+
+/// Feeds every [`mangle`]d version of every frame to a fresh source that has
+/// already seen the frames before it, then checks any quotes, ticker stats,
+/// trades, books and perpetual figures and maps them to instruments and keys, or formats the
+/// error, the way the runner does. Fails the test if anything panics.
+pub fn parse_mangled<S: QuoteSource>(new_source: impl Fn() -> S, frames: &[String]) {
+    for (position, frame) in frames.iter().enumerate() {
+        for mangled in mangle(frame) {
+            let mut source = new_source();
+            parse_frames(&mut source, &frames[..position]);
+            match source.parse(&mangled) {
+                Frame::Quotes(quotes) => {
+                    for quote in quotes.into_iter().filter(|quote| quote.validate().is_ok()) {
+                        if let Some(instrument) = source.instrument(&quote.symbol) {
+                            source.key_prefix(&instrument);
+                        }
+                    }
+                }
+                Frame::Stats(tickers) => {
+                    for ticker in tickers.into_iter().filter(|ticker| ticker.validate().is_ok()) {
+                        if let Some(instrument) = source.instrument(&ticker.symbol) {
+                            source.stats_prefix(&instrument);
+                        }
+                    }
+                }
+                Frame::Trades(trades) => {
+                    for trade in trades.into_iter().filter(|trade| trade.validate().is_ok()) {
+                        if let Some(instrument) = source.instrument(&trade.symbol) {
+                            source.trade_prefix(&instrument);
+                        }
+                    }
+                }
+                Frame::Books(books) => {
+                    for book in books.into_iter().filter(|book| book.validate().is_ok()) {
+                        if let Some(instrument) = source.instrument(&book.symbol) {
+                            source.book_prefix(&instrument);
+                        }
+                    }
+                }
+                Frame::Perps(perps) => {
+                    for perp in perps.into_iter().filter(|perp| perp.validate().is_ok()) {
+                        if let Some(instrument) = source.instrument(&perp.symbol) {
+                            source.perp_prefix(&instrument);
+                        }
+                    }
+                }
+                Frame::Error(error) => {
+                    let _ = error.to_string();
+                }
+                Frame::Resync { .. } | Frame::Reply(_) | Frame::Event(_) | Frame::Ignored => {}
+            }
+        }
+    }
+}
+
+/// What a venue that changed or broke its format might send instead of
+/// `frame`: every prefix of it, and for every field and array element, the
+/// frame without it or with it swapped for a value of the wrong type, an
+/// empty string, an unparseable number or an extreme one.
+pub fn mangle(frame: &str) -> Vec<String> {
+    let mut mangled: Vec<String> =
+        frame.char_indices().map(|(end, _)| frame[..end].to_string()).collect();
+    if let Ok(value) = serde_json::from_str::<Value>(frame) {
+        mangled.extend(variants(&value).iter().map(Value::to_string));
+    }
+    mangled
+}
+
+fn replacements() -> Vec<Value> {
+    vec![
+        Value::Null,
+        json!(true),
+        json!(""),
+        json!("not a number"),
+        json!("NaN"),
+        json!("-1e999"),
+        json!(-1),
+        json!(u64::MAX),
+        json!(1e308),
+        json!([]),
+        json!({}),
+    ]
+}
+
+/// `value` with one node, itself included, dropped or replaced.
+fn variants(value: &Value) -> Vec<Value> {
+    let mut mangled = replacements();
+    match value {
+        Value::Object(fields) => {
+            for (key, field) in fields {
+                let mut dropped = fields.clone();
+                dropped.remove(key);
+                mangled.push(Value::Object(dropped));
+                for variant in variants(field) {
+                    let mut replaced = fields.clone();
+                    replaced.insert(key.clone(), variant);
+                    mangled.push(Value::Object(replaced));
+                }
+            }
+        }
+        Value::Array(items) => {
+            for (index, item) in items.iter().enumerate() {
+                let mut dropped = items.clone();
+                dropped.remove(index);
+                mangled.push(Value::Array(dropped));
+                for variant in variants(item) {
+                    let mut replaced = items.clone();
+                    replaced[index] = variant;
+                    mangled.push(Value::Array(replaced));
+                }
+            }
+        }
+        _ => {}
+    }
+    mangled
+}
@@ -0,0 +1,18 @@
+//! A local stand-in for the exchanges and for Redis, so a scraper can be run
+//! end to end in a test without the network. [`MockExchange`] speaks one
+//! venue's WebSocket [`Dialect`] and lets the test push quotes, trades,
+//! heartbeats, malformed frames and [`Fault`]s at the client; [`MockRedis`]
+//! records what the scraper writes. The [`fixture_frames`] helpers drive a parser
+//! directly from captured frames instead.
+
+mod dialect;
+mod exchange;
+mod feed;
+mod fixtures;
+mod redis;
+
+pub use dialect::{Dialect, MockQuote, MockTrade};
+pub use exchange::{Fault, MockExchange, Session};
+pub use feed::{spawn_feed, spawn_feed_with, MockFeed};
+pub use fixtures::{fixture_frames, mangle, parse_frames, parse_mangled};
+pub use redis::MockRedis;
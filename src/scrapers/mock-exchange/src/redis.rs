@@ -0,0 +1,199 @@
+use crate::exchange::TIMEOUT;
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+type Commands = Arc<Mutex<Vec<Vec<String>>>>;
+type Keys = Arc<Mutex<HashSet<String>>>;
+
+/// What Redis answers a sample written to a key holding another type.
+const WRONGTYPE: &str = "-WRONGTYPE Operation against a key holding the wrong kind of value\r\n";
+
+/// Just enough of a Redis server for [`scraper_core::RedisSink`]: accepts any
+/// command, answers `+OK`, or a timestamp per sample added, and remembers it,
+/// so a test can check what the scraper wrote. [`Self::stop`] and
+/// [`Self::restart`] stage an outage, [`Self::reject`] a bad key.
+pub struct MockRedis {
+    url: String,
+    commands: Commands,
+    rejected: Keys,
+    /// Set while stopped: new connections are closed as soon as they're accepted.
+    down: Arc<AtomicBool>,
+    connections: Arc<Mutex<Vec<JoinHandle<()>>>>,
+    task: JoinHandle<()>,
+}
+
+impl MockRedis {
+    pub async fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind mock Redis");
+        let url = format!("redis://{}", listener.local_addr().expect("mock Redis address"));
+        let commands = Commands::default();
+        let rejected = Keys::default();
+        let down = Arc::new(AtomicBool::new(false));
+        let connections: Arc<Mutex<Vec<JoinHandle<()>>>> = Arc::default();
+        let (recorded, rejecting, refusing, served) = (commands.clone(), rejected.clone(), down.clone(), connections.clone());
+        let task = tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                if refusing.load(Ordering::SeqCst) {
+                    continue;
+                }
+                let connection = tokio::spawn(serve(stream, recorded.clone(), rejecting.clone()));
+                served.lock().expect("mock Redis lock poisoned").push(connection);
+            }
+        });
+        MockRedis { url, commands, rejected, down, connections, task }
+    }
+
+    /// Drops every open connection and closes new ones until [`Self::restart`],
+    /// as a Redis that went away would. Nothing sent meanwhile is recorded.
+    pub fn stop(&self) {
+        self.down.store(true, Ordering::SeqCst);
+        for connection in self.connections.lock().expect("mock Redis lock poisoned").drain(..) {
+            connection.abort();
+        }
+    }
+
+    /// Takes connections again after [`Self::stop`].
+    pub fn restart(&self) {
+        self.down.store(false, Ordering::SeqCst);
+    }
+
+    /// Turns down every sample written to `key` from now on, as Redis does
+    /// when the key holds something other than a time series. The rest of a
+    /// `TS.MADD` holding one is still answered with its timestamps.
+    pub fn reject(&self, key: &str) {
+        self.rejected.lock().expect("mock Redis lock poisoned").insert(key.to_string());
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Every command received so far, pipelined ones included.
+    pub fn commands(&self) -> Vec<Vec<String>> {
+        self.commands.lock().expect("mock Redis lock poisoned").clone()
+    }
+
+    /// `(timestamp, value)` of every sample written to `key`, by `TS.ADD` or
+    /// `TS.MADD`, oldest first.
+    pub fn samples(&self, key: &str) -> Vec<(u64, f64)> {
+        let mut samples = Vec::new();
+        for command in self.commands() {
+            let triples = match command.first().map(|name| name.to_uppercase()).as_deref() {
+                Some("TS.MADD") => &command[1..],
+                Some("TS.ADD") => &command[1..command.len().min(4)],
+                _ => continue,
+            };
+            for triple in triples.chunks_exact(3) {
+                if triple[0] == key {
+                    if let (Ok(ts), Ok(value)) = (triple[1].parse(), triple[2].parse()) {
+                        samples.push((ts, value));
+                    }
+                }
+            }
+        }
+        samples
+    }
+
+    /// Waits until `key` holds at least `count` samples and returns them.
+    /// Panics with every command received if that takes too long.
+    pub async fn wait_for_samples(&self, key: &str, count: usize) -> Vec<(u64, f64)> {
+        let started = Instant::now();
+        loop {
+            let samples = self.samples(key);
+            if samples.len() >= count {
+                return samples;
+            }
+            if started.elapsed() >= TIMEOUT {
+                panic!("{} got {} of {} samples, commands: {:?}", key, samples.len(), count, self.commands());
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    }
+}
+
+impl Drop for MockRedis {
+    fn drop(&mut self) {
+        self.stop();
+        self.task.abort();
+    }
+}
+
+async fn serve(mut stream: TcpStream, commands: Commands, rejected: Keys) {
+    let mut buffer = Vec::new();
+    let mut chunk = [0u8; 16 * 1024];
+    loop {
+        match stream.read(&mut chunk).await {
+            Ok(0) | Err(_) => return,
+            Ok(read) => buffer.extend_from_slice(&chunk[..read]),
+        }
+        let mut replies = Vec::new();
+        while let Some((command, used)) = parse_command(&buffer) {
+            buffer.drain(..used);
+            replies.extend_from_slice(reply(&command, &rejected).as_bytes());
+            commands.lock().expect("mock Redis lock poisoned").push(command);
+        }
+        if stream.write_all(&replies).await.is_err() {
+            return;
+        }
+    }
+}
+
+/// `TS.MADD` gets an array holding each sample's timestamp, or an error for
+/// a rejected key, and `TS.ADD` the same for its one sample.
+fn reply(command: &[String], rejected: &Keys) -> String {
+    let rejected = rejected.lock().expect("mock Redis lock poisoned");
+    let added = |triple: &[String]| {
+        if rejected.contains(&triple[0]) {
+            WRONGTYPE.to_string()
+        } else {
+            format!(":{}\r\n", triple[1])
+        }
+    };
+    match command.first().map(|name| name.to_uppercase()).as_deref() {
+        Some("PING") => "+PONG\r\n".to_string(),
+        Some("TS.MADD") => {
+            let triples: Vec<&[String]> = command[1..].chunks_exact(3).collect();
+            let mut reply = format!("*{}\r\n", triples.len());
+            for triple in triples {
+                reply.push_str(&added(triple));
+            }
+            reply
+        }
+        Some("TS.ADD") if command.len() >= 4 => added(&command[1..4]),
+        _ => "+OK\r\n".to_string(),
+    }
+}
+
+/// One RESP array of bulk strings from the front of `buffer` and the bytes it
+/// took, or `None` until all of it has arrived.
+fn parse_command(buffer: &[u8]) -> Option<(Vec<String>, usize)> {
+    let (count, mut position) = parse_header(buffer, b'*')?;
+    let mut command = Vec::with_capacity(count);
+    for _ in 0..count {
+        let (length, start) = parse_header(&buffer[position..], b'$')?;
+        let start = position + start;
+        let end = start + length;
+        if buffer.len() < end + 2 {
+            return None;
+        }
+        command.push(String::from_utf8_lossy(&buffer[start..end]).into_owned());
+        position = end + 2;
+    }
+    Some((command, position))
+}
+
+/// The number after `prefix` on the first line of `buffer`, and where the
+/// next line starts.
+fn parse_header(buffer: &[u8], prefix: u8) -> Option<(usize, usize)> {
+    if buffer.first() != Some(&prefix) {
+        return None;
+    }
+    let end = buffer.windows(2).position(|window| window == b"\r\n")?;
+    let number = std::str::from_utf8(&buffer[1..end]).ok()?.parse().ok()?;
+    Some((number, end + 2))
+}
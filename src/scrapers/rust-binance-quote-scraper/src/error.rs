@@ -0,0 +1,47 @@
+use scraper_core::FeedError;
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug)]
+pub enum BinanceError {
+    /// A book snapshot couldn't be fetched over REST. Only its symbol's book
+    /// is resynced; the socket stays up.
+    Snapshot(reqwest::Error),
+    /// Neither a stream event nor a reply to one of our requests, or an
+    /// event not shaped like its stream's.
+    Malformed(serde_json::Error),
+    /// An event from a stream we never subscribe to.
+    UnexpectedStream(String),
+    /// Binance answered a request with an error, e.g. for an unknown stream.
+    Rejected { code: i64, msg: String },
+}
+
+impl fmt::Display for BinanceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BinanceError::Snapshot(e) => write!(f, "can't fetch a book snapshot: {}", e),
+            BinanceError::Malformed(e) => write!(f, "malformed message: {}", e),
+            BinanceError::UnexpectedStream(stream) => write!(f, "unexpected stream {}", stream),
+            BinanceError::Rejected { code, msg } => write!(f, "Binance rejected the request: {} (code {})", msg, code),
+        }
+    }
+}
+
+impl Error for BinanceError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            BinanceError::Snapshot(e) => Some(e),
+            BinanceError::Malformed(e) => Some(e),
+            BinanceError::UnexpectedStream(_) | BinanceError::Rejected { .. } => None,
+        }
+    }
+}
+
+impl From<BinanceError> for FeedError {
+    fn from(error: BinanceError) -> Self {
+        match error {
+            BinanceError::Snapshot(_) | BinanceError::Malformed(_) | BinanceError::UnexpectedStream(_) => FeedError::decode(error),
+            BinanceError::Rejected { .. } => FeedError::subscribe(error),
+        }
+    }
+}
@@ -0,0 +1,417 @@
+mod error;
+
+pub use error::BinanceError;
+
+use scraper_core::{
+    de_float_from_str, BookLevel, FeedError, Frame, Instrument, InstrumentType, OrderBook, PerpStats, Quote, QuoteSource,
+    Side, Trade,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::sync::oneshot;
+
+#[derive(Serialize, Deserialize, Debug)]
+struct BinanceMessageQuote {
+    u: i64, // 42572951956 Order ID
+    s: String, // BTCUSDT
+    #[serde(deserialize_with = "de_float_from_str")]
+    b: f64, // 43244.39000000 bid price
+    #[serde(rename = "B", deserialize_with = "de_float_from_str")]
+    _b: f64, // 0.02441000 bid vol
+    #[serde(deserialize_with = "de_float_from_str")]
+    a: f64, // 43244.39000000 ask price
+    #[serde(rename = "A", deserialize_with = "de_float_from_str")]
+    _a: f64, // 0.02441000 ask vol
+    #[serde(rename = "T")]
+    _t: Option<u64> // 1704240590512 transaction time, USDⓈ-M only
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct BinanceMessageTrade {
+    s: String, // BTCUSDT
+    t: u64, // 3340234715 Trade ID
+    #[serde(deserialize_with = "de_float_from_str")]
+    p: f64, // 43244.39000000 price
+    #[serde(deserialize_with = "de_float_from_str")]
+    q: f64, // 0.00120000 quantity
+    #[serde(rename = "T")]
+    _t: u64, // 1704240590512 trade time
+    m: bool // buyer is the maker, so the seller took liquidity
+}
+
+/// A USDⓈ-M `@markPrice@1s` event.
+#[derive(Serialize, Deserialize, Debug)]
+struct BinanceMarkPrice {
+    #[serde(rename = "E")]
+    _e: u64, // 1704240590000 event time
+    s: String, // BTCUSDT
+    #[serde(deserialize_with = "de_float_from_str")]
+    p: f64, // 43251.20000000 mark price
+    #[serde(deserialize_with = "de_float_from_str")]
+    i: f64, // 43240.81574468 index price
+    #[serde(deserialize_with = "de_float_from_str")]
+    r: f64, // 0.00010000 funding rate at the next funding
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct BinanceDepthUpdate {
+    #[serde(rename = "E")]
+    _e: u64, // 1704240590512 event time
+    s: String, // BTCUSDT
+    #[serde(rename = "U")]
+    _u: u64, // 157 first update ID in event
+    u: u64, // 160 final update ID in event
+    b: Vec<(String, String)>, // [["43244.39000000", "0.02441000"]] bids to update, size 0 removes
+    a: Vec<(String, String)>, // [["43244.40000000", "0"]] asks to update
+}
+
+/// `GET /api/v3/depth`.
+#[derive(Serialize, Deserialize, Debug)]
+struct BinanceDepthSnapshot {
+    #[serde(rename = "lastUpdateId")]
+    last_update_id: u64,
+    bids: Vec<(String, String)>,
+    asks: Vec<(String, String)>,
+}
+
+/// A combined stream event; `data` is parsed once `stream` says which stream
+/// it's from.
+#[derive(Serialize, Deserialize, Debug)]
+struct BinanceCombinedMessage {
+    stream: String, // btcusdt@bookTicker
+    data: Value
+}
+
+/// Answer to a request we sent, e.g. `{"result":null,"id":1}`.
+#[derive(Serialize, Deserialize, Debug)]
+struct BinanceReply {
+    id: u64,
+    error: Option<BinanceApiError>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct BinanceApiError {
+    code: i64,
+    msg: String,
+}
+
+const BINANCE_WS_API: &str = "wss://stream.binance.com:9443/stream";
+const BINANCE_REST_API: &str = "https://api.binance.com";
+const BINANCE_USD_M_WS_API: &str = "wss://fstream.binance.com/stream";
+
+/// Levels asked for in a REST snapshot, the most Binance hands out.
+const SNAPSHOT_LEVELS: usize = 1000;
+
+/// How long to wait before fetching again after a snapshot that failed or
+/// didn't make a usable book, so a struggling REST API isn't hammered.
+const SNAPSHOT_RETRY: Duration = Duration::from_secs(5);
+
+/// Diffs kept per symbol while a snapshot is fetched. Older ones are dropped:
+/// a snapshot that arrives after them doesn't need them, and one that doesn't
+/// shows up as a gap and is fetched again.
+const MAX_BUFFERED: usize = 1000;
+
+const DEFAULT_SYMBOL: &str = "BTCUSDT";
+
+/// Binance's books start from a REST snapshot, which can only be lined up with
+/// the diffs once it arrives.
+enum BinanceBook {
+    /// The snapshot is being fetched on a task of its own; diffs wait in
+    /// `buffered` until it's in.
+    Fetching {
+        snapshot: oneshot::Receiver<Result<BinanceDepthSnapshot, BinanceError>>,
+        buffered: Vec<BinanceDepthUpdate>,
+    },
+    /// In step with the stream up to update ID `last`.
+    Synced { book: OrderBook, last: u64 },
+}
+
+pub struct BinanceSource {
+    endpoint: String,
+    rest_api: String,
+    /// Shared by every snapshot fetch, so they reuse its connections.
+    client: reqwest::Client,
+    symbols: Vec<String>,
+    /// USDⓈ-M perpetuals rather than spot.
+    usd_m: bool,
+    /// Levels per side written from each local book; `None` leaves books off.
+    book_levels: Option<usize>,
+    /// Books fetching or kept, by symbol as Binance writes it.
+    books: HashMap<String, BinanceBook>,
+}
+
+impl BinanceSource {
+    pub fn new(symbols: Vec<String>) -> Self {
+        BinanceSource {
+            endpoint: BINANCE_WS_API.to_string(),
+            rest_api: BINANCE_REST_API.to_string(),
+            client: reqwest::Client::new(),
+            symbols,
+            usd_m: false,
+            book_levels: None,
+            books: HashMap::new(),
+        }
+    }
+
+    /// Also keeps a local book per symbol, from a REST snapshot and the
+    /// `@depth@100ms` diffs after it, and writes its best `levels` on each side.
+    pub fn with_book(mut self, levels: usize) -> Self {
+        self.book_levels = Some(levels);
+        self
+    }
+
+    /// Takes the symbols as USDⓈ-M perpetuals from `fstream.binance.com`
+    /// instead of spot, written as `BINANCE_USDM`: `@bookTicker` quotes and
+    /// `@markPrice@1s` funding, mark and index prices. Books are spot only.
+    /// Call it before [`Self::with_endpoint`].
+    pub fn usd_m(mut self) -> Self {
+        self.endpoint = BINANCE_USD_M_WS_API.to_string();
+        self.usd_m = true;
+        self
+    }
+
+    /// Fetches book snapshots from `rest_api` instead of `api.binance.com`,
+    /// e.g. a mock exchange in tests.
+    pub fn with_rest_api(mut self, rest_api: impl Into<String>) -> Self {
+        self.rest_api = rest_api.into();
+        self
+    }
+
+    /// Connects to `endpoint` instead of `stream.binance.com`, e.g. a mock exchange in tests.
+    pub fn with_endpoint(mut self, endpoint: impl Into<String>) -> Self {
+        self.endpoint = endpoint.into();
+        self
+    }
+
+    fn streams(&self) -> Vec<String> {
+        self.symbols
+            .iter()
+            .flat_map(|symbol| {
+                let symbol = symbol.to_lowercase();
+                if self.usd_m {
+                    return vec![format!("{}@bookTicker", symbol), format!("{}@markPrice@1s", symbol)];
+                }
+                let book = self.book_levels.map(|_| format!("{}@depth@100ms", symbol));
+                [format!("{}@bookTicker", symbol), format!("{}@trade", symbol)].into_iter().chain(book).collect()
+            })
+            .collect()
+    }
+
+    /// Starts fetching a snapshot of `symbol`'s book after `delay`, to be
+    /// lined up with `buffered` once it arrives.
+    fn fetch(&self, symbol: &str, buffered: Vec<BinanceDepthUpdate>, delay: Duration) -> BinanceBook {
+        let url = format!("{}/api/v3/depth?symbol={}&limit={}", self.rest_api, symbol, SNAPSHOT_LEVELS);
+        let client = self.client.clone();
+        let (sender, snapshot) = oneshot::channel();
+        tokio::spawn(async move {
+            tokio::time::sleep(delay).await;
+            let fetched = match client.get(url).send().await.and_then(|response| response.error_for_status()) {
+                Ok(response) => response.json().await,
+                Err(e) => Err(e),
+            };
+            // The source may have reconnected and dropped the receiver meanwhile
+            let _ = sender.send(fetched.map_err(BinanceError::Snapshot));
+        });
+        BinanceBook::Fetching { snapshot, buffered }
+    }
+
+    /// Throws `symbol`'s book away and fetches a fresh snapshot after
+    /// `delay`, keeping `buffered` for it.
+    fn resync(&mut self, symbol: String, buffered: Vec<BinanceDepthUpdate>, reason: String, delay: Duration) -> Frame {
+        let fetching = self.fetch(&symbol, buffered, delay);
+        self.books.insert(symbol.clone(), fetching);
+        // The snapshot comes over REST, so there's nothing to ask the stream for
+        Frame::Resync { symbol, reason, messages: Vec::new() }
+    }
+
+    /// A failed snapshot only resyncs its own symbol, so quotes and trades
+    /// carry on over the socket while the book waits to be fetched again.
+    fn parse_book(&mut self, update: BinanceDepthUpdate, levels: usize) -> Frame {
+        let symbol = update.s.clone();
+        let state = match self.books.remove(&symbol) {
+            Some(state) => state,
+            None => self.fetch(&symbol, Vec::new(), Duration::ZERO),
+        };
+        let (mut book, last) = match state {
+            BinanceBook::Fetching { mut snapshot, mut buffered } => {
+                if buffered.len() >= MAX_BUFFERED {
+                    buffered.remove(0);
+                }
+                buffered.push(update);
+                return match snapshot.try_recv() {
+                    Ok(Ok(snapshot)) => self.sync(symbol, snapshot, buffered, levels),
+                    Ok(Err(error)) => self.resync(symbol, buffered, error.to_string(), SNAPSHOT_RETRY),
+                    Err(oneshot::error::TryRecvError::Empty) => {
+                        self.books.insert(symbol, BinanceBook::Fetching { snapshot, buffered });
+                        Frame::Ignored
+                    }
+                    Err(oneshot::error::TryRecvError::Closed) => {
+                        self.resync(symbol, buffered, "snapshot fetch went away".to_string(), Duration::ZERO)
+                    }
+                };
+            }
+            BinanceBook::Synced { book, last } => (book, last),
+        };
+        if update._u != last + 1 {
+            let reason = format!("update gap: U {} after u {}", update._u, last);
+            return self.resync(symbol, vec![update], reason, Duration::ZERO);
+        }
+        let exchange_ts = update._e;
+        let last = update.u;
+        if let Err(reason) = apply(&mut book, update) {
+            return self.resync(symbol, Vec::new(), reason, Duration::ZERO);
+        }
+        let depth = book.depth(&symbol, levels, Some(exchange_ts));
+        // A crossed or one-sided book has fallen out of step with Binance's
+        if let Err(error) = depth.validate() {
+            return self.resync(symbol, Vec::new(), error.cause().to_string(), Duration::ZERO);
+        }
+        self.books.insert(symbol, BinanceBook::Synced { book, last });
+        Frame::Books(vec![depth])
+    }
+
+    /// Builds `symbol`'s book from `snapshot` and the diffs buffered while it
+    /// was fetched, dropping those it already covers.
+    fn sync(&mut self, symbol: String, snapshot: BinanceDepthSnapshot, buffered: Vec<BinanceDepthUpdate>, levels: usize) -> Frame {
+        let mut book = OrderBook::default();
+        let mut last = snapshot.last_update_id;
+        for (side, levels) in [(Side::Buy, snapshot.bids), (Side::Sell, snapshot.asks)] {
+            for (price, size) in levels {
+                let Some(level) = BookLevel::parse(&price, &size) else {
+                    let reason = format!("bad snapshot level [{:?}, {:?}]", price, size);
+                    return self.resync(symbol, buffered, reason, SNAPSHOT_RETRY);
+                };
+                book.update(side, level);
+            }
+        }
+        let mut exchange_ts = None;
+        let covered = snapshot.last_update_id;
+        let mut pending = buffered.into_iter().skip_while(|update| update.u <= covered);
+        while let Some(update) = pending.next() {
+            // The first diff may straddle the snapshot; every later one follows on
+            if update._u > last + 1 {
+                let reason = format!("update gap: U {} after u {}", update._u, last);
+                let buffered = std::iter::once(update).chain(pending).collect();
+                return self.resync(symbol, buffered, reason, Duration::ZERO);
+            }
+            exchange_ts = Some(update._e);
+            last = update.u;
+            if let Err(reason) = apply(&mut book, update) {
+                return self.resync(symbol, Vec::new(), reason, Duration::ZERO);
+            }
+        }
+        let depth = book.depth(&symbol, levels, exchange_ts);
+        // Straight from a snapshot, so fetching again at once would likely get the same book
+        if let Err(error) = depth.validate() {
+            return self.resync(symbol, Vec::new(), error.cause().to_string(), SNAPSHOT_RETRY);
+        }
+        self.books.insert(symbol, BinanceBook::Synced { book, last });
+        Frame::Books(vec![depth])
+    }
+
+    fn parse_stream(&mut self, message: BinanceCombinedMessage) -> Result<Frame, BinanceError> {
+        if message.stream.ends_with("@bookTicker") {
+            let quote: BinanceMessageQuote = serde_json::from_value(message.data).map_err(BinanceError::Malformed)?;
+            Ok(Frame::Quotes(vec![Quote {
+                symbol: quote.s,
+                bid: quote.b,
+                bid_vol: quote._b,
+                ask: quote.a,
+                ask_vol: quote._a,
+                // Spot's bookTicker carries an update id but no time
+                exchange_ts: quote._t,
+            }]))
+        } else if message.stream.ends_with("@trade") {
+            let trade: BinanceMessageTrade = serde_json::from_value(message.data).map_err(BinanceError::Malformed)?;
+            Ok(Frame::Trades(vec![Trade {
+                symbol: trade.s,
+                price: trade.p,
+                size: trade.q,
+                side: if trade.m { Side::Sell } else { Side::Buy },
+                trade_id: Some(trade.t.to_string()),
+                exchange_ts: Some(trade._t),
+            }]))
+        } else if message.stream.ends_with("@markPrice@1s") {
+            let mark: BinanceMarkPrice = serde_json::from_value(message.data).map_err(BinanceError::Malformed)?;
+            Ok(Frame::Perps(vec![PerpStats {
+                symbol: mark.s,
+                funding_rate: Some(mark.r),
+                funding_rate_paid: None,
+                mark_price: Some(mark.p),
+                index_price: Some(mark.i),
+                open_interest: None,
+                exchange_ts: Some(mark._e),
+            }]))
+        } else if let (true, Some(levels)) = (message.stream.ends_with("@depth@100ms"), self.book_levels) {
+            let update: BinanceDepthUpdate = serde_json::from_value(message.data).map_err(BinanceError::Malformed)?;
+            Ok(self.parse_book(update, levels))
+        } else {
+            Err(BinanceError::UnexpectedStream(message.stream))
+        }
+    }
+}
+
+/// Applies a diff's levels to `book`; `Err` says which level couldn't be read.
+fn apply(book: &mut OrderBook, update: BinanceDepthUpdate) -> Result<(), String> {
+    for (side, levels) in [(Side::Buy, update.b), (Side::Sell, update.a)] {
+        for (price, size) in levels {
+            let level = BookLevel::parse(&price, &size).ok_or_else(|| format!("bad level [{:?}, {:?}]", price, size))?;
+            book.update(side, level);
+        }
+    }
+    Ok(())
+}
+
+impl Default for BinanceSource {
+    fn default() -> Self {
+        Self::new(vec![DEFAULT_SYMBOL.to_string()])
+    }
+}
+
+impl QuoteSource for BinanceSource {
+    fn exchange(&self) -> &'static str {
+        if self.usd_m {
+            "BINANCE_USDM"
+        } else {
+            "BINANCE"
+        }
+    }
+
+    fn symbols(&self) -> &[String] {
+        &self.symbols
+    }
+
+    fn instrument(&self, symbol: &str) -> Option<Instrument> {
+        let kind = if self.usd_m { InstrumentType::Perpetual } else { InstrumentType::Spot };
+        Instrument::parse(symbol, kind)
+    }
+
+    fn endpoint(&mut self) -> Result<String, FeedError> {
+        Ok(format!("{}?streams={}", self.endpoint, self.streams().join("/")))
+    }
+
+    fn subscribe_messages(&mut self) -> Vec<String> {
+        // Streams are chosen in the URL; a new connection only needs fresh books
+        self.books.clear();
+        Vec::new()
+    }
+
+    fn unsubscribe_messages(&mut self) -> Vec<String> {
+        vec![json!({"method": "UNSUBSCRIBE", "params": self.streams(), "id": 1}).to_string()]
+    }
+
+    fn parse(&mut self, text: &str) -> Frame {
+        match serde_json::from_str::<BinanceCombinedMessage>(text) {
+            Ok(message) => self.parse_stream(message).unwrap_or_else(|e| Frame::Error(e.into())),
+            Err(e) => match serde_json::from_str::<BinanceReply>(text) {
+                Ok(BinanceReply { error: Some(error), .. }) => {
+                    Frame::Error(BinanceError::Rejected { code: error.code, msg: error.msg }.into())
+                }
+                Ok(BinanceReply { id, .. }) => Frame::Event(format!("Request {} acknowledged", id)),
+                Err(_) => Frame::Error(BinanceError::Malformed(e).into()),
+            },
+        }
+    }
+}
@@ -0,0 +1,427 @@
+use mock_exchange::{spawn_feed, spawn_feed_with, Dialect, Fault, MockExchange, MockQuote, MockRedis, MockTrade};
+use rust_binance_quote_scraper::BinanceSource;
+use scraper_core::{FeedError, RunOptions, Side};
+
+const BID_PRICE: &str = "BINANCE:BTC-USDT:QUOTE:BUY:PRICE";
+
+fn quote(bid: f64) -> MockQuote {
+    MockQuote { bid, bid_vol: 0.5, ask: bid + 1.0, ask_vol: 0.25, ts: 1_700_000_000_000 }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn stores_quotes_and_survives_malformed_frames() {
+    let redis = MockRedis::start().await;
+    let mut exchange = MockExchange::start(Dialect::Binance).await;
+    let feed = spawn_feed(BinanceSource::default().with_endpoint(exchange.endpoint()), &redis).await;
+
+    let mut session = exchange.session().await;
+    assert_eq!(session.path, "/stream?streams=btcusdt@bookTicker/btcusdt@trade");
+    session.send_quote("BTCUSDT", quote(43000.0)).await;
+    session.send_malformed().await;
+    session.send_quote("BTCUSDT", quote(43001.0)).await;
+
+    let bids: Vec<f64> = redis.wait_for_samples(BID_PRICE, 2).await.into_iter().map(|(_, bid)| bid).collect();
+    assert_eq!(bids, [43000.0, 43001.0]);
+    assert_eq!(redis.samples("BINANCE:BTC-USDT:QUOTE:BUY:VOL")[0].1, 0.5);
+    assert_eq!(redis.samples("BINANCE:BTC-USDT:QUOTE:SELL:PRICE")[0].1, 43001.0);
+    assert_eq!(redis.samples("BINANCE:BTC-USDT:QUOTE:SELL:VOL")[0].1, 0.25);
+    // Twice as much bid as ask: (0.5 - 0.25) / 0.75, and the microprice leans to the ask
+    let imbalance = redis.samples("BINANCE:BTC-USDT:QUOTE:IMBALANCE")[0].1;
+    assert!((imbalance - 1.0 / 3.0).abs() < 1e-12);
+    let microprice = redis.samples("BINANCE:BTC-USDT:QUOTE:MICROPRICE")[0].1;
+    assert!((microprice - (43000.0 * 0.25 + 43001.0 * 0.5) / 0.75).abs() < 1e-9);
+    assert_eq!(exchange.connections(), 1);
+
+    let stopping = tokio::spawn(feed.stop());
+    let unsubscribes = session.expect_close().await;
+    assert_eq!(unsubscribes, [r#"{"id":1,"method":"UNSUBSCRIBE","params":["btcusdt@bookTicker","btcusdt@trade"]}"#]);
+    stopping.await.unwrap().unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn sums_trades_within_a_millisecond() {
+    let redis = MockRedis::start().await;
+    let mut exchange = MockExchange::start(Dialect::Binance).await;
+    let feed = spawn_feed(BinanceSource::default().with_endpoint(exchange.endpoint()), &redis).await;
+
+    let mut session = exchange.session().await;
+    let trade = |price, size, side, id, ts| MockTrade { price, size, side, id, ts };
+    session.send_trade("BTCUSDT", trade(43000.0, 0.5, Side::Buy, 1, 1_700_000_000_000)).await;
+    session.send_trade("BTCUSDT", trade(42999.0, 2.0, Side::Sell, 2, 1_700_000_000_000)).await;
+    session.send_trade("BTCUSDT", trade(43001.0, 0.25, Side::Buy, 3, 1_700_000_000_001)).await;
+
+    // Each trade rewrites its millisecond's running totals
+    let signed = redis.wait_for_samples("BINANCE:BTC-USDT:TRADE:SIGNED_VOL", 3).await;
+    assert_eq!(signed, [(1_700_000_000_000, 0.5), (1_700_000_000_000, -1.5), (1_700_000_000_001, 0.25)]);
+    let volumes: Vec<f64> = redis.samples("BINANCE:BTC-USDT:TRADE:VOL").into_iter().map(|(_, volume)| volume).collect();
+    assert_eq!(volumes, [0.5, 2.5, 0.25]);
+    let prices: Vec<f64> = redis.samples("BINANCE:BTC-USDT:TRADE:PRICE").into_iter().map(|(_, price)| price).collect();
+    assert_eq!(prices, [43000.0, 42999.0, 43001.0]);
+    // Trades aren't quotes
+    assert!(redis.samples(BID_PRICE).is_empty());
+
+    let stopping = tokio::spawn(feed.stop());
+    session.expect_close().await;
+    stopping.await.unwrap().unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn adds_a_late_trade_to_its_own_millisecond() {
+    let redis = MockRedis::start().await;
+    let mut exchange = MockExchange::start(Dialect::Binance).await;
+    let feed = spawn_feed(BinanceSource::default().with_endpoint(exchange.endpoint()), &redis).await;
+
+    let mut session = exchange.session().await;
+    let trade = |price, size, side, id, ts| MockTrade { price, size, side, id, ts };
+    session.send_trade("BTCUSDT", trade(43000.0, 0.5, Side::Buy, 1, 1_700_000_000_000)).await;
+    session.send_trade("BTCUSDT", trade(43001.0, 0.25, Side::Buy, 2, 1_700_000_000_001)).await;
+    session.send_trade("BTCUSDT", trade(42999.0, 2.0, Side::Sell, 3, 1_700_000_000_000)).await;
+
+    // The late trade adds to the first millisecond's totals rather than replacing them
+    let signed = redis.wait_for_samples("BINANCE:BTC-USDT:TRADE:SIGNED_VOL", 3).await;
+    assert_eq!(signed, [(1_700_000_000_000, 0.5), (1_700_000_000_001, 0.25), (1_700_000_000_000, -1.5)]);
+    let volumes: Vec<f64> = redis.samples("BINANCE:BTC-USDT:TRADE:VOL").into_iter().map(|(_, volume)| volume).collect();
+    assert_eq!(volumes, [0.5, 0.25, 2.5]);
+
+    let stopping = tokio::spawn(feed.stop());
+    session.expect_close().await;
+    stopping.await.unwrap().unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn reconnects_after_faults() {
+    let redis = MockRedis::start().await;
+    let mut exchange = MockExchange::start(Dialect::Binance).await;
+    let feed = spawn_feed(BinanceSource::default().with_endpoint(exchange.endpoint()), &redis).await;
+
+    for (sent, fault) in [Fault::Disconnect, Fault::Close, Fault::ProtocolError].into_iter().enumerate() {
+        let mut session = exchange.session().await;
+        session.send_quote("BTCUSDT", quote(43000.0 + sent as f64)).await;
+        redis.wait_for_samples(BID_PRICE, sent + 1).await;
+        session.fail(fault).await;
+    }
+
+    let mut session = exchange.session().await;
+    session.send_quote("BTCUSDT", quote(43003.0)).await;
+    let samples = redis.wait_for_samples(BID_PRICE, 4).await;
+    assert_eq!(samples.last().unwrap().1, 43003.0);
+    assert_eq!(exchange.connections(), 4);
+
+    let stopping = tokio::spawn(feed.stop());
+    session.expect_close().await;
+    stopping.await.unwrap().unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn drops_quotes_with_zero_prices() {
+    let redis = MockRedis::start().await;
+    let mut exchange = MockExchange::start(Dialect::Binance).await;
+    let feed = spawn_feed(BinanceSource::default().with_endpoint(exchange.endpoint()), &redis).await;
+
+    let mut session = exchange.session().await;
+    session.send_quote("BTCUSDT", quote(0.0)).await;
+    session.send_quote("BTCUSDT", quote(43000.0)).await;
+
+    let bids: Vec<f64> = redis.wait_for_samples(BID_PRICE, 1).await.into_iter().map(|(_, bid)| bid).collect();
+    assert_eq!(bids, [43000.0]);
+    assert_eq!(redis.samples("BINANCE:BTC-USDT:QUOTE:SELL:PRICE").len(), 1);
+    assert_eq!(exchange.connections(), 1);
+
+    let stopping = tokio::spawn(feed.stop());
+    session.expect_close().await;
+    stopping.await.unwrap().unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn trades_alone_keep_the_feed_from_going_stale() {
+    let redis = MockRedis::start().await;
+    let mut exchange = MockExchange::start(Dialect::Binance).await;
+    let options = RunOptions { stale_after: Some(std::time::Duration::from_secs(1)), ..RunOptions::default() };
+    let feed = spawn_feed_with(BinanceSource::default().with_endpoint(exchange.endpoint()), &redis, options).await;
+
+    let mut session = exchange.session().await;
+    for id in 1..=12 {
+        let trade = MockTrade { price: 43000.0, size: 0.5, side: Side::Buy, id, ts: 1_700_000_000_000 + id };
+        session.send_trade("BTCUSDT", trade).await;
+        tokio::time::sleep(std::time::Duration::from_millis(250)).await;
+    }
+    assert_eq!(exchange.connections(), 1);
+
+    let stopping = tokio::spawn(feed.stop());
+    session.expect_close().await;
+    stopping.await.unwrap().unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn spools_quotes_while_redis_is_down_and_replays_them_in_order() {
+    let redis = MockRedis::start().await;
+    let mut exchange = MockExchange::start(Dialect::Binance).await;
+    let feed = spawn_feed(BinanceSource::default().with_endpoint(exchange.endpoint()), &redis).await;
+
+    let mut session = exchange.session().await;
+    session.send_quote("BTCUSDT", quote(43000.0)).await;
+    redis.wait_for_samples(BID_PRICE, 1).await;
+    // Let the reply reach the feed before the connection goes
+    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+    redis.stop();
+    for bid in [43001.0, 43002.0, 43003.0] {
+        session.send_quote("BTCUSDT", quote(bid)).await;
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    }
+    assert_eq!(redis.samples(BID_PRICE).len(), 1);
+
+    // Recovery is tried on the next write, at most every few seconds
+    redis.restart();
+    let mut bid = 43004.0;
+    while redis.samples(BID_PRICE).len() < 5 {
+        session.send_quote("BTCUSDT", quote(bid)).await;
+        bid += 1.0;
+        tokio::time::sleep(std::time::Duration::from_millis(250)).await;
+    }
+    let samples = redis.samples(BID_PRICE);
+    let bids: Vec<f64> = samples.iter().map(|&(_, bid)| bid).collect();
+    assert_eq!(bids[..5], [43000.0, 43001.0, 43002.0, 43003.0, 43004.0]);
+    assert!(samples.windows(2).all(|pair| pair[0].0 <= pair[1].0), "out of order: {:?}", samples);
+    assert_eq!(exchange.connections(), 1);
+
+    let stopping = tokio::spawn(feed.stop());
+    session.expect_close().await;
+    stopping.await.unwrap().unwrap();
+}
+
+/// Waits for `bid` to reach Redis, well before a degraded feed would next
+/// try to replay it.
+async fn wait_for_bid(redis: &MockRedis, bid: f64) {
+    let written = async {
+        while !redis.samples(BID_PRICE).iter().any(|&(_, written)| written == bid) {
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+    };
+    if tokio::time::timeout(std::time::Duration::from_secs(2), written).await.is_err() {
+        panic!("{} never reached Redis, commands: {:?}", bid, redis.commands());
+    }
+}
+
+/// How many times the feed has created `key`; it recreates every series on
+/// recovering from an outage.
+fn times_created(redis: &MockRedis, key: &str) -> usize {
+    redis.commands().iter().filter(|command| command[0].eq_ignore_ascii_case("TS.CREATE") && command[1] == key).count()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn drops_samples_redis_turns_down_and_keeps_writing() {
+    let redis = MockRedis::start().await;
+    redis.reject("BINANCE:BTC-USDT:QUOTE:IMBALANCE");
+    let mut exchange = MockExchange::start(Dialect::Binance).await;
+    let feed = spawn_feed(BinanceSource::default().with_endpoint(exchange.endpoint()), &redis).await;
+
+    let mut session = exchange.session().await;
+    for bid in [43000.0, 43001.0, 43002.0] {
+        session.send_quote("BTCUSDT", quote(bid)).await;
+        wait_for_bid(&redis, bid).await;
+    }
+    // Whatever was batched with a rejected sample still goes in
+    let microprice = redis.samples("BINANCE:BTC-USDT:QUOTE:MICROPRICE").last().unwrap().1;
+    assert!((microprice - (43002.0 * 0.25 + 43003.0 * 0.5) / 0.75).abs() < 1e-9);
+    // Redis answered, so the feed never degraded
+    assert_eq!(times_created(&redis, BID_PRICE), 1);
+
+    let stopping = tokio::spawn(feed.stop());
+    session.expect_close().await;
+    stopping.await.unwrap().unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn replays_the_spool_past_samples_redis_turns_down() {
+    let redis = MockRedis::start().await;
+    let mut exchange = MockExchange::start(Dialect::Binance).await;
+    let feed = spawn_feed(BinanceSource::default().with_endpoint(exchange.endpoint()), &redis).await;
+
+    let mut session = exchange.session().await;
+    session.send_quote("BTCUSDT", quote(43000.0)).await;
+    wait_for_bid(&redis, 43000.0).await;
+    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+    redis.stop();
+    for bid in [43001.0, 43002.0, 43003.0] {
+        session.send_quote("BTCUSDT", quote(bid)).await;
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    }
+    // Redis comes back turning down part of every spooled quote
+    redis.reject("BINANCE:BTC-USDT:QUOTE:IMBALANCE");
+    redis.restart();
+    let mut bid = 43004.0;
+    while !redis.samples(BID_PRICE).iter().any(|&(_, written)| written == 43003.0) {
+        session.send_quote("BTCUSDT", quote(bid)).await;
+        bid += 1.0;
+        tokio::time::sleep(std::time::Duration::from_millis(250)).await;
+    }
+
+    // The spool is gone and live writes are back, rather than waiting on the next replay
+    session.send_quote("BTCUSDT", quote(bid)).await;
+    wait_for_bid(&redis, bid).await;
+    assert_eq!(times_created(&redis, BID_PRICE), 2);
+    assert_eq!(exchange.connections(), 1);
+
+    let stopping = tokio::spawn(feed.stop());
+    session.expect_close().await;
+    stopping.await.unwrap().unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn stops_when_a_request_is_rejected() {
+    let redis = MockRedis::start().await;
+    let mut exchange = MockExchange::start(Dialect::Binance).await;
+    let feed = spawn_feed(BinanceSource::default().with_endpoint(exchange.endpoint()), &redis).await;
+
+    let mut session = exchange.session().await;
+    session.send_text(r#"{"error":{"code":2,"msg":"Invalid request: unknown stream"},"id":1}"#).await;
+
+    let stopped = tokio::spawn(feed.stopped());
+    let unsubscribes = session.expect_close().await;
+    assert_eq!(unsubscribes, [r#"{"id":1,"method":"UNSUBSCRIBE","params":["btcusdt@bookTicker","btcusdt@trade"]}"#]);
+    assert!(matches!(stopped.await.unwrap(), Err(FeedError::Subscribe(_))));
+    assert_eq!(exchange.connections(), 1);
+}
+
+/// A `@depth@100ms` diff carrying update IDs `first..=last`.
+fn depth(first: u64, last: u64, bids: &str, asks: &str) -> String {
+    format!(
+        r#"{{"stream":"btcusdt@depth@100ms","data":{{"e":"depthUpdate","E":{},"s":"BTCUSDT","U":{},"u":{},"b":{},"a":{}}}}}"#,
+        1_700_000_000_000 + last,
+        first,
+        last,
+        bids,
+        asks
+    )
+}
+
+/// Sends empty diffs from update ID `next` on until `key` has a sample
+/// matching `synced`; the snapshot is only picked up with a diff. Returns the
+/// next unused update ID.
+async fn send_until(session: &mut mock_exchange::Session, redis: &MockRedis, key: &str, mut next: u64, synced: f64) -> u64 {
+    for _ in 0..200 {
+        session.send_text(&depth(next, next, "[]", "[]")).await;
+        next += 1;
+        if redis.samples(key).iter().any(|&(_, value)| value == synced) {
+            return next;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    }
+    panic!("{} never reached {}: {:?}", key, synced, redis.samples(key));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn keeps_a_book_from_a_snapshot_and_refetches_after_a_gap() {
+    let redis = MockRedis::start().await;
+    let mut exchange = MockExchange::start(Dialect::Binance).await;
+    exchange.respond(
+        "/api/v3/depth",
+        r#"{"lastUpdateId":100,"bids":[["43000.00","1.0"],["42990.00","2.0"]],"asks":[["43001.00","1.5"],["43010.00","3.0"]]}"#,
+    );
+    let source = BinanceSource::default().with_endpoint(exchange.endpoint()).with_rest_api(exchange.rest_api()).with_book(2);
+    let feed = spawn_feed(source, &redis).await;
+
+    let mut session = exchange.session().await;
+    assert_eq!(session.path, "/stream?streams=btcusdt@bookTicker/btcusdt@trade/btcusdt@depth@100ms");
+    // Already in the snapshot, so dropped once it arrives
+    session.send_text(&depth(99, 100, r#"[["43000.00","9.0"]]"#, "[]")).await;
+    assert_eq!(exchange.wait_for_requests(1).await, ["/api/v3/depth?symbol=BTCUSDT&limit=1000"]);
+    session.send_text(&depth(101, 101, r#"[["43000.00","1.25"]]"#, r#"[["43001.00","0"]]"#)).await;
+    let next = send_until(&mut session, &redis, "BINANCE:BTC-USDT:BOOK:BUY:L1:VOL", 102, 1.25).await;
+
+    assert!(redis.samples("BINANCE:BTC-USDT:BOOK:BUY:L1:PRICE").iter().all(|&(_, bid)| bid == 43000.0));
+    assert_eq!(redis.samples("BINANCE:BTC-USDT:BOOK:BUY:L2:VOL").last().unwrap().1, 2.0);
+    assert_eq!(redis.samples("BINANCE:BTC-USDT:BOOK:SELL:L1:PRICE").last().unwrap().1, 43010.0);
+    assert!(redis.samples("BINANCE:BTC-USDT:BOOK:SELL:L2:PRICE").is_empty());
+    // 3.25 bid against 3.0 ask over the two levels written
+    let imbalance = redis.samples("BINANCE:BTC-USDT:BOOK:IMBALANCE").last().unwrap().1;
+    assert!((imbalance - 0.25 / 6.25).abs() < 1e-12);
+    // Every level is within 10bps of the 43005 mid
+    assert_eq!(redis.samples("BINANCE:BTC-USDT:BOOK:IMBALANCE:10BPS").last().unwrap().1, imbalance);
+
+    // Skipping update IDs throws the book away and fetches it again
+    exchange.respond("/api/v3/depth", r#"{"lastUpdateId":1000,"bids":[["43005.00","4.0"]],"asks":[["43006.00","0.5"]]}"#);
+    session.send_text(&depth(next + 5, 1000, "[]", "[]")).await;
+    exchange.wait_for_requests(2).await;
+    let next = send_until(&mut session, &redis, "BINANCE:BTC-USDT:BOOK:BUY:L1:PRICE", 1001, 43005.0).await;
+    assert_eq!(redis.samples("BINANCE:BTC-USDT:BOOK:SELL:L1:VOL").last().unwrap().1, 0.5);
+
+    // So does a diff that crosses it, without writing the crossed book
+    exchange.respond("/api/v3/depth", r#"{"lastUpdateId":2000,"bids":[["43002.00","1.0"]],"asks":[["43003.00","1.0"]]}"#);
+    session.send_text(&depth(next, next, r#"[["43007.00","1.0"]]"#, "[]")).await;
+    exchange.wait_for_requests(3).await;
+    send_until(&mut session, &redis, "BINANCE:BTC-USDT:BOOK:BUY:L1:PRICE", 2001, 43002.0).await;
+    assert!(redis.samples("BINANCE:BTC-USDT:BOOK:BUY:L1:PRICE").iter().all(|&(_, bid)| bid != 43007.0));
+    assert_eq!(exchange.connections(), 1);
+
+    let stopping = tokio::spawn(feed.stop());
+    session.expect_close().await;
+    stopping.await.unwrap().unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn keeps_quoting_and_refetches_when_a_snapshot_fails() {
+    let redis = MockRedis::start().await;
+    let mut exchange = MockExchange::start(Dialect::Binance).await;
+    let source = BinanceSource::default().with_endpoint(exchange.endpoint()).with_rest_api(exchange.rest_api()).with_book(2);
+    let feed = spawn_feed(source, &redis).await;
+
+    let mut session = exchange.session().await;
+    // Nothing is routed yet, so the first snapshot is a 404
+    session.send_text(&depth(99, 100, "[]", "[]")).await;
+    exchange.wait_for_requests(1).await;
+    session.send_quote("BTCUSDT", quote(43000.0)).await;
+    redis.wait_for_samples(BID_PRICE, 1).await;
+
+    // The next diff finds the failure and fetches again after a pause
+    exchange.respond("/api/v3/depth", r#"{"lastUpdateId":100,"bids":[["43000.00","1.0"]],"asks":[["43001.00","1.5"]]}"#);
+    send_until(&mut session, &redis, "BINANCE:BTC-USDT:BOOK:BUY:L1:PRICE", 101, 43000.0).await;
+    session.send_quote("BTCUSDT", quote(43001.0)).await;
+    redis.wait_for_samples(BID_PRICE, 2).await;
+    assert_eq!(exchange.requests().len(), 2);
+    assert_eq!(exchange.connections(), 1);
+
+    let stopping = tokio::spawn(feed.stop());
+    session.expect_close().await;
+    stopping.await.unwrap().unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn stores_usd_m_funding_mark_and_index_prices() {
+    let redis = MockRedis::start().await;
+    let mut exchange = MockExchange::start(Dialect::Binance).await;
+    let feed = spawn_feed(BinanceSource::default().usd_m().with_endpoint(exchange.endpoint()), &redis).await;
+
+    let mut session = exchange.session().await;
+    assert_eq!(session.path, "/stream?streams=btcusdt@bookTicker/btcusdt@markPrice@1s");
+    session.send_quote("BTCUSDT", quote(43000.0)).await;
+    session
+        .send_text(r#"{"stream":"btcusdt@markPrice@1s","data":{"e":"markPriceUpdate","E":1700000001000,"s":"BTCUSDT","p":"43043.00","P":"43040.00","i":"43000.00","r":"-0.00012500","T":1700006400000}}"#)
+        .await;
+
+    // Perpetuals are their own feed, under their own exchange and canonical symbol
+    assert_eq!(redis.wait_for_samples("BINANCE_USDM:BTC-USDT-PERP:QUOTE:BUY:PRICE", 1).await[0].1, 43000.0);
+    let prefix = "BINANCE_USDM:BTC-USDT-PERP:PERP";
+    assert_eq!(redis.wait_for_samples(&format!("{}:FUNDING_RATE", prefix), 1).await, [(1_700_000_001_000, -0.000125)]);
+    assert_eq!(redis.samples(&format!("{}:MARK_PRICE", prefix))[0].1, 43043.0);
+    assert_eq!(redis.samples(&format!("{}:INDEX_PRICE", prefix))[0].1, 43000.0);
+    assert!((redis.samples(&format!("{}:BASIS", prefix))[0].1 - 0.001).abs() < 1e-12);
+    // Binance sends no open interest or paid funding with the mark price, so neither series is created
+    let created: Vec<String> = redis
+        .commands()
+        .into_iter()
+        .filter(|command| command[0].eq_ignore_ascii_case("TS.CREATE") && command[1].starts_with(prefix))
+        .map(|command| command[1].clone())
+        .collect();
+    for group in ["FUNDING_RATE", "MARK_PRICE", "INDEX_PRICE", "BASIS"] {
+        assert!(created.contains(&format!("{}:{}", prefix, group)), "{} not created: {:?}", group, created);
+    }
+    assert!(!created.iter().any(|key| key.ends_with(":OPEN_INTEREST") || key.ends_with(":FUNDING_RATE_PAID")), "{:?}", created);
+
+    let stopping = tokio::spawn(feed.stop());
+    let unsubscribes = session.expect_close().await;
+    assert_eq!(unsubscribes, [r#"{"id":1,"method":"UNSUBSCRIBE","params":["btcusdt@bookTicker","btcusdt@markPrice@1s"]}"#]);
+    stopping.await.unwrap().unwrap();
+}
@@ -0,0 +1,30 @@
+use mock_exchange::{fixture_frames, parse_frames, parse_mangled};
+use rust_binance_quote_scraper::BinanceSource;
+
+#[test]
+fn parses_fixtures() {
+    insta::glob!("fixtures/*.jsonl", |path| {
+        insta::assert_debug_snapshot!(parse_frames(&mut BinanceSource::default(), &fixture_frames(path)));
+    });
+}
+
+#[test]
+fn parses_usd_m_fixtures() {
+    insta::glob!("fixtures/usd_m/*.jsonl", |path| {
+        insta::assert_debug_snapshot!(parse_frames(&mut usd_m(), &fixture_frames(path)));
+    });
+}
+
+#[test]
+fn never_panics_on_mangled_fixtures() {
+    insta::glob!("fixtures/*.jsonl", |path| {
+        parse_mangled(BinanceSource::default, &fixture_frames(path));
+    });
+    insta::glob!("fixtures/usd_m/*.jsonl", |path| {
+        parse_mangled(usd_m, &fixture_frames(path));
+    });
+}
+
+fn usd_m() -> BinanceSource {
+    BinanceSource::default().usd_m()
+}
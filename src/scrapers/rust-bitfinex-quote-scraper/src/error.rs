@@ -0,0 +1,51 @@
+use scraper_core::FeedError;
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug)]
+pub enum BitfinexError {
+    /// Not JSON at all.
+    Malformed(serde_json::Error),
+    /// A ticker update on one of our channels that isn't the ten numbers
+    /// Bitfinex documents.
+    BadTicker { chan_id: i64, data: String },
+    /// A `te` trade on one of our channels that isn't `[ID, MTS, AMOUNT, PRICE]`.
+    BadTrade { chan_id: i64, data: String },
+    /// An `error` event, e.g. for a symbol Bitfinex doesn't list.
+    Rejected { code: i64, msg: String },
+    /// Info code 20051: the server is restarting and wants us to reconnect.
+    Restarting,
+}
+
+impl fmt::Display for BitfinexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BitfinexError::Malformed(e) => write!(f, "malformed message: {}", e),
+            BitfinexError::BadTicker { chan_id, data } => write!(f, "bad ticker on channel {}: {}", chan_id, data),
+            BitfinexError::BadTrade { chan_id, data } => write!(f, "bad trade on channel {}: {}", chan_id, data),
+            BitfinexError::Rejected { code, msg } => write!(f, "Bitfinex rejected the request: {} (code {})", msg, code),
+            BitfinexError::Restarting => write!(f, "Bitfinex is restarting the server"),
+        }
+    }
+}
+
+impl Error for BitfinexError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            BitfinexError::Malformed(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<BitfinexError> for FeedError {
+    fn from(error: BitfinexError) -> Self {
+        match error {
+            BitfinexError::Malformed(_) | BitfinexError::BadTicker { .. } | BitfinexError::BadTrade { .. } => {
+                FeedError::decode(error)
+            }
+            BitfinexError::Rejected { .. } => FeedError::subscribe(error),
+            BitfinexError::Restarting => FeedError::connect(error),
+        }
+    }
+}
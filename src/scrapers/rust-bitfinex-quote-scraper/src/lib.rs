@@ -0,0 +1,342 @@
+mod error;
+
+pub use error::BitfinexError;
+
+use scraper_core::{BookLevel, FeedError, Frame, Instrument, InstrumentType, OrderBook, Quote, QuoteSource, Side, Trade};
+use serde_json::value::RawValue;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// A book level as Bitfinex wrote it, `[PRICE, COUNT, AMOUNT]`, kept as text
+/// since that is what its checksum covers.
+type BitfinexBookLevel = [Box<RawValue>; 3];
+
+const BITFINEX_WS_API: &str = "wss://api-pub.bitfinex.com/ws/2";
+
+/// Info code asking clients to reconnect before the server restarts.
+const INFO_RESTARTING: i64 = 20051;
+
+/// `conf` flag adding a `[chanId, "cs", CHECKSUM]` after every book update.
+const OB_CHECKSUM: u64 = 131072;
+
+/// Levels per side Bitfinex's book checksum covers.
+const CHECKSUM_LEVELS: usize = 25;
+
+/// The lengths Bitfinex lets a book subscription ask for.
+const BOOK_LENGTHS: [usize; 3] = [25, 100, 250];
+
+const DEFAULT_SYMBOL: &str = "tBTCUSD";
+
+/// What a channel id was handed out for, with its symbol.
+#[derive(Debug)]
+enum BitfinexChannel {
+    Ticker(String),
+    Trades(String),
+    /// The local book, filled by the snapshot that follows the subscribe ack.
+    Book(String, OrderBook),
+}
+
+pub struct BitfinexSource {
+    endpoint: String,
+    symbols: Vec<String>,
+    channels: HashMap<i64, BitfinexChannel>,
+    /// Levels per side written from each local book; `None` leaves books off.
+    book_levels: Option<usize>,
+}
+
+impl BitfinexSource {
+    pub fn new(symbols: Vec<String>) -> Self {
+        BitfinexSource { endpoint: BITFINEX_WS_API.to_string(), symbols, channels: HashMap::new(), book_levels: None }
+    }
+
+    /// Also keeps a local book per symbol from the `book` channel and writes
+    /// its best `levels` on each side.
+    pub fn with_book(mut self, levels: usize) -> Self {
+        self.book_levels = Some(levels);
+        self
+    }
+
+    /// Connects to `endpoint` instead of `api-pub.bitfinex.com`, e.g. a mock exchange in tests.
+    pub fn with_endpoint(mut self, endpoint: impl Into<String>) -> Self {
+        self.endpoint = endpoint.into();
+        self
+    }
+}
+
+/// CRC32 of the best [`CHECKSUM_LEVELS`] bids and asks as Bitfinex sent
+/// them, interleaved as `bidPrice:bidAmount:askPrice:askAmount:...` with ask
+/// amounts negative, read as a signed 32 bit number.
+fn checksum(book: &OrderBook) -> i32 {
+    let bids: Vec<&BookLevel> = book.bids().take(CHECKSUM_LEVELS).collect();
+    let asks: Vec<&BookLevel> = book.asks().take(CHECKSUM_LEVELS).collect();
+    let mut fields = Vec::new();
+    for index in 0..CHECKSUM_LEVELS {
+        for level in [bids.get(index), asks.get(index)].into_iter().flatten() {
+            fields.push(level.text.0.as_str());
+            fields.push(level.text.1.as_str());
+        }
+    }
+    crc32fast::hash(fields.join(":").as_bytes()) as i32
+}
+
+/// `[PRICE, COUNT, AMOUNT]` as a level on its side: positive amounts are
+/// bids and negative ones asks. A count of 0 removes the level.
+fn book_level([price, count, amount]: &BitfinexBookLevel) -> Option<(Side, BookLevel)> {
+    let count: i64 = count.get().parse().ok()?;
+    let mut level = BookLevel::parse(price.get(), amount.get())?;
+    let side = if level.size > 0.0 {
+        Side::Buy
+    } else if level.size < 0.0 {
+        Side::Sell
+    } else {
+        return None;
+    };
+    level.size = if count > 0 { level.size.abs() } else { 0.0 };
+    Some((side, level))
+}
+
+/// Bitfinex unsubscribes by the channel id handed out in the subscribe ack.
+fn unsubscription(chan_id: i64) -> String {
+    json!({"event": "unsubscribe", "chanId": chan_id}).to_string()
+}
+
+impl Default for BitfinexSource {
+    fn default() -> Self {
+        Self::new(vec![DEFAULT_SYMBOL.to_string()])
+    }
+}
+
+impl BitfinexSource {
+    fn parse_event(&mut self, event: &str, data: &Value) -> Frame {
+        let code = data.get("code").and_then(|v| v.as_i64());
+        let msg = data.get("msg").and_then(|v| v.as_str()).unwrap_or_default();
+        match event {
+            "subscribed" => {
+                let chan_id = data.get("chanId").and_then(|v| v.as_i64());
+                let channel = data.get("channel").and_then(|v| v.as_str());
+                let symbol = data.get("symbol").and_then(|v| v.as_str());
+                let (Some(chan_id), Some(channel), Some(symbol)) = (chan_id, channel, symbol) else {
+                    return Frame::Ignored;
+                };
+                let subscribed = match channel {
+                    "ticker" => BitfinexChannel::Ticker(symbol.to_string()),
+                    "trades" => BitfinexChannel::Trades(symbol.to_string()),
+                    "book" if self.book_levels.is_some() => BitfinexChannel::Book(symbol.to_string(), OrderBook::default()),
+                    _ => return Frame::Ignored,
+                };
+                self.channels.insert(chan_id, subscribed);
+                Frame::Event(format!("Subscribed to {} {} on channel ID: {}", symbol, channel, chan_id))
+            }
+            "error" => Frame::Error(BitfinexError::Rejected { code: code.unwrap_or_default(), msg: msg.to_string() }.into()),
+            "info" if code == Some(INFO_RESTARTING) => Frame::Error(BitfinexError::Restarting.into()),
+            "info" => match data.get("version") {
+                Some(version) => Frame::Event(format!("Connected to API v{}", version)),
+                None => Frame::Event(format!("Info: {} (code {})", msg, code.unwrap_or_default())),
+            },
+            _ => Frame::Event(format!("Received {} event", event)),
+        }
+    }
+
+    /// `[chanId, [BID, BID_SIZE, ASK, ASK_SIZE, ...]]`.
+    fn parse_ticker(symbol: &str, chan_id: i64, payload: &Value) -> Frame {
+        // `[chanId, "hb"]` heartbeats have no array payload
+        let Some(ticker) = payload.as_array() else {
+            return Frame::Ignored;
+        };
+        let numbers: Option<Vec<f64>> = ticker.iter().take(4).map(Value::as_f64).collect();
+        match numbers.as_deref() {
+            Some(&[bid, bid_vol, ask, ask_vol]) => Frame::Quotes(vec![Quote {
+                symbol: symbol.to_string(),
+                bid,
+                bid_vol,
+                ask,
+                ask_vol,
+                exchange_ts: None,
+            }]),
+            _ => Frame::Error(BitfinexError::BadTicker { chan_id, data: payload.to_string() }.into()),
+        }
+    }
+
+    /// `[chanId, "te", [ID, MTS, AMOUNT, PRICE]]`, with a negative amount for
+    /// sells. The snapshot of past trades sent on subscribing and the `tu`
+    /// repeat of every `te` are dropped.
+    fn parse_trade(symbol: &str, chan_id: i64, payload: &Value, rest: &[Value]) -> Frame {
+        if payload.as_str() != Some("te") {
+            return Frame::Ignored;
+        }
+        let fields = rest.first().and_then(Value::as_array).map(Vec::as_slice);
+        let Some([id, mts, amount, price, ..]) = fields else {
+            return Frame::Error(BitfinexError::BadTrade { chan_id, data: Value::from(rest).to_string() }.into());
+        };
+        match (id.as_i64(), mts.as_u64(), amount.as_f64(), price.as_f64()) {
+            (Some(id), Some(mts), Some(amount), Some(price)) => Frame::Trades(vec![Trade {
+                symbol: symbol.to_string(),
+                price,
+                size: amount.abs(),
+                side: if amount < 0.0 { Side::Sell } else { Side::Buy },
+                trade_id: Some(id.to_string()),
+                exchange_ts: Some(mts),
+            }]),
+            _ => Frame::Error(BitfinexError::BadTrade { chan_id, data: Value::from(rest).to_string() }.into()),
+        }
+    }
+}
+
+impl BitfinexSource {
+    fn subscription(&self, channel: &str, symbol: &str) -> String {
+        if channel != "book" {
+            return json!({"event": "subscribe", "channel": channel, "symbol": symbol}).to_string();
+        }
+        let length = BOOK_LENGTHS
+            .into_iter()
+            .find(|length| *length >= self.book_levels.unwrap_or_default())
+            .unwrap_or(BOOK_LENGTHS[BOOK_LENGTHS.len() - 1]);
+        json!({"event": "subscribe", "channel": channel, "symbol": symbol, "prec": "P0", "len": length.to_string()}).to_string()
+    }
+
+    /// Drops the book on `chan_id`, unsubscribes it and subscribes to
+    /// `symbol`'s book again, which starts over from a snapshot on a new
+    /// channel id.
+    fn resync(&mut self, chan_id: i64, symbol: String, reason: String) -> Frame {
+        self.channels.remove(&chan_id);
+        let messages = vec![unsubscription(chan_id), self.subscription("book", &symbol)];
+        Frame::Resync { symbol, reason, messages }
+    }
+
+    /// `[chanId, [[PRICE, COUNT, AMOUNT], ...]]` snapshots, `[chanId, [PRICE,
+    /// COUNT, AMOUNT]]` updates and `[chanId, "cs", CHECKSUM]` checksums.
+    fn parse_book(&mut self, chan_id: i64, text: &str, levels: usize) -> Frame {
+        let Some(BitfinexChannel::Book(symbol, book)) = self.channels.get_mut(&chan_id) else {
+            return Frame::Ignored;
+        };
+        let symbol = symbol.clone();
+        let message = serde_json::from_str::<Vec<Box<RawValue>>>(text).unwrap_or_default();
+        let [_, payload, rest @ ..] = message.as_slice() else {
+            return Frame::Ignored;
+        };
+        match serde_json::from_str::<&str>(payload.get()) {
+            Ok("cs") => {
+                let expected = rest.first().and_then(|checksum| checksum.get().parse::<i32>().ok());
+                let checksum = checksum(book);
+                if expected == Some(checksum) {
+                    return Frame::Ignored;
+                }
+                let reason = match expected {
+                    Some(expected) => format!("checksum {} doesn't match Bitfinex's {}", checksum, expected),
+                    None => "unreadable checksum".to_string(),
+                };
+                return self.resync(chan_id, symbol, reason);
+            }
+            // `hb` heartbeats
+            Ok(_) => return Frame::Ignored,
+            Err(_) => {}
+        }
+        let updates = match serde_json::from_str::<Vec<BitfinexBookLevel>>(payload.get()) {
+            Ok(snapshot) => {
+                book.clear();
+                snapshot
+            }
+            Err(_) => match serde_json::from_str::<BitfinexBookLevel>(payload.get()) {
+                Ok(update) => vec![update],
+                Err(_) => return self.resync(chan_id, symbol, format!("bad book update {}", payload)),
+            },
+        };
+        for update in &updates {
+            let Some((side, level)) = book_level(update) else {
+                let level: Vec<&str> = update.iter().map(|field| field.get()).collect();
+                return self.resync(chan_id, symbol, format!("bad level [{}]", level.join(",")));
+            };
+            book.update(side, level);
+        }
+        let depth = book.depth(&symbol, levels, None);
+        // A crossed or one-sided book has fallen out of step with Bitfinex's
+        if let Err(error) = depth.validate() {
+            return self.resync(chan_id, symbol, error.cause().to_string());
+        }
+        Frame::Books(vec![depth])
+    }
+}
+
+impl QuoteSource for BitfinexSource {
+    fn exchange(&self) -> &'static str {
+        "BITFINEX"
+    }
+
+    fn symbols(&self) -> &[String] {
+        &self.symbols
+    }
+
+    fn instrument(&self, symbol: &str) -> Option<Instrument> {
+        // Trading pairs carry a `t` prefix (`tBTCUSD`) that isn't part of either currency
+        Instrument::parse(symbol.strip_prefix('t').unwrap_or(symbol), InstrumentType::Spot)
+    }
+
+    fn endpoint(&mut self) -> Result<String, FeedError> {
+        Ok(self.endpoint.clone())
+    }
+
+    fn subscribe_messages(&mut self) -> Vec<String> {
+        self.channels.clear();
+        let channels: &[&str] = if self.book_levels.is_some() { &["ticker", "trades", "book"] } else { &["ticker", "trades"] };
+        let mut messages = Vec::new();
+        if self.book_levels.is_some() {
+            // Turns on connection-wide options, here book checksums
+            messages.push(json!({"event": "conf", "flags": OB_CHECKSUM}).to_string());
+        }
+        for symbol in &self.symbols {
+            messages.extend(channels.iter().map(|channel| self.subscription(channel, symbol)));
+        }
+        messages
+    }
+
+    fn unsubscribe_messages(&mut self) -> Vec<String> {
+        let mut chan_ids: Vec<i64> = self.channels.keys().copied().collect();
+        chan_ids.sort_unstable();
+        chan_ids.into_iter().map(unsubscription).collect()
+    }
+
+    fn parse(&mut self, text: &str) -> Frame {
+        let data = match serde_json::from_str::<Value>(text) {
+            Ok(data) => data,
+            Err(e) => return Frame::Error(BitfinexError::Malformed(e).into()),
+        };
+        if let Some(event) = data.get("event").and_then(|v| v.as_str()) {
+            return self.parse_event(event, &data);
+        }
+        let Some([chan_id, payload, rest @ ..]) = data.as_array().map(Vec::as_slice) else {
+            return Frame::Ignored;
+        };
+        let Some(chan_id) = chan_id.as_i64() else {
+            return Frame::Ignored;
+        };
+        match (self.channels.get(&chan_id), self.book_levels) {
+            (Some(BitfinexChannel::Ticker(symbol)), _) => Self::parse_ticker(symbol, chan_id, payload),
+            (Some(BitfinexChannel::Trades(symbol)), _) => Self::parse_trade(symbol, chan_id, payload, rest),
+            (Some(BitfinexChannel::Book(..)), Some(levels)) => self.parse_book(chan_id, text, levels),
+            _ => Frame::Ignored,
+        }
+    }
+
+    fn ping_interval(&self) -> Duration {
+        Duration::from_secs(30)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The example from Bitfinex's book checksum docs, with the text it works
+    /// out to.
+    #[test]
+    fn checksums_the_documented_book() {
+        let levels: Vec<BitfinexBookLevel> = serde_json::from_str("[[6000,1,1],[5900,1,2],[6100,1,-3],[6200,1,-4]]").unwrap();
+        let mut book = OrderBook::default();
+        for level in &levels {
+            let (side, level) = book_level(level).unwrap();
+            book.update(side, level);
+        }
+        assert_eq!(checksum(&book), crc32fast::hash(b"6000:1:6100:-3:5900:2:6200:-4") as i32);
+    }
+}
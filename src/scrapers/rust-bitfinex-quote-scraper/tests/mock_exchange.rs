@@ -0,0 +1,70 @@
+use mock_exchange::{spawn_feed, Dialect, Fault, MockExchange, MockQuote, MockRedis, MockTrade};
+use rust_bitfinex_quote_scraper::BitfinexSource;
+use scraper_core::Side;
+
+const BID_PRICE: &str = "BITFINEX:BTC-USD:QUOTE:BUY:PRICE";
+
+fn quote(bid: f64) -> MockQuote {
+    MockQuote { bid, bid_vol: 0.5, ask: bid + 1.0, ask_vol: 0.25, ts: 1_700_000_000_000 }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn follows_channel_ids_across_reconnects() {
+    let redis = MockRedis::start().await;
+    let mut exchange = MockExchange::start(Dialect::Bitfinex).await;
+    let feed = spawn_feed(BitfinexSource::default().with_endpoint(exchange.endpoint()), &redis).await;
+
+    let mut session = exchange.session().await;
+    let subscriptions = session.expect_subscriptions(2).await;
+    assert_eq!(
+        subscriptions,
+        [
+            r#"{"channel":"ticker","event":"subscribe","symbol":"tBTCUSD"}"#,
+            r#"{"channel":"trades","event":"subscribe","symbol":"tBTCUSD"}"#
+        ]
+    );
+    session.send_heartbeat(0).await;
+    session.send_quote("tBTCUSD", quote(43000.0)).await;
+    session.send_malformed().await;
+    session.send_quote("tBTCUSD", quote(43001.0)).await;
+    redis.wait_for_samples(BID_PRICE, 2).await;
+    session.fail(Fault::Disconnect).await;
+
+    // The new connection hands out different chanIds for the same channels
+    let mut session = exchange.session().await;
+    session.expect_subscriptions(2).await;
+    session.send_quote("tBTCUSD", quote(43002.0)).await;
+    let trade = MockTrade { price: 43002.5, size: 0.75, side: Side::Sell, id: 1, ts: 1_700_000_000_000 };
+    session.send_trade("tBTCUSD", trade).await;
+    let bids: Vec<f64> = redis.wait_for_samples(BID_PRICE, 3).await.into_iter().map(|(_, bid)| bid).collect();
+    assert_eq!(bids, [43000.0, 43001.0, 43002.0]);
+    assert_eq!(redis.samples("BITFINEX:BTC-USD:QUOTE:SELL:VOL")[0].1, 0.25);
+    let signed = redis.wait_for_samples("BITFINEX:BTC-USD:TRADE:SIGNED_VOL", 1).await;
+    assert_eq!(signed, [(1_700_000_000_000, -0.75)]);
+
+    let stopping = tokio::spawn(feed.stop());
+    let unsubscribes = session.expect_close().await;
+    assert_eq!(unsubscribes, [r#"{"chanId":2000,"event":"unsubscribe"}"#, r#"{"chanId":2001,"event":"unsubscribe"}"#]);
+    stopping.await.unwrap().unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn reconnects_when_the_server_restarts() {
+    let redis = MockRedis::start().await;
+    let mut exchange = MockExchange::start(Dialect::Bitfinex).await;
+    let feed = spawn_feed(BitfinexSource::default().with_endpoint(exchange.endpoint()), &redis).await;
+
+    let mut session = exchange.session().await;
+    session.expect_subscriptions(2).await;
+    session.send_text(r#"{"event":"info","code":20051,"msg":"Stopping. Please try to reconnect"}"#).await;
+
+    let mut session = exchange.session().await;
+    session.expect_subscriptions(2).await;
+    session.send_quote("tBTCUSD", quote(43000.0)).await;
+    redis.wait_for_samples(BID_PRICE, 1).await;
+    assert_eq!(exchange.connections(), 2);
+
+    let stopping = tokio::spawn(feed.stop());
+    session.expect_close().await;
+    stopping.await.unwrap().unwrap();
+}
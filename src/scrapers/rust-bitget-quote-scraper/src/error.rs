@@ -0,0 +1,42 @@
+use scraper_core::FeedError;
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug)]
+pub enum BitgetError {
+    /// Neither a push nor an event, or a push whose data isn't shaped like
+    /// its channel's.
+    Malformed(serde_json::Error),
+    /// A push on a channel other than `ticker` or `trade`.
+    UnexpectedChannel(String),
+    /// An `error` event, e.g. for an instrument Bitget doesn't list.
+    Rejected { code: i64, msg: String },
+}
+
+impl fmt::Display for BitgetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BitgetError::Malformed(e) => write!(f, "malformed message: {}", e),
+            BitgetError::UnexpectedChannel(channel) => write!(f, "unexpected channel {}", channel),
+            BitgetError::Rejected { code, msg } => write!(f, "Bitget rejected the request: {} (code {})", msg, code),
+        }
+    }
+}
+
+impl Error for BitgetError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            BitgetError::Malformed(e) => Some(e),
+            BitgetError::UnexpectedChannel(_) | BitgetError::Rejected { .. } => None,
+        }
+    }
+}
+
+impl From<BitgetError> for FeedError {
+    fn from(error: BitgetError) -> Self {
+        match error {
+            BitgetError::Malformed(_) | BitgetError::UnexpectedChannel(_) => FeedError::decode(error),
+            BitgetError::Rejected { .. } => FeedError::subscribe(error),
+        }
+    }
+}
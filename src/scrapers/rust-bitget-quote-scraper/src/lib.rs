@@ -0,0 +1,180 @@
+mod error;
+
+pub use error::BitgetError;
+
+use scraper_core::{de_float_from_str, de_millis_from_str, FeedError, Frame, Quote, QuoteSource, Side, Trade};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+#[derive(Serialize, Deserialize, Debug)]
+struct BitgetTickerData {
+    #[serde(rename = "instId")]
+    inst_id: String,
+    #[serde(rename = "bidPr", deserialize_with = "de_float_from_str")]
+    bid_pr: f64,
+    #[serde(rename = "askPr", deserialize_with = "de_float_from_str")]
+    ask_pr: f64,
+    #[serde(rename = "bidSz", deserialize_with = "de_float_from_str")]
+    bid_sz: f64,
+    #[serde(rename = "askSz", deserialize_with = "de_float_from_str")]
+    ask_sz: f64,
+    #[serde(rename = "ts", deserialize_with = "de_millis_from_str")]
+    ts: u64,
+}
+
+/// A `trade` push; `side` is the taker's.
+#[derive(Serialize, Deserialize, Debug)]
+struct BitgetTradeData {
+    #[serde(rename = "tradeId")]
+    trade_id: String,
+    #[serde(deserialize_with = "de_float_from_str")]
+    price: f64,
+    #[serde(deserialize_with = "de_float_from_str")]
+    size: f64,
+    side: Side,
+    #[serde(deserialize_with = "de_millis_from_str")]
+    ts: u64,
+}
+
+/// A push on one of our channels; `data` is parsed once `arg` says which.
+#[derive(Serialize, Deserialize, Debug)]
+struct BitgetMarketMessage {
+    action: String,
+    arg: BitgetChannelArg,
+    data: Value,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct BitgetChannelArg {
+    #[serde(rename = "instType")]
+    inst_type: String,
+    channel: String,
+    #[serde(rename = "instId")]
+    inst_id: String,
+}
+
+/// `subscribe` and `unsubscribe` confirmations, and `error` with a code and
+/// message.
+#[derive(Serialize, Deserialize, Debug)]
+struct BitgetEvent {
+    event: String,
+    code: Option<i64>,
+    msg: Option<String>,
+}
+
+const BITGET_WS_API: &str = "wss://ws.bitget.com/v2/ws/public";
+
+const DEFAULT_SYMBOL: &str = "BTCUSDT";
+
+pub struct BitgetSource {
+    endpoint: String,
+    symbols: Vec<String>,
+}
+
+impl BitgetSource {
+    pub fn new(symbols: Vec<String>) -> Self {
+        BitgetSource { endpoint: BITGET_WS_API.to_string(), symbols }
+    }
+
+    /// Connects to `endpoint` instead of `ws.bitget.com`, e.g. a mock exchange in tests.
+    pub fn with_endpoint(mut self, endpoint: impl Into<String>) -> Self {
+        self.endpoint = endpoint.into();
+        self
+    }
+
+    /// A `subscribe` or `unsubscribe` request for every symbol's ticker and
+    /// trades.
+    fn subscription(&self, op: &str) -> String {
+        let args: Vec<Value> = self
+            .symbols
+            .iter()
+            .flat_map(|symbol| ["ticker", "trade"].map(|channel| json!({"instType": "SPOT", "channel": channel, "instId": symbol})))
+            .collect();
+        json!({"op": op, "args": args}).to_string()
+    }
+
+    fn parse_market(message: BitgetMarketMessage) -> Result<Frame, BitgetError> {
+        match (message.arg.channel.as_str(), message.action.as_str()) {
+            ("ticker", _) => {
+                let tickers: Vec<BitgetTickerData> = serde_json::from_value(message.data).map_err(BitgetError::Malformed)?;
+                Ok(Frame::Quotes(
+                    tickers
+                        .into_iter()
+                        .map(|ticker| Quote {
+                            symbol: ticker.inst_id,
+                            bid: ticker.bid_pr,
+                            bid_vol: ticker.bid_sz,
+                            ask: ticker.ask_pr,
+                            ask_vol: ticker.ask_sz,
+                            exchange_ts: Some(ticker.ts),
+                        })
+                        .collect(),
+                ))
+            }
+            // The snapshot is the last trades before we subscribed
+            ("trade", "snapshot") => Ok(Frame::Ignored),
+            ("trade", _) => {
+                let trades: Vec<BitgetTradeData> = serde_json::from_value(message.data).map_err(BitgetError::Malformed)?;
+                Ok(Frame::Trades(
+                    trades
+                        .into_iter()
+                        .map(|trade| Trade {
+                            symbol: message.arg.inst_id.clone(),
+                            price: trade.price,
+                            size: trade.size,
+                            side: trade.side,
+                            trade_id: Some(trade.trade_id),
+                            exchange_ts: Some(trade.ts),
+                        })
+                        .collect(),
+                ))
+            }
+            _ => Err(BitgetError::UnexpectedChannel(message.arg.channel)),
+        }
+    }
+}
+
+impl Default for BitgetSource {
+    fn default() -> Self {
+        Self::new(vec![DEFAULT_SYMBOL.to_string()])
+    }
+}
+
+impl QuoteSource for BitgetSource {
+    fn exchange(&self) -> &'static str {
+        "BITGET"
+    }
+
+    fn symbols(&self) -> &[String] {
+        &self.symbols
+    }
+
+    fn endpoint(&mut self) -> Result<String, FeedError> {
+        Ok(self.endpoint.clone())
+    }
+
+    fn subscribe_messages(&mut self) -> Vec<String> {
+        vec![self.subscription("subscribe")]
+    }
+
+    fn unsubscribe_messages(&mut self) -> Vec<String> {
+        vec![self.subscription("unsubscribe")]
+    }
+
+    fn parse(&mut self, text: &str) -> Frame {
+        // The answer to a text `ping`
+        if text == "pong" {
+            return Frame::Ignored;
+        }
+        match serde_json::from_str::<BitgetMarketMessage>(text) {
+            Ok(message) => Self::parse_market(message).unwrap_or_else(|e| Frame::Error(e.into())),
+            Err(e) => match serde_json::from_str::<BitgetEvent>(text) {
+                Ok(BitgetEvent { event, code, msg }) if event == "error" => Frame::Error(
+                    BitgetError::Rejected { code: code.unwrap_or_default(), msg: msg.unwrap_or_default() }.into(),
+                ),
+                Ok(BitgetEvent { event, .. }) => Frame::Event(format!("Received {} confirmation", event)),
+                Err(_) => Frame::Error(BitgetError::Malformed(e).into()),
+            },
+        }
+    }
+}
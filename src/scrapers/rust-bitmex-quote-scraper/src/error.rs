@@ -0,0 +1,42 @@
+use scraper_core::FeedError;
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug)]
+pub enum BitmexError {
+    /// Neither table data nor a reply BitMEX is known to send, or rows not
+    /// shaped like their table's.
+    Malformed(serde_json::Error),
+    /// Data from a table other than `quote`, `trade`, `instrument` or `funding`.
+    UnexpectedTable(String),
+    /// An error reply, e.g. for an unknown table or symbol.
+    Rejected { status: i64, error: String },
+}
+
+impl fmt::Display for BitmexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BitmexError::Malformed(e) => write!(f, "malformed message: {}", e),
+            BitmexError::UnexpectedTable(table) => write!(f, "unexpected table {}", table),
+            BitmexError::Rejected { status, error } => write!(f, "BitMEX rejected the request: {} (status {})", error, status),
+        }
+    }
+}
+
+impl Error for BitmexError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            BitmexError::Malformed(e) => Some(e),
+            BitmexError::UnexpectedTable(_) | BitmexError::Rejected { .. } => None,
+        }
+    }
+}
+
+impl From<BitmexError> for FeedError {
+    fn from(error: BitmexError) -> Self {
+        match error {
+            BitmexError::Malformed(_) | BitmexError::UnexpectedTable(_) => FeedError::decode(error),
+            BitmexError::Rejected { .. } => FeedError::subscribe(error),
+        }
+    }
+}
@@ -0,0 +1,219 @@
+mod error;
+
+pub use error::BitmexError;
+
+use scraper_core::{rfc3339_millis, FeedError, Frame, Instrument, InstrumentType, PerpStats, Quote, QuoteSource, Side, Trade};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct BitmexMessageQuote {
+    symbol: String,
+    timestamp: String, // 2024-01-03T00:09:50.444Z
+    bid_price: f64,
+    bid_size: f64,
+    ask_price: f64,
+    ask_size: f64
+}
+
+/// A row of the `trade` table; `side` is the taker's and `size` is in
+/// contracts.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct BitmexMessageTrade {
+    symbol: String,
+    timestamp: String,
+    side: Side,
+    size: f64,
+    price: f64,
+    #[serde(rename = "trdMatchID")]
+    trd_match_id: String,
+}
+
+/// A row of the `instrument` table. The partial carries every field, updates
+/// only those that changed, and most of them can be null.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct BitmexMessageInstrument {
+    symbol: String,
+    timestamp: String,
+    funding_rate: Option<f64>,
+    mark_price: Option<f64>,
+    /// The index the swap settles against, `.BXBT` for XBTUSD.
+    indicative_settle_price: Option<f64>,
+    /// In contracts.
+    open_interest: Option<f64>,
+}
+
+/// A row of the `funding` table, sent as each funding is paid.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct BitmexMessageFunding {
+    symbol: String,
+    timestamp: String,
+    funding_rate: f64,
+}
+
+/// Table data; `data` is parsed once `table` says which table it's from.
+#[derive(Serialize, Deserialize, Debug)]
+struct BitmexMessage {
+    table: String,
+    action: String,
+    data: Value
+}
+
+/// The welcome banner (`info`), subscription confirmations (`success`) and
+/// errors (`status` and `error`).
+#[derive(Serialize, Deserialize, Debug)]
+struct BitmexReply {
+    info: Option<String>,
+    success: Option<bool>,
+    subscribe: Option<String>,
+    status: Option<i64>,
+    error: Option<String>,
+}
+
+const BITMEX_WS_API: &str = "wss://ws.bitmex.com/realtime";
+
+const DEFAULT_SYMBOL: &str = "XBTUSD";
+
+pub struct BitmexSource {
+    endpoint: String,
+    symbols: Vec<String>,
+}
+
+impl BitmexSource {
+    pub fn new(symbols: Vec<String>) -> Self {
+        BitmexSource { endpoint: BITMEX_WS_API.to_string(), symbols }
+    }
+
+    /// Connects to `endpoint` instead of `ws.bitmex.com`, e.g. a mock exchange in tests.
+    pub fn with_endpoint(mut self, endpoint: impl Into<String>) -> Self {
+        self.endpoint = endpoint.into();
+        self
+    }
+
+    fn topics(&self) -> Vec<String> {
+        let tables = ["quote", "trade", "instrument", "funding"];
+        self.symbols.iter().flat_map(|symbol| tables.map(|table| format!("{}:{}", table, symbol))).collect()
+    }
+}
+
+impl Default for BitmexSource {
+    fn default() -> Self {
+        Self::new(vec![DEFAULT_SYMBOL.to_string()])
+    }
+}
+
+fn process_bitmex_data(payload: BitmexMessage) -> Result<Frame, BitmexError> {
+    match (payload.table.as_str(), payload.action.as_str()) {
+        (_, "delete") => Ok(Frame::Event(format!("Received {} delete: {}", payload.table, payload.data))),
+        ("quote", "partial" | "update" | "insert") => {
+            let quotes: Vec<BitmexMessageQuote> = serde_json::from_value(payload.data).map_err(BitmexError::Malformed)?;
+            Ok(Frame::Quotes(
+                quotes
+                    .into_iter()
+                    .map(|quote| Quote {
+                        symbol: quote.symbol,
+                        bid: quote.bid_price,
+                        bid_vol: quote.bid_size,
+                        ask: quote.ask_price,
+                        ask_vol: quote.ask_size,
+                        exchange_ts: rfc3339_millis(&quote.timestamp),
+                    })
+                    .collect(),
+            ))
+        }
+        // The partial is the last trades before we connected, already written or missed
+        ("trade", "partial") => Ok(Frame::Ignored),
+        ("trade", "insert") => {
+            let trades: Vec<BitmexMessageTrade> = serde_json::from_value(payload.data).map_err(BitmexError::Malformed)?;
+            Ok(Frame::Trades(
+                trades
+                    .into_iter()
+                    .map(|trade| Trade {
+                        exchange_ts: rfc3339_millis(&trade.timestamp),
+                        symbol: trade.symbol,
+                        price: trade.price,
+                        size: trade.size,
+                        side: trade.side,
+                        trade_id: Some(trade.trd_match_id),
+                    })
+                    .collect(),
+            ))
+        }
+        ("instrument", "partial" | "update" | "insert") => {
+            let rows: Vec<BitmexMessageInstrument> = serde_json::from_value(payload.data).map_err(BitmexError::Malformed)?;
+            let perps: Vec<PerpStats> = rows
+                .into_iter()
+                .map(|row| PerpStats {
+                    exchange_ts: rfc3339_millis(&row.timestamp),
+                    symbol: row.symbol,
+                    funding_rate: row.funding_rate,
+                    funding_rate_paid: None,
+                    mark_price: row.mark_price,
+                    index_price: row.indicative_settle_price,
+                    open_interest: row.open_interest,
+                })
+                // Most updates only move fields we don't store, e.g. `lastPrice` or `volume`
+                .filter(|perp| !perp.is_empty())
+                .collect();
+            Ok(if perps.is_empty() { Frame::Ignored } else { Frame::Perps(perps) })
+        }
+        ("funding", "partial" | "insert") => {
+            let rows: Vec<BitmexMessageFunding> = serde_json::from_value(payload.data).map_err(BitmexError::Malformed)?;
+            Ok(Frame::Perps(
+                rows.into_iter()
+                    .map(|row| PerpStats {
+                        exchange_ts: rfc3339_millis(&row.timestamp),
+                        symbol: row.symbol,
+                        funding_rate_paid: Some(row.funding_rate),
+                        ..PerpStats::default()
+                    })
+                    .collect(),
+            ))
+        }
+        ("quote" | "trade" | "instrument" | "funding", _) => Ok(Frame::Event("Unknown action key value".to_string())),
+        _ => Err(BitmexError::UnexpectedTable(payload.table)),
+    }
+}
+
+impl QuoteSource for BitmexSource {
+    fn exchange(&self) -> &'static str {
+        "BITMEX"
+    }
+
+    fn symbols(&self) -> &[String] {
+        &self.symbols
+    }
+
+    fn instrument(&self, symbol: &str) -> Option<Instrument> {
+        // The `quote` table only carries derivatives; the bare pairs are perpetual swaps
+        Instrument::parse(symbol, InstrumentType::Perpetual)
+    }
+
+    fn endpoint(&mut self) -> Result<String, FeedError> {
+        Ok(format!("{}?subscribe={}", self.endpoint, self.topics().join(",")))
+    }
+
+    fn unsubscribe_messages(&mut self) -> Vec<String> {
+        vec![json!({"op": "unsubscribe", "args": self.topics()}).to_string()]
+    }
+
+    fn parse(&mut self, text: &str) -> Frame {
+        match serde_json::from_str::<BitmexMessage>(text) {
+            Ok(data) => process_bitmex_data(data).unwrap_or_else(|e| Frame::Error(e.into())),
+            Err(e) => match serde_json::from_str::<BitmexReply>(text) {
+                Ok(BitmexReply { error: Some(error), status, .. }) => {
+                    Frame::Error(BitmexError::Rejected { status: status.unwrap_or_default(), error }.into())
+                }
+                Ok(BitmexReply { info: Some(info), .. }) => Frame::Event(info),
+                Ok(BitmexReply { success: Some(true), subscribe, .. }) => {
+                    Frame::Event(format!("Subscribed to {}", subscribe.unwrap_or_default()))
+                }
+                _ => Frame::Error(BitmexError::Malformed(e).into()),
+            },
+        }
+    }
+}
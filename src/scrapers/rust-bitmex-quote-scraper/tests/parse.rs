@@ -0,0 +1,16 @@
+use mock_exchange::{fixture_frames, parse_frames, parse_mangled};
+use rust_bitmex_quote_scraper::BitmexSource;
+
+#[test]
+fn parses_fixtures() {
+    insta::glob!("fixtures/*.jsonl", |path| {
+        insta::assert_debug_snapshot!(parse_frames(&mut BitmexSource::default(), &fixture_frames(path)));
+    });
+}
+
+#[test]
+fn never_panics_on_mangled_fixtures() {
+    insta::glob!("fixtures/*.jsonl", |path| {
+        parse_mangled(BitmexSource::default, &fixture_frames(path));
+    });
+}
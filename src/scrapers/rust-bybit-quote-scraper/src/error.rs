@@ -0,0 +1,42 @@
+use scraper_core::FeedError;
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug)]
+pub enum BybitError {
+    /// Neither a market data push nor an operation reply, or a push whose
+    /// data isn't shaped like its topic's.
+    Malformed(serde_json::Error),
+    /// A push on a topic we never subscribe to.
+    UnexpectedTopic(String),
+    /// An unsuccessful reply, e.g. for a symbol Bybit doesn't list.
+    Rejected { op: String, msg: String },
+}
+
+impl fmt::Display for BybitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BybitError::Malformed(e) => write!(f, "malformed message: {}", e),
+            BybitError::UnexpectedTopic(topic) => write!(f, "unexpected topic {}", topic),
+            BybitError::Rejected { op, msg } => write!(f, "Bybit rejected {}: {}", op, msg),
+        }
+    }
+}
+
+impl Error for BybitError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            BybitError::Malformed(e) => Some(e),
+            BybitError::UnexpectedTopic(_) | BybitError::Rejected { .. } => None,
+        }
+    }
+}
+
+impl From<BybitError> for FeedError {
+    fn from(error: BybitError) -> Self {
+        match error {
+            BybitError::Malformed(_) | BybitError::UnexpectedTopic(_) => FeedError::decode(error),
+            BybitError::Rejected { .. } => FeedError::subscribe(error),
+        }
+    }
+}
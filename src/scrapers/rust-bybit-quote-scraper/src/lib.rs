@@ -0,0 +1,281 @@
+mod error;
+
+pub use error::BybitError;
+
+use scraper_core::{
+    de_float_from_str, de_opt_float_from_str, FeedError, Frame, Instrument, InstrumentType, PerpStats, Quote, QuoteSource,
+    Side, TickerStats, Trade,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+/// A `[price, size]` book level; a size of zero removes the price.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+struct BybitLevel(
+    #[serde(deserialize_with = "de_float_from_str")] f64,
+    #[serde(deserialize_with = "de_float_from_str")] f64,
+);
+
+/// An `orderbook.1` snapshot or delta.
+#[derive(Serialize, Deserialize, Debug)]
+struct BybitOrderbookData {
+    s: String,
+    b: Vec<BybitLevel>,
+    a: Vec<BybitLevel>,
+}
+
+/// The fields of a spot `tickers` push we read: the last price and rolling
+/// 24h base volume. The other 24h stats and `usdIndexPrice`, which only some
+/// symbols carry, are ignored.
+#[derive(Serialize, Deserialize, Debug)]
+struct BybitTickerData {
+    symbol: String,
+    #[serde(rename = "lastPrice", deserialize_with = "de_float_from_str")]
+    last_price: f64,
+    #[serde(rename = "volume24h", deserialize_with = "de_float_from_str")]
+    volume24h: f64,
+}
+
+/// The fields of a linear `tickers` push we read. The snapshot carries them
+/// all, deltas only those that changed.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct BybitLinearTickerData {
+    symbol: String,
+    #[serde(default, deserialize_with = "de_opt_float_from_str")]
+    funding_rate: Option<f64>,
+    #[serde(default, deserialize_with = "de_opt_float_from_str")]
+    mark_price: Option<f64>,
+    #[serde(default, deserialize_with = "de_opt_float_from_str")]
+    index_price: Option<f64>,
+    /// In the base currency.
+    #[serde(default, deserialize_with = "de_opt_float_from_str")]
+    open_interest: Option<f64>,
+}
+
+/// One trade in a `publicTrade` push; `S` is the taker's side. Block trades
+/// (`BT`) are on the tape like any other.
+#[derive(Serialize, Deserialize, Debug)]
+struct BybitTradeData {
+    s: String,
+    #[serde(rename = "S")]
+    side: Side,
+    #[serde(deserialize_with = "de_float_from_str")]
+    p: f64,
+    #[serde(deserialize_with = "de_float_from_str")]
+    v: f64,
+    i: String, // trade id
+    #[serde(rename = "T")]
+    time: u64,
+}
+
+/// A market data push; `data` is parsed once `topic` says which stream it's from.
+#[derive(Serialize, Deserialize, Debug)]
+struct BybitMarketMessage {
+    topic: String, // orderbook.1.BTCUSDT,
+    #[serde(rename = "type")]
+    kind: String, // snapshot or delta,
+    ts: u64, // 1708270510698,
+    data: Value,
+}
+
+/// A symbol's level 1 book as the last snapshot and the deltas since left it.
+#[derive(Debug, Default)]
+struct TopOfBook {
+    bid: Option<BybitLevel>,
+    ask: Option<BybitLevel>,
+}
+
+impl TopOfBook {
+    fn apply(side: &mut Option<BybitLevel>, levels: &[BybitLevel]) {
+        for level in levels {
+            if level.1 != 0.0 {
+                *side = Some(*level);
+            } else if side.is_some_and(|top| top.0 == level.0) {
+                *side = None;
+            }
+        }
+    }
+
+    fn update(&mut self, data: &BybitOrderbookData) {
+        Self::apply(&mut self.bid, &data.b);
+        Self::apply(&mut self.ask, &data.a);
+    }
+}
+
+/// The reply to a `subscribe`, `unsubscribe` or `ping` operation.
+#[derive(Serialize, Deserialize, Debug)]
+struct BybitReply {
+    success: bool,
+    ret_msg: String,
+    op: String,
+}
+
+const BYBIT_WS_API: &str = "wss://stream.bybit.com/v5/public/spot";
+
+const BYBIT_LINEAR_WS_API: &str = "wss://stream.bybit.com/v5/public/linear";
+
+const DEFAULT_SYMBOL: &str = "BTCUSDT";
+
+/// Bybit spot turns down a request with more topics than this.
+const MAX_ARGS_PER_REQUEST: usize = 10;
+
+pub struct BybitSource {
+    endpoint: String,
+    symbols: Vec<String>,
+    /// USDT and USDC perpetuals rather than spot.
+    linear: bool,
+    /// Level 1 book by Bybit symbol, reset on every (re)connect.
+    books: HashMap<String, TopOfBook>,
+}
+
+impl BybitSource {
+    pub fn new(symbols: Vec<String>) -> Self {
+        BybitSource { endpoint: BYBIT_WS_API.to_string(), symbols, linear: false, books: HashMap::new() }
+    }
+
+    /// Takes the symbols as USDT and USDC perpetuals from the linear endpoint
+    /// instead of spot, written as `BYBIT_LINEAR`. Their tickers carry funding,
+    /// mark, index and open interest instead of 24h stats. Call it before
+    /// [`Self::with_endpoint`].
+    pub fn linear(mut self) -> Self {
+        self.endpoint = BYBIT_LINEAR_WS_API.to_string();
+        self.linear = true;
+        self
+    }
+
+    /// Connects to `endpoint` instead of `stream.bybit.com`, e.g. a mock exchange in tests.
+    pub fn with_endpoint(mut self, endpoint: impl Into<String>) -> Self {
+        self.endpoint = endpoint.into();
+        self
+    }
+
+    /// `subscribe` or `unsubscribe` requests for every symbol's level 1 book,
+    /// ticker and public trades, at most [`MAX_ARGS_PER_REQUEST`] topics each.
+    fn subscriptions(&self, op: &str) -> Vec<String> {
+        let topics: Vec<String> = self
+            .symbols
+            .iter()
+            .flat_map(|symbol| [format!("orderbook.1.{}", symbol), format!("tickers.{}", symbol), format!("publicTrade.{}", symbol)])
+            .collect();
+        topics
+            .chunks(MAX_ARGS_PER_REQUEST)
+            .map(|args| json!({"op": op, "args": args}).to_string())
+            .collect()
+    }
+
+    fn parse_market(&mut self, message: BybitMarketMessage) -> Result<Frame, BybitError> {
+        if message.topic.starts_with("orderbook.1.") {
+            let data: BybitOrderbookData = serde_json::from_value(message.data).map_err(BybitError::Malformed)?;
+            let book = self.books.entry(data.s.clone()).or_default();
+            if message.kind == "snapshot" {
+                *book = TopOfBook::default();
+            }
+            book.update(&data);
+            // Until both sides are known there is no quote to write
+            let quotes = match (book.bid, book.ask) {
+                (Some(BybitLevel(bid, bid_vol)), Some(BybitLevel(ask, ask_vol))) => {
+                    vec![Quote { symbol: data.s, bid, bid_vol, ask, ask_vol, exchange_ts: Some(message.ts) }]
+                }
+                _ => Vec::new(),
+            };
+            Ok(Frame::Quotes(quotes))
+        } else if message.topic.starts_with("tickers.") && self.linear {
+            let data: BybitLinearTickerData = serde_json::from_value(message.data).map_err(BybitError::Malformed)?;
+            let perp = PerpStats {
+                symbol: data.symbol,
+                funding_rate: data.funding_rate,
+                funding_rate_paid: None,
+                mark_price: data.mark_price,
+                index_price: data.index_price,
+                open_interest: data.open_interest,
+                exchange_ts: Some(message.ts),
+            };
+            // Deltas that only moved the 24h figures or the last price
+            if perp.is_empty() {
+                return Ok(Frame::Ignored);
+            }
+            Ok(Frame::Perps(vec![perp]))
+        } else if message.topic.starts_with("tickers.") {
+            let data: BybitTickerData = serde_json::from_value(message.data).map_err(BybitError::Malformed)?;
+            Ok(Frame::Stats(vec![TickerStats {
+                symbol: data.symbol,
+                last: data.last_price,
+                volume: data.volume24h,
+                exchange_ts: Some(message.ts),
+            }]))
+        } else if message.topic.starts_with("publicTrade.") {
+            let trades: Vec<BybitTradeData> = serde_json::from_value(message.data).map_err(BybitError::Malformed)?;
+            Ok(Frame::Trades(
+                trades
+                    .into_iter()
+                    .map(|trade| Trade {
+                        symbol: trade.s,
+                        price: trade.p,
+                        size: trade.v,
+                        side: trade.side,
+                        trade_id: Some(trade.i),
+                        exchange_ts: Some(trade.time),
+                    })
+                    .collect(),
+            ))
+        } else {
+            Err(BybitError::UnexpectedTopic(message.topic))
+        }
+    }
+}
+
+impl Default for BybitSource {
+    fn default() -> Self {
+        Self::new(vec![DEFAULT_SYMBOL.to_string()])
+    }
+}
+
+impl QuoteSource for BybitSource {
+    fn exchange(&self) -> &'static str {
+        if self.linear {
+            "BYBIT_LINEAR"
+        } else {
+            "BYBIT"
+        }
+    }
+
+    fn symbols(&self) -> &[String] {
+        &self.symbols
+    }
+
+    fn instrument(&self, symbol: &str) -> Option<Instrument> {
+        let kind = if self.linear { InstrumentType::Perpetual } else { InstrumentType::Spot };
+        Instrument::parse(symbol, kind)
+    }
+
+    // Linear tickers count open interest in the base coin, e.g. BTC for BTCUSDT
+    fn open_interest_unit(&self) -> &'static str {
+        "BASE"
+    }
+
+    fn endpoint(&mut self) -> Result<String, FeedError> {
+        Ok(self.endpoint.clone())
+    }
+
+    fn subscribe_messages(&mut self) -> Vec<String> {
+        self.books.clear();
+        self.subscriptions("subscribe")
+    }
+
+    fn unsubscribe_messages(&mut self) -> Vec<String> {
+        self.subscriptions("unsubscribe")
+    }
+
+    fn parse(&mut self, text: &str) -> Frame {
+        match serde_json::from_str::<BybitMarketMessage>(text) {
+            Ok(message) => self.parse_market(message).unwrap_or_else(|e| Frame::Error(e.into())),
+            Err(e) => match serde_json::from_str::<BybitReply>(text) {
+                Ok(BybitReply { success: false, ret_msg, op }) => Frame::Error(BybitError::Rejected { op, msg: ret_msg }.into()),
+                Ok(BybitReply { op, .. }) => Frame::Event(format!("Received {} confirmation", op)),
+                Err(_) => Frame::Error(BybitError::Malformed(e).into()),
+            },
+        }
+    }
+}
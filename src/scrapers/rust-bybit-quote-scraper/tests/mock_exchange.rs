@@ -0,0 +1,42 @@
+use mock_exchange::{spawn_feed, Dialect, MockExchange, MockQuote, MockRedis};
+use rust_bybit_quote_scraper::BybitSource;
+use serde_json::Value;
+
+fn quote(bid: f64) -> MockQuote {
+    MockQuote { bid, bid_vol: 0.5, ask: bid + 1.0, ask_vol: 0.25, ts: 1_700_000_000_000 }
+}
+
+fn args(request: &str) -> Vec<String> {
+    let request: Value = serde_json::from_str(request).unwrap();
+    request["args"].as_array().unwrap().iter().map(|arg| arg.as_str().unwrap().to_string()).collect()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn splits_subscriptions_bybit_would_turn_down() {
+    let symbols = ["BTCUSDT", "ETHUSDT", "SOLUSDT", "XRPUSDT"];
+    let redis = MockRedis::start().await;
+    let mut exchange = MockExchange::start(Dialect::Bybit).await;
+    let source = BybitSource::new(symbols.iter().map(|symbol| symbol.to_string()).collect());
+    let feed = spawn_feed(source.with_endpoint(exchange.endpoint()), &redis).await;
+
+    // 12 topics, so two requests of at most 10
+    let mut session = exchange.session().await;
+    let subscriptions = session.expect_subscriptions(2).await;
+    let topics: Vec<String> = subscriptions.iter().flat_map(|request| args(request)).collect();
+    assert_eq!(subscriptions.iter().map(|request| args(request).len()).collect::<Vec<_>>(), [10, 2]);
+    assert_eq!(topics.len(), 12);
+    assert!(topics.contains(&"publicTrade.XRPUSDT".to_string()));
+
+    for (bid, symbol) in [43000.0, 2300.0, 100.0, 0.5].into_iter().zip(symbols) {
+        session.send_quote(symbol, quote(bid)).await;
+    }
+    for (bid, key) in [(43000.0, "BTC-USDT"), (2300.0, "ETH-USDT"), (100.0, "SOL-USDT"), (0.5, "XRP-USDT")] {
+        let samples = redis.wait_for_samples(&format!("BYBIT:{}:QUOTE:BUY:PRICE", key), 1).await;
+        assert_eq!(samples, [(1_700_000_000_000, bid)]);
+    }
+
+    let stopping = tokio::spawn(feed.stop());
+    let unsubscribes = session.expect_close().await;
+    assert_eq!(unsubscribes.iter().map(|request| args(request).len()).collect::<Vec<_>>(), [10, 2]);
+    stopping.await.unwrap().unwrap();
+}
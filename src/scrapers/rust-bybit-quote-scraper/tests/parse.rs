@@ -0,0 +1,30 @@
+use mock_exchange::{fixture_frames, parse_frames, parse_mangled};
+use rust_bybit_quote_scraper::BybitSource;
+
+#[test]
+fn parses_fixtures() {
+    insta::glob!("fixtures/*.jsonl", |path| {
+        insta::assert_debug_snapshot!(parse_frames(&mut BybitSource::default(), &fixture_frames(path)));
+    });
+}
+
+#[test]
+fn parses_linear_fixtures() {
+    insta::glob!("fixtures/linear/*.jsonl", |path| {
+        insta::assert_debug_snapshot!(parse_frames(&mut linear(), &fixture_frames(path)));
+    });
+}
+
+#[test]
+fn never_panics_on_mangled_fixtures() {
+    insta::glob!("fixtures/*.jsonl", |path| {
+        parse_mangled(BybitSource::default, &fixture_frames(path));
+    });
+    insta::glob!("fixtures/linear/*.jsonl", |path| {
+        parse_mangled(linear, &fixture_frames(path));
+    });
+}
+
+fn linear() -> BybitSource {
+    BybitSource::default().linear()
+}
@@ -0,0 +1,38 @@
+use scraper_core::FeedError;
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug)]
+pub enum CoinbaseError {
+    /// A ticker or match we can't read, or no JSON object with a `type` at all.
+    Malformed(serde_json::Error),
+    /// An `error` message, e.g. for a product Coinbase doesn't list.
+    Rejected { message: String, reason: String },
+}
+
+impl fmt::Display for CoinbaseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CoinbaseError::Malformed(e) => write!(f, "malformed message: {}", e),
+            CoinbaseError::Rejected { message, reason } => write!(f, "Coinbase rejected the request: {}: {}", message, reason),
+        }
+    }
+}
+
+impl Error for CoinbaseError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            CoinbaseError::Malformed(e) => Some(e),
+            CoinbaseError::Rejected { .. } => None,
+        }
+    }
+}
+
+impl From<CoinbaseError> for FeedError {
+    fn from(error: CoinbaseError) -> Self {
+        match error {
+            CoinbaseError::Malformed(_) => FeedError::decode(error),
+            CoinbaseError::Rejected { .. } => FeedError::subscribe(error),
+        }
+    }
+}
@@ -0,0 +1,146 @@
+mod error;
+
+pub use error::CoinbaseError;
+
+use scraper_core::{de_float_from_str, rfc3339_millis, FeedError, Frame, Quote, QuoteSource, Side, Trade};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+/// The fields of a `ticker` message we read. Coinbase sends many more
+/// (`price`, `open_24h`, `volume_30d`, `trade_id`, ...); they are ignored so
+/// adding or dropping one doesn't break parsing.
+#[derive(Serialize, Deserialize, Debug)]
+struct CoinbaseMessageQuote {
+    product_id: String, // "ETH-USD",
+    #[serde(deserialize_with = "de_float_from_str")]
+    best_bid: f64, // "1285.04",
+    #[serde(deserialize_with = "de_float_from_str")]
+    best_bid_size: f64, // "0.46688654",
+    #[serde(deserialize_with = "de_float_from_str")]
+    best_ask: f64, // "1285.27",
+    #[serde(deserialize_with = "de_float_from_str")]
+    best_ask_size: f64, // "1.56637040",
+    time: Option<String>, // "2022-10-19T23:28:22.061769Z",
+}
+
+/// A `match` from the `matches` channel. `side` is the maker order's, so the
+/// taker, who we count as the aggressor, was on the other side.
+#[derive(Serialize, Deserialize, Debug)]
+struct CoinbaseMessageMatch {
+    product_id: String,
+    trade_id: u64,
+    #[serde(deserialize_with = "de_float_from_str")]
+    price: f64,
+    #[serde(deserialize_with = "de_float_from_str")]
+    size: f64,
+    side: Side,
+    time: String,
+}
+
+/// Any other message: `subscriptions` confirmations, and `error` with its
+/// `message` and `reason`.
+#[derive(Serialize, Deserialize, Debug)]
+struct CoinbaseReply {
+    r#type: String,
+    message: Option<String>,
+    reason: Option<String>,
+}
+
+const COINBASE_WS_API: &str = "wss://ws-feed.exchange.coinbase.com";
+
+const DEFAULT_SYMBOL: &str = "BTC-USD";
+
+pub struct CoinbaseSource {
+    endpoint: String,
+    symbols: Vec<String>,
+}
+
+impl CoinbaseSource {
+    pub fn new(symbols: Vec<String>) -> Self {
+        CoinbaseSource { endpoint: COINBASE_WS_API.to_string(), symbols }
+    }
+
+    /// Connects to `endpoint` instead of `ws-feed.exchange.coinbase.com`, e.g. a mock exchange in tests.
+    pub fn with_endpoint(mut self, endpoint: impl Into<String>) -> Self {
+        self.endpoint = endpoint.into();
+        self
+    }
+
+    /// A `subscribe` or `unsubscribe` request for the ticker and matches
+    /// channels of every product.
+    fn subscription(&self, op: &str) -> String {
+        json!({"type": op, "channels": ["ticker", "matches"], "product_ids": self.symbols}).to_string()
+    }
+}
+
+impl Default for CoinbaseSource {
+    fn default() -> Self {
+        Self::new(vec![DEFAULT_SYMBOL.to_string()])
+    }
+}
+
+impl QuoteSource for CoinbaseSource {
+    fn exchange(&self) -> &'static str {
+        "COINBASE"
+    }
+
+    fn symbols(&self) -> &[String] {
+        &self.symbols
+    }
+
+    fn endpoint(&mut self) -> Result<String, FeedError> {
+        Ok(self.endpoint.clone())
+    }
+
+    fn subscribe_messages(&mut self) -> Vec<String> {
+        vec![self.subscription("subscribe")]
+    }
+
+    fn unsubscribe_messages(&mut self) -> Vec<String> {
+        vec![self.subscription("unsubscribe")]
+    }
+
+    fn parse(&mut self, text: &str) -> Frame {
+        let reply = match serde_json::from_str::<CoinbaseReply>(text) {
+            Ok(reply) => reply,
+            Err(e) => return Frame::Error(CoinbaseError::Malformed(e).into()),
+        };
+        match reply.r#type.as_str() {
+            "ticker" => match serde_json::from_str::<CoinbaseMessageQuote>(text) {
+                Ok(quote) => Frame::Quotes(vec![Quote {
+                    symbol: quote.product_id,
+                    bid: quote.best_bid,
+                    bid_vol: quote.best_bid_size,
+                    ask: quote.best_ask,
+                    ask_vol: quote.best_ask_size,
+                    exchange_ts: quote.time.as_deref().and_then(rfc3339_millis),
+                }]),
+                Err(e) => Frame::Error(CoinbaseError::Malformed(e).into()),
+            },
+            "match" => match serde_json::from_str::<CoinbaseMessageMatch>(text) {
+                Ok(trade) => Frame::Trades(vec![Trade {
+                    exchange_ts: rfc3339_millis(&trade.time),
+                    symbol: trade.product_id,
+                    price: trade.price,
+                    size: trade.size,
+                    side: match trade.side {
+                        Side::Buy => Side::Sell,
+                        Side::Sell => Side::Buy,
+                    },
+                    trade_id: Some(trade.trade_id.to_string()),
+                }]),
+                Err(e) => Frame::Error(CoinbaseError::Malformed(e).into()),
+            },
+            // The last trade before we subscribed, sent once per product
+            "last_match" => Frame::Ignored,
+            "error" => Frame::Error(
+                CoinbaseError::Rejected {
+                    message: reply.message.unwrap_or_default(),
+                    reason: reply.reason.unwrap_or_default(),
+                }
+                .into(),
+            ),
+            other => Frame::Event(format!("Received {}", other)),
+        }
+    }
+}
@@ -0,0 +1,39 @@
+use scraper_core::FeedError;
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug)]
+pub enum GateioError {
+    /// No `channel` and `event` envelope, or a ticker or trade update we
+    /// can't read.
+    Malformed(serde_json::Error),
+    /// A request answered with an `error`, e.g. for an unknown currency pair.
+    Rejected { code: i64, message: String },
+}
+
+impl fmt::Display for GateioError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GateioError::Malformed(e) => write!(f, "malformed message: {}", e),
+            GateioError::Rejected { code, message } => write!(f, "Gate.io rejected the request: {} (code {})", message, code),
+        }
+    }
+}
+
+impl Error for GateioError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            GateioError::Malformed(e) => Some(e),
+            GateioError::Rejected { .. } => None,
+        }
+    }
+}
+
+impl From<GateioError> for FeedError {
+    fn from(error: GateioError) -> Self {
+        match error {
+            GateioError::Malformed(_) => FeedError::decode(error),
+            GateioError::Rejected { .. } => FeedError::subscribe(error),
+        }
+    }
+}
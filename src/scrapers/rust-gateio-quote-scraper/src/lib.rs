@@ -0,0 +1,166 @@
+mod error;
+
+pub use error::GateioError;
+
+use scraper_core::{de_float_from_str, get_current_timestamp, FeedError, Frame, Quote, QuoteSource, Side, Trade};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+/// The fields of a `spot.tickers` result we read; `last`, the 24h high and
+/// low and the change are ignored.
+#[derive(Serialize, Deserialize, Debug)]
+struct GateioTickerData {
+    currency_pair: String,
+    #[serde(deserialize_with = "de_float_from_str")]
+    lowest_ask: f64,
+    #[serde(deserialize_with = "de_float_from_str")]
+    highest_bid: f64,
+    #[serde(deserialize_with = "de_float_from_str")]
+    base_volume: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct GateioTickerMessage {
+    time: u64,
+    time_ms: Option<u64>,
+    result: GateioTickerData,
+}
+
+/// A `spot.trades` result; `side` is the taker's.
+#[derive(Serialize, Deserialize, Debug)]
+struct GateioTradeData {
+    id: u64,
+    /// Epoch millis with a fractional part, as a string.
+    #[serde(deserialize_with = "de_float_from_str")]
+    create_time_ms: f64,
+    side: Side,
+    currency_pair: String,
+    #[serde(deserialize_with = "de_float_from_str")]
+    amount: f64,
+    #[serde(deserialize_with = "de_float_from_str")]
+    price: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct GateioTradeMessage {
+    result: GateioTradeData,
+}
+
+/// What every message carries, telling updates apart from request replies.
+#[derive(Serialize, Deserialize, Debug)]
+struct GateioEnvelope {
+    channel: String,
+    event: String,
+    error: Option<GateioApiError>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct GateioApiError {
+    code: i64,
+    message: String,
+}
+
+const GATEIO_WS_API: &str = "wss://api.gateio.ws/ws/v4/";
+
+const CHANNELS: [&str; 2] = ["spot.tickers", "spot.trades"];
+
+const DEFAULT_SYMBOL: &str = "BTC_USDT";
+
+pub struct GateioSource {
+    endpoint: String,
+    symbols: Vec<String>,
+}
+
+impl GateioSource {
+    pub fn new(symbols: Vec<String>) -> Self {
+        GateioSource { endpoint: GATEIO_WS_API.to_string(), symbols }
+    }
+
+    /// Connects to `endpoint` instead of `api.gateio.ws`, e.g. a mock exchange in tests.
+    pub fn with_endpoint(mut self, endpoint: impl Into<String>) -> Self {
+        self.endpoint = endpoint.into();
+        self
+    }
+
+    /// `subscribe` or `unsubscribe` requests for every symbol's ticker and
+    /// trades.
+    fn subscriptions(&self, op: &str) -> Vec<String> {
+        let time = get_current_timestamp() / 1000;
+        CHANNELS
+            .iter()
+            .map(|channel| json!({"time": time, "channel": channel, "event": op, "payload": self.symbols}).to_string())
+            .collect()
+    }
+
+    fn parse_update(channel: &str, text: &str) -> Result<Frame, GateioError> {
+        if channel == "spot.trades" {
+            let data: GateioTradeMessage = serde_json::from_str(text).map_err(GateioError::Malformed)?;
+            return Ok(Frame::Trades(vec![Trade {
+                symbol: data.result.currency_pair,
+                price: data.result.price,
+                size: data.result.amount,
+                side: data.result.side,
+                trade_id: Some(data.result.id.to_string()),
+                exchange_ts: Some(data.result.create_time_ms as u64),
+            }]));
+        }
+
+        let data: GateioTickerMessage = serde_json::from_str(text).map_err(GateioError::Malformed)?;
+        let volume = data.result.base_volume;
+        Ok(Frame::Quotes(vec![Quote {
+            symbol: data.result.currency_pair,
+            bid: data.result.highest_bid,
+            bid_vol: volume / 2.0,
+            ask: data.result.lowest_ask,
+            ask_vol: volume / 2.0,
+            exchange_ts: Some(data.time_ms.unwrap_or(data.time.saturating_mul(1000))),
+        }]))
+    }
+}
+
+impl Default for GateioSource {
+    fn default() -> Self {
+        Self::new(vec![DEFAULT_SYMBOL.to_string()])
+    }
+}
+
+impl QuoteSource for GateioSource {
+    fn exchange(&self) -> &'static str {
+        "GATEIO"
+    }
+
+    fn symbols(&self) -> &[String] {
+        &self.symbols
+    }
+
+    fn endpoint(&mut self) -> Result<String, FeedError> {
+        Ok(self.endpoint.clone())
+    }
+
+    fn subscribe_messages(&mut self) -> Vec<String> {
+        self.subscriptions("subscribe")
+    }
+
+    fn unsubscribe_messages(&mut self) -> Vec<String> {
+        self.subscriptions("unsubscribe")
+    }
+
+    fn parse(&mut self, text: &str) -> Frame {
+        let envelope = match serde_json::from_str::<GateioEnvelope>(text) {
+            Ok(envelope) => envelope,
+            Err(e) => return Frame::Error(GateioError::Malformed(e).into()),
+        };
+        // Pongs
+        if !CHANNELS.contains(&envelope.channel.as_str()) {
+            return Frame::Ignored;
+        }
+        if let Some(error) = envelope.error {
+            return Frame::Error(GateioError::Rejected { code: error.code, message: error.message }.into());
+        }
+        if envelope.event != "update" {
+            return Frame::Event(format!("Received {} confirmation", envelope.event));
+        }
+
+        Self::parse_update(&envelope.channel, text).unwrap_or_else(|e| Frame::Error(e.into()))
+    }
+}
@@ -0,0 +1,16 @@
+use mock_exchange::{fixture_frames, parse_frames, parse_mangled};
+use rust_gateio_quote_scraper::GateioSource;
+
+#[test]
+fn parses_fixtures() {
+    insta::glob!("fixtures/*.jsonl", |path| {
+        insta::assert_debug_snapshot!(parse_frames(&mut GateioSource::default(), &fixture_frames(path)));
+    });
+}
+
+#[test]
+fn never_panics_on_mangled_fixtures() {
+    insta::glob!("fixtures/*.jsonl", |path| {
+        parse_mangled(GateioSource::default, &fixture_frames(path));
+    });
+}
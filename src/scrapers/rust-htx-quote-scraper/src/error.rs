@@ -0,0 +1,51 @@
+use scraper_core::FeedError;
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+#[derive(Debug)]
+pub enum HtxError {
+    /// A binary frame that isn't gzip, or doesn't inflate to UTF-8.
+    Gzip(io::Error),
+    /// A binary frame that inflates past the given number of bytes.
+    TooLarge(u64),
+    /// Neither a market data push, a ping nor a request status, or a push
+    /// whose tick isn't shaped like its channel's.
+    Malformed(serde_json::Error),
+    /// A push whose `ch` isn't `market.$symbol.bbo`, `market.$symbol.ticker`
+    /// or `market.$symbol.trade.detail`.
+    UnexpectedChannel(String),
+    /// An `error` status, e.g. for an invalid topic.
+    Rejected { code: String, msg: String },
+}
+
+impl fmt::Display for HtxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HtxError::Gzip(e) => write!(f, "can't inflate frame: {}", e),
+            HtxError::TooLarge(limit) => write!(f, "frame inflates past {} bytes", limit),
+            HtxError::Malformed(e) => write!(f, "malformed message: {}", e),
+            HtxError::UnexpectedChannel(channel) => write!(f, "unexpected channel {}", channel),
+            HtxError::Rejected { code, msg } => write!(f, "HTX rejected the request: {} ({})", msg, code),
+        }
+    }
+}
+
+impl Error for HtxError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            HtxError::Gzip(e) => Some(e),
+            HtxError::Malformed(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<HtxError> for FeedError {
+    fn from(error: HtxError) -> Self {
+        match error {
+            HtxError::Rejected { .. } => FeedError::subscribe(error),
+            _ => FeedError::decode(error),
+        }
+    }
+}
@@ -0,0 +1,225 @@
+mod error;
+
+pub use error::HtxError;
+
+use flate2::read::GzDecoder;
+use scraper_core::{FeedError, Frame, Quote, QuoteSource, Side, TickerStats, Trade};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::io::Read;
+
+/// A `market.$symbol.bbo` tick, pushed whenever the best bid or offer changes.
+#[derive(Serialize, Deserialize, Debug)]
+struct HtxBboData {
+    bid: f64,
+    #[serde(rename = "bidSize")]
+    bid_size: f64,
+    ask: f64,
+    #[serde(rename = "askSize")]
+    ask_size: f64,
+    #[serde(rename = "quoteTime")]
+    quote_time: u64,
+}
+
+/// The fields of a `market.$symbol.ticker` tick we read: the last price and
+/// the rolling 24h base volume (`vol` is in the quote currency). The OHLC and
+/// trade counts beside them are ignored, and so is its top of book, which
+/// comes from `bbo`.
+#[derive(Serialize, Deserialize, Debug)]
+struct HtxTickerData {
+    close: f64,
+    amount: f64,
+}
+
+/// One trade in a `market.$symbol.trade.detail` tick; `direction` is the
+/// taker's side. `tradeId` supersedes the deprecated `id`.
+#[derive(Serialize, Deserialize, Debug)]
+struct HtxTradeDetail {
+    #[serde(rename = "tradeId")]
+    trade_id: u64,
+    ts: u64,
+    amount: f64,
+    price: f64,
+    direction: Side,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct HtxTradeDetailData {
+    data: Vec<HtxTradeDetail>,
+}
+
+/// A market data push; `tick` is parsed once `ch` says which channel it's from.
+#[derive(Serialize, Deserialize, Debug)]
+struct HtxMarketMessage {
+    ch: String,
+    ts: u64,
+    tick: Value,
+}
+
+/// The status of a `sub` or `unsub` request.
+#[derive(Serialize, Deserialize, Debug)]
+struct HtxStatusReply {
+    status: String,
+    subbed: Option<String>,
+    #[serde(rename = "err-code")]
+    err_code: Option<String>,
+    #[serde(rename = "err-msg")]
+    err_msg: Option<String>,
+}
+
+const HTX_WS_API: &str = "wss://api.huobi.pro/ws";
+
+/// Far beyond any real frame; stops a hostile gzip bomb from eating memory.
+const MAX_FRAME_BYTES: u64 = 16 * 1024 * 1024;
+
+fn decompress_gzip(data: &[u8]) -> Result<String, HtxError> {
+    let mut decoder = GzDecoder::new(data).take(MAX_FRAME_BYTES + 1);
+    let mut decompressed = String::new();
+    decoder.read_to_string(&mut decompressed).map_err(HtxError::Gzip)?;
+    if decompressed.len() as u64 > MAX_FRAME_BYTES {
+        return Err(HtxError::TooLarge(MAX_FRAME_BYTES));
+    }
+    Ok(decompressed)
+}
+
+const DEFAULT_SYMBOL: &str = "btcusdt";
+
+pub struct HtxSource {
+    endpoint: String,
+    symbols: Vec<String>,
+}
+
+impl HtxSource {
+    pub fn new(symbols: Vec<String>) -> Self {
+        HtxSource { endpoint: HTX_WS_API.to_string(), symbols }
+    }
+
+    /// Connects to `endpoint` instead of `api.huobi.pro`, e.g. a mock exchange in tests.
+    pub fn with_endpoint(mut self, endpoint: impl Into<String>) -> Self {
+        self.endpoint = endpoint.into();
+        self
+    }
+
+    /// The `bbo`, `ticker` and `trade.detail` topics of every symbol, in
+    /// subscription order.
+    fn topics(&self) -> Vec<String> {
+        self.symbols
+            .iter()
+            .flat_map(|symbol| {
+                let symbol = symbol.to_lowercase();
+                [
+                    format!("market.{}.bbo", symbol),
+                    format!("market.{}.ticker", symbol),
+                    format!("market.{}.trade.detail", symbol),
+                ]
+            })
+            .collect()
+    }
+
+    fn parse_market(message: HtxMarketMessage) -> Result<Frame, HtxError> {
+        // market.btcusdt.bbo
+        let parts: Vec<&str> = message.ch.split('.').collect();
+        match parts[..] {
+            ["market", symbol, "bbo"] => {
+                let bbo: HtxBboData = serde_json::from_value(message.tick).map_err(HtxError::Malformed)?;
+                Ok(Frame::Quotes(vec![Quote {
+                    symbol: symbol.to_string(),
+                    bid: bbo.bid,
+                    bid_vol: bbo.bid_size,
+                    ask: bbo.ask,
+                    ask_vol: bbo.ask_size,
+                    exchange_ts: Some(bbo.quote_time),
+                }]))
+            }
+            ["market", symbol, "ticker"] => {
+                let ticker: HtxTickerData = serde_json::from_value(message.tick).map_err(HtxError::Malformed)?;
+                Ok(Frame::Stats(vec![TickerStats {
+                    symbol: symbol.to_string(),
+                    last: ticker.close,
+                    volume: ticker.amount,
+                    exchange_ts: Some(message.ts),
+                }]))
+            }
+            ["market", symbol, "trade", "detail"] => {
+                let detail: HtxTradeDetailData = serde_json::from_value(message.tick).map_err(HtxError::Malformed)?;
+                Ok(Frame::Trades(
+                    detail
+                        .data
+                        .into_iter()
+                        .map(|trade| Trade {
+                            symbol: symbol.to_string(),
+                            price: trade.price,
+                            size: trade.amount,
+                            side: trade.direction,
+                            trade_id: Some(trade.trade_id.to_string()),
+                            exchange_ts: Some(trade.ts),
+                        })
+                        .collect(),
+                ))
+            }
+            _ => Err(HtxError::UnexpectedChannel(message.ch)),
+        }
+    }
+}
+
+impl Default for HtxSource {
+    fn default() -> Self {
+        Self::new(vec![DEFAULT_SYMBOL.to_string()])
+    }
+}
+
+impl QuoteSource for HtxSource {
+    fn exchange(&self) -> &'static str {
+        "HTX"
+    }
+
+    fn symbols(&self) -> &[String] {
+        &self.symbols
+    }
+
+    fn endpoint(&mut self) -> Result<String, FeedError> {
+        Ok(self.endpoint.clone())
+    }
+
+    fn subscribe_messages(&mut self) -> Vec<String> {
+        self.topics()
+            .into_iter()
+            .enumerate()
+            .map(|(i, topic)| json!({"sub": topic, "id": format!("id{}", i + 1)}).to_string())
+            .collect()
+    }
+
+    fn unsubscribe_messages(&mut self) -> Vec<String> {
+        self.topics()
+            .into_iter()
+            .enumerate()
+            .map(|(i, topic)| json!({"unsub": topic, "id": format!("id{}", i + 1)}).to_string())
+            .collect()
+    }
+
+    fn decode_binary(&self, data: &[u8]) -> Result<String, FeedError> {
+        Ok(decompress_gzip(data)?)
+    }
+
+    fn parse(&mut self, text: &str) -> Frame {
+        if text.contains("\"ping\"") {
+            if let Ok(ping_data) = serde_json::from_str::<serde_json::Value>(text) {
+                if let Some(ping_ts) = ping_data.get("ping") {
+                    return Frame::Reply(format!("{{\"pong\":{}}}", ping_ts));
+                }
+            }
+        }
+
+        match serde_json::from_str::<HtxMarketMessage>(text) {
+            Ok(message) => Self::parse_market(message).unwrap_or_else(|e| Frame::Error(e.into())),
+            Err(e) => match serde_json::from_str::<HtxStatusReply>(text) {
+                Ok(HtxStatusReply { status, err_code, err_msg, .. }) if status == "error" => Frame::Error(
+                    HtxError::Rejected { code: err_code.unwrap_or_default(), msg: err_msg.unwrap_or_default() }.into(),
+                ),
+                Ok(HtxStatusReply { subbed: Some(topic), .. }) => Frame::Event(format!("Subscribed to {}", topic)),
+                Ok(HtxStatusReply { status, .. }) => Frame::Event(format!("Request status {}", status)),
+                Err(_) => Frame::Error(HtxError::Malformed(e).into()),
+            },
+        }
+    }
+}
@@ -0,0 +1,74 @@
+use mock_exchange::{spawn_feed, Dialect, Fault, MockExchange, MockQuote, MockRedis};
+use rust_htx_quote_scraper::HtxSource;
+
+const BID_PRICE: &str = "HTX:BTC-USDT:QUOTE:BUY:PRICE";
+
+fn quote(bid: f64, ts: u64) -> MockQuote {
+    MockQuote { bid, bid_vol: 0.5, ask: bid + 1.0, ask_vol: 0.25, ts }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn answers_pings_and_keeps_exchange_time() {
+    let redis = MockRedis::start().await;
+    let mut exchange = MockExchange::start(Dialect::Htx).await;
+    let feed = spawn_feed(HtxSource::default().with_endpoint(exchange.endpoint()), &redis).await;
+
+    let mut session = exchange.session().await;
+    let subscriptions = session.expect_subscriptions(3).await;
+    assert_eq!(
+        subscriptions,
+        [
+            r#"{"id":"id1","sub":"market.btcusdt.bbo"}"#,
+            r#"{"id":"id2","sub":"market.btcusdt.ticker"}"#,
+            r#"{"id":"id3","sub":"market.btcusdt.trade.detail"}"#
+        ]
+    );
+    session.send_heartbeat(1_700_000_000_123).await;
+    assert_eq!(session.recv_text().await.unwrap(), r#"{"pong":1700000000123}"#);
+
+    session.send_quote("btcusdt", quote(43000.0, 1_700_000_000_200)).await;
+    session.send_malformed().await;
+    session.send_quote("btcusdt", quote(43001.0, 1_700_000_000_300)).await;
+    let samples = redis.wait_for_samples(BID_PRICE, 2).await;
+    assert_eq!(samples, [(1_700_000_000_200, 43000.0), (1_700_000_000_300, 43001.0)]);
+    assert_eq!(redis.samples("HTX:BTC-USDT:QUOTE:SELL:PRICE")[1], (1_700_000_000_300, 43002.0));
+    assert_eq!(redis.samples("HTX:BTC-USDT:QUOTE:SELL:VOL")[1], (1_700_000_000_300, 0.25));
+    session.fail(Fault::ProtocolError).await;
+
+    let mut session = exchange.session().await;
+    session.expect_subscriptions(3).await;
+    session.send_quote("btcusdt", quote(43002.0, 1_700_000_000_400)).await;
+    redis.wait_for_samples(BID_PRICE, 3).await;
+
+    let stopping = tokio::spawn(feed.stop());
+    let unsubscribes = session.expect_close().await;
+    assert_eq!(
+        unsubscribes,
+        [
+            r#"{"id":"id1","unsub":"market.btcusdt.bbo"}"#,
+            r#"{"id":"id2","unsub":"market.btcusdt.ticker"}"#,
+            r#"{"id":"id3","unsub":"market.btcusdt.trade.detail"}"#
+        ]
+    );
+    stopping.await.unwrap().unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn writes_ticker_stats_apart_from_quotes() {
+    let redis = MockRedis::start().await;
+    let mut exchange = MockExchange::start(Dialect::Htx).await;
+    let feed = spawn_feed(HtxSource::default().with_endpoint(exchange.endpoint()), &redis).await;
+
+    let mut session = exchange.session().await;
+    session.expect_subscriptions(3).await;
+    session
+        .send_text(r#"{"ch":"market.btcusdt.ticker","ts":1700000000500,"tick":{"open":42800.0,"high":43500.0,"low":42500.0,"close":43100.5,"amount":1234.5,"vol":53000000.0,"count":4021}}"#)
+        .await;
+    assert_eq!(redis.wait_for_samples("HTX:BTC-USDT:STATS:24H:LAST", 1).await, [(1_700_000_000_500, 43100.5)]);
+    assert_eq!(redis.samples("HTX:BTC-USDT:STATS:24H:VOL"), [(1_700_000_000_500, 1234.5)]);
+    assert!(redis.samples(BID_PRICE).is_empty());
+
+    let stopping = tokio::spawn(feed.stop());
+    session.expect_close().await;
+    stopping.await.unwrap().unwrap();
+}
@@ -0,0 +1,31 @@
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use mock_exchange::{fixture_frames, parse_frames, parse_mangled};
+use rust_htx_quote_scraper::HtxSource;
+use scraper_core::{FeedError, QuoteSource};
+use std::io::Write;
+
+#[test]
+fn parses_fixtures() {
+    insta::glob!("fixtures/*.jsonl", |path| {
+        insta::assert_debug_snapshot!(parse_frames(&mut HtxSource::default(), &fixture_frames(path)));
+    });
+}
+
+#[test]
+fn never_panics_on_mangled_fixtures() {
+    insta::glob!("fixtures/*.jsonl", |path| {
+        parse_mangled(HtxSource::default, &fixture_frames(path));
+    });
+}
+
+#[test]
+fn rejects_undecodable_binary_frames() {
+    let source = HtxSource::default();
+    assert!(matches!(source.decode_binary(b"not gzip"), Err(FeedError::Decode(_))));
+
+    // A few KiB that inflate past the frame limit
+    let mut bomb = GzEncoder::new(Vec::new(), Compression::best());
+    bomb.write_all(&vec![b' '; 17 * 1024 * 1024]).unwrap();
+    assert!(matches!(source.decode_binary(&bomb.finish().unwrap()), Err(FeedError::Decode(_))));
+}
@@ -0,0 +1,39 @@
+use scraper_core::FeedError;
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug)]
+pub enum KrakenError {
+    /// Neither a channel message nor a method response, or a ticker, trade
+    /// or book we can't read.
+    Malformed(serde_json::Error),
+    /// An unsuccessful method response, e.g. for a pair Kraken doesn't list.
+    Rejected { method: String, error: String },
+}
+
+impl fmt::Display for KrakenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KrakenError::Malformed(e) => write!(f, "malformed message: {}", e),
+            KrakenError::Rejected { method, error } => write!(f, "Kraken rejected {}: {}", method, error),
+        }
+    }
+}
+
+impl Error for KrakenError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            KrakenError::Malformed(e) => Some(e),
+            KrakenError::Rejected { .. } => None,
+        }
+    }
+}
+
+impl From<KrakenError> for FeedError {
+    fn from(error: KrakenError) -> Self {
+        match error {
+            KrakenError::Malformed(_) => FeedError::decode(error),
+            KrakenError::Rejected { .. } => FeedError::subscribe(error),
+        }
+    }
+}
@@ -0,0 +1,352 @@
+mod error;
+
+pub use error::KrakenError;
+
+use scraper_core::{rfc3339_millis, BookLevel, FeedError, Frame, OrderBook, Quote, QuoteSource, Side, Trade};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use serde_json::value::RawValue;
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// The fields of a `ticker` entry we read; `last`, `vwap` and the 24h stats
+/// are ignored.
+#[derive(Serialize, Deserialize, Debug)]
+struct KrakenTickerData {
+    symbol: String,
+    bid: f64,
+    bid_qty: f64,
+    ask: f64,
+    ask_qty: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct KrakenTickerMessage {
+    data: Vec<KrakenTickerData>,
+}
+
+/// A `trade` entry; `side` is the taker's. `ord_type` is ignored.
+#[derive(Serialize, Deserialize, Debug)]
+struct KrakenTradeData {
+    symbol: String,
+    side: Side,
+    price: f64,
+    qty: f64,
+    trade_id: u64,
+    timestamp: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct KrakenTradeMessage {
+    data: Vec<KrakenTradeData>,
+}
+
+/// A `book` level; a `qty` of 0 removes it. Kept as the JSON Kraken wrote,
+/// trailing zeros and all, since that is what its checksum covers.
+#[derive(Serialize, Deserialize, Debug)]
+struct KrakenBookLevel {
+    price: Box<RawValue>,
+    qty: Box<RawValue>,
+}
+
+/// A `book` entry, with the CRC32 of the best ten levels after it. Only
+/// updates carry a `timestamp`.
+#[derive(Serialize, Deserialize, Debug)]
+struct KrakenBookData {
+    symbol: String,
+    bids: Vec<KrakenBookLevel>,
+    asks: Vec<KrakenBookLevel>,
+    checksum: u32,
+    timestamp: Option<String>,
+}
+
+/// `type` is `snapshot` or `update`.
+#[derive(Serialize, Deserialize, Debug)]
+struct KrakenBookMessage {
+    #[serde(rename = "type")]
+    kind: String,
+    data: Vec<KrakenBookData>,
+}
+
+/// What channel a push is on: `ticker`, `trade`, `book`, `heartbeat` or `status`.
+#[derive(Serialize, Deserialize, Debug)]
+struct KrakenChannelMessage {
+    channel: String,
+}
+
+/// The response to a `subscribe` or `unsubscribe` request.
+#[derive(Serialize, Deserialize, Debug)]
+struct KrakenMethodResponse {
+    method: String,
+    success: bool,
+    error: Option<String>,
+}
+
+const KRAKEN_WS_API: &str = "wss://ws.kraken.com/v2";
+
+const DEFAULT_SYMBOL: &str = "BTC/USD";
+
+/// The depths Kraken lets a `book` subscription ask for.
+const BOOK_DEPTHS: [usize; 5] = [10, 25, 100, 500, 1000];
+
+/// Levels per side Kraken's `checksum` covers.
+const CHECKSUM_LEVELS: usize = 10;
+
+pub struct KrakenSource {
+    endpoint: String,
+    symbols: Vec<String>,
+    /// Levels per side written from each local book; `None` leaves books off.
+    book_levels: Option<usize>,
+    /// Books that have had their snapshot, by symbol.
+    books: HashMap<String, OrderBook>,
+}
+
+impl KrakenSource {
+    pub fn new(symbols: Vec<String>) -> Self {
+        KrakenSource { endpoint: KRAKEN_WS_API.to_string(), symbols, book_levels: None, books: HashMap::new() }
+    }
+
+    /// Also keeps a local book per symbol from the `book` channel and writes
+    /// its best `levels` on each side.
+    pub fn with_book(mut self, levels: usize) -> Self {
+        self.book_levels = Some(levels);
+        self
+    }
+
+    /// The smallest depth Kraken offers that covers the levels written, and
+    /// never less than the checksum needs.
+    fn book_depth(&self) -> usize {
+        let levels = self.book_levels.unwrap_or_default();
+        BOOK_DEPTHS.into_iter().find(|depth| *depth >= levels).unwrap_or(BOOK_DEPTHS[BOOK_DEPTHS.len() - 1])
+    }
+
+    /// Connects to `endpoint` instead of `ws.kraken.com`, e.g. a mock exchange in tests.
+    pub fn with_endpoint(mut self, endpoint: impl Into<String>) -> Self {
+        self.endpoint = endpoint.into();
+        self
+    }
+
+    /// A `subscribe` or `unsubscribe` request for `symbols` on `channel`.
+    /// Tickers fire on every top of book change and books start from a
+    /// snapshot; trades skip the snapshot of past trades.
+    fn subscription(&self, method: &str, channel: &str, symbols: &[String]) -> String {
+        let mut params = json!({"channel": channel, "symbol": symbols});
+        // The rest is only sent when subscribing
+        if method == "subscribe" {
+            params["snapshot"] = json!(channel != "trade");
+            if channel == "ticker" {
+                params["event_trigger"] = json!("bbo");
+            }
+            if channel == "book" {
+                params["depth"] = json!(self.book_depth());
+            }
+        }
+        json!({"method": method, "params": params}).to_string()
+    }
+
+    /// Every channel we subscribe to.
+    fn channels(&self) -> &'static [&'static str] {
+        if self.book_levels.is_some() {
+            &["ticker", "trade", "book"]
+        } else {
+            &["ticker", "trade"]
+        }
+    }
+
+    /// Drops `symbol`'s book and subscribes to it again for a fresh snapshot.
+    fn resync(&mut self, symbol: String, reason: String) -> Frame {
+        self.books.remove(&symbol);
+        let symbols = [symbol.clone()];
+        let messages = vec![self.subscription("unsubscribe", "book", &symbols), self.subscription("subscribe", "book", &symbols)];
+        Frame::Resync { symbol, reason, messages }
+    }
+
+    fn parse_book(&mut self, message: KrakenBookMessage, levels: usize) -> Frame {
+        let depth = self.book_depth();
+        let mut books = Vec::new();
+        for update in message.data {
+            if message.kind == "snapshot" {
+                self.books.insert(update.symbol.clone(), OrderBook::default());
+            }
+            // Updates sent between asking for a fresh book and its snapshot
+            let Some(book) = self.books.get_mut(&update.symbol) else {
+                continue;
+            };
+            for (side, levels) in [(Side::Buy, &update.bids), (Side::Sell, &update.asks)] {
+                for level in levels {
+                    let Some(parsed) = BookLevel::parse(level.price.get(), level.qty.get()) else {
+                        let reason = format!("bad level {} {}", level.price, level.qty);
+                        return self.resync(update.symbol, reason);
+                    };
+                    book.update(side, parsed);
+                }
+            }
+            // Levels pushed out of the subscribed depth get no more updates
+            book.truncate(depth);
+            let checksum = checksum(book);
+            if checksum != update.checksum {
+                let reason = format!("checksum {} doesn't match Kraken's {}", checksum, update.checksum);
+                return self.resync(update.symbol, reason);
+            }
+            let exchange_ts = update.timestamp.as_deref().and_then(rfc3339_millis);
+            let top = book.depth(&update.symbol, levels, exchange_ts);
+            // A crossed or one-sided book has fallen out of step with Kraken's
+            if let Err(error) = top.validate() {
+                return self.resync(update.symbol, error.cause().to_string());
+            }
+            books.push(top);
+        }
+        if books.is_empty() {
+            Frame::Ignored
+        } else {
+            Frame::Books(books)
+        }
+    }
+}
+
+/// Kraken's checksum text for a price or quantity: the decimal point and
+/// any leading zeros dropped.
+fn checksum_digits(text: &str) -> String {
+    text.replace('.', "").trim_start_matches('0').to_string()
+}
+
+/// CRC32 of the best [`CHECKSUM_LEVELS`] asks, lowest first, then bids,
+/// highest first, each as its price digits followed by its quantity digits.
+fn checksum(book: &OrderBook) -> u32 {
+    let mut text = String::new();
+    for level in book.asks().take(CHECKSUM_LEVELS).chain(book.bids().take(CHECKSUM_LEVELS)) {
+        text.push_str(&checksum_digits(&level.text.0));
+        text.push_str(&checksum_digits(&level.text.1));
+    }
+    crc32fast::hash(text.as_bytes())
+}
+
+impl Default for KrakenSource {
+    fn default() -> Self {
+        Self::new(vec![DEFAULT_SYMBOL.to_string()])
+    }
+}
+
+impl QuoteSource for KrakenSource {
+    fn exchange(&self) -> &'static str {
+        "KRAKEN"
+    }
+
+    fn symbols(&self) -> &[String] {
+        &self.symbols
+    }
+
+    fn endpoint(&mut self) -> Result<String, FeedError> {
+        Ok(self.endpoint.clone())
+    }
+
+    fn subscribe_messages(&mut self) -> Vec<String> {
+        self.books.clear();
+        self.channels().iter().map(|channel| self.subscription("subscribe", channel, &self.symbols)).collect()
+    }
+
+    fn unsubscribe_messages(&mut self) -> Vec<String> {
+        self.channels().iter().map(|channel| self.subscription("unsubscribe", channel, &self.symbols)).collect()
+    }
+
+    fn parse(&mut self, text: &str) -> Frame {
+        if let Ok(response) = serde_json::from_str::<KrakenMethodResponse>(text) {
+            return match response {
+                KrakenMethodResponse { method, success: false, error } => {
+                    Frame::Error(KrakenError::Rejected { method, error: error.unwrap_or_default() }.into())
+                }
+                KrakenMethodResponse { method, .. } => Frame::Event(format!("Received {} acknowledgement", method)),
+            };
+        }
+        let channel = match serde_json::from_str::<KrakenChannelMessage>(text) {
+            Ok(message) => message.channel,
+            Err(e) => return Frame::Error(KrakenError::Malformed(e).into()),
+        };
+        match channel.as_str() {
+            "ticker" => match serde_json::from_str::<KrakenTickerMessage>(text) {
+                Ok(data) => Frame::Quotes(
+                    data.data
+                        .into_iter()
+                        .map(|ticker| Quote {
+                            symbol: ticker.symbol,
+                            bid: ticker.bid,
+                            bid_vol: ticker.bid_qty,
+                            ask: ticker.ask,
+                            ask_vol: ticker.ask_qty,
+                            exchange_ts: None,
+                        })
+                        .collect(),
+                ),
+                Err(e) => Frame::Error(KrakenError::Malformed(e).into()),
+            },
+            "trade" => match serde_json::from_str::<KrakenTradeMessage>(text) {
+                Ok(data) => Frame::Trades(
+                    data.data
+                        .into_iter()
+                        .map(|trade| Trade {
+                            exchange_ts: rfc3339_millis(&trade.timestamp),
+                            symbol: trade.symbol,
+                            price: trade.price,
+                            size: trade.qty,
+                            side: trade.side,
+                            trade_id: Some(trade.trade_id.to_string()),
+                        })
+                        .collect(),
+                ),
+                Err(e) => Frame::Error(KrakenError::Malformed(e).into()),
+            },
+            "book" => match (self.book_levels, serde_json::from_str::<KrakenBookMessage>(text)) {
+                (Some(levels), Ok(message)) => self.parse_book(message, levels),
+                (Some(_), Err(e)) => Frame::Error(KrakenError::Malformed(e).into()),
+                (None, _) => Frame::Ignored,
+            },
+            "heartbeat" => Frame::Event("Received heartbeat".to_string()),
+            "status" => Frame::Event("Received status update".to_string()),
+            _ => Frame::Ignored,
+        }
+    }
+
+    fn ping_interval(&self) -> Duration {
+        Duration::from_secs(30)
+    }
+
+    fn stale_after(&self) -> Duration {
+        // bbo only publishes when the top of book changes and heartbeats keep the socket busy meanwhile
+        Duration::from_secs(120)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The book from Kraken's v2 checksum guide, with the checksum it works out.
+    const GUIDE_BOOK: &str = r#"{"symbol":"BTC/USD","checksum":974947235,"asks":[
+        {"price":0.05005,"qty":0.00000500},{"price":0.05010,"qty":0.00000500},{"price":0.05015,"qty":0.00000500},
+        {"price":0.05020,"qty":0.00000500},{"price":0.05025,"qty":0.00000500},{"price":0.05030,"qty":0.00000500},
+        {"price":0.05035,"qty":0.00000500},{"price":0.05040,"qty":0.00000500},{"price":0.05045,"qty":0.00000500},
+        {"price":0.05050,"qty":0.00000500}],"bids":[
+        {"price":0.05000,"qty":0.00000500},{"price":0.04995,"qty":0.00000500},{"price":0.04990,"qty":0.00000500},
+        {"price":0.04980,"qty":0.00000500},{"price":0.04975,"qty":0.00000500},{"price":0.04970,"qty":0.00000500},
+        {"price":0.04965,"qty":0.00000500},{"price":0.04960,"qty":0.00000500},{"price":0.04955,"qty":0.00000500},
+        {"price":0.04950,"qty":0.00000500}]}"#;
+
+    #[test]
+    fn checksums_the_guide_book() {
+        let data: KrakenBookData = serde_json::from_str(GUIDE_BOOK).unwrap();
+        let mut book = OrderBook::default();
+        for (side, levels) in [(Side::Buy, &data.bids), (Side::Sell, &data.asks)] {
+            for level in levels {
+                book.update(side, BookLevel::parse(level.price.get(), level.qty.get()).unwrap());
+            }
+        }
+        assert_eq!(checksum(&book), data.checksum);
+    }
+
+    #[test]
+    fn drops_the_point_and_leading_zeros() {
+        assert_eq!(checksum_digits("0.05005"), "5005");
+        assert_eq!(checksum_digits("0.00000500"), "500");
+        assert_eq!(checksum_digits("43250.10"), "4325010");
+    }
+}
@@ -0,0 +1,50 @@
+use mock_exchange::{spawn_feed, Dialect, Fault, MockExchange, MockQuote, MockRedis};
+use rust_kraken_quote_scraper::KrakenSource;
+
+const BID_PRICE: &str = "KRAKEN:BTC-USD:QUOTE:BUY:PRICE";
+
+fn quote(bid: f64) -> MockQuote {
+    MockQuote { bid, bid_vol: 0.5, ask: bid + 1.0, ask_vol: 0.25, ts: 1_700_000_000_000 }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn stores_tickers_between_heartbeats() {
+    let redis = MockRedis::start().await;
+    let mut exchange = MockExchange::start(Dialect::Kraken).await;
+    let feed = spawn_feed(KrakenSource::default().with_endpoint(exchange.endpoint()), &redis).await;
+
+    let mut session = exchange.session().await;
+    let subscriptions = session.expect_subscriptions(2).await;
+    assert_eq!(
+        subscriptions,
+        [
+            r#"{"method":"subscribe","params":{"channel":"ticker","event_trigger":"bbo","snapshot":true,"symbol":["BTC/USD"]}}"#,
+            r#"{"method":"subscribe","params":{"channel":"trade","snapshot":false,"symbol":["BTC/USD"]}}"#
+        ]
+    );
+    session.send_heartbeat(0).await;
+    session.send_quote("BTC/USD", quote(43000.0)).await;
+    session.send_heartbeat(0).await;
+    session.send_malformed().await;
+    session.send_quote("BTC/USD", quote(43001.0)).await;
+    let bids: Vec<f64> = redis.wait_for_samples(BID_PRICE, 2).await.into_iter().map(|(_, bid)| bid).collect();
+    assert_eq!(bids, [43000.0, 43001.0]);
+    assert_eq!(redis.samples("KRAKEN:BTC-USD:QUOTE:SELL:VOL")[0].1, 0.25);
+    session.fail(Fault::Disconnect).await;
+
+    let mut session = exchange.session().await;
+    session.expect_subscriptions(2).await;
+    session.send_quote("BTC/USD", quote(43002.0)).await;
+    redis.wait_for_samples(BID_PRICE, 3).await;
+
+    let stopping = tokio::spawn(feed.stop());
+    let unsubscribes = session.expect_close().await;
+    assert_eq!(
+        unsubscribes,
+        [
+            r#"{"method":"unsubscribe","params":{"channel":"ticker","symbol":["BTC/USD"]}}"#,
+            r#"{"method":"unsubscribe","params":{"channel":"trade","symbol":["BTC/USD"]}}"#
+        ]
+    );
+    stopping.await.unwrap().unwrap();
+}
@@ -0,0 +1,49 @@
+use scraper_core::FeedError;
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug)]
+pub enum KucoinError {
+    /// bullet-public couldn't be reached or didn't return a token.
+    Token(reqwest::Error),
+    /// bullet-public returned a token but no server to use it on.
+    NoInstanceServers,
+    /// A ticker or match we can't read, or no JSON object with a `type`.
+    Malformed(serde_json::Error),
+    /// A push on a topic we never subscribe to.
+    UnexpectedTopic(String),
+    /// An `error` message, e.g. for a topic KuCoin doesn't know.
+    Rejected { code: i64, msg: String },
+}
+
+impl fmt::Display for KucoinError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KucoinError::Token(e) => write!(f, "can't fetch a bullet-public token: {}", e),
+            KucoinError::NoInstanceServers => write!(f, "bullet-public returned no instance servers"),
+            KucoinError::Malformed(e) => write!(f, "malformed message: {}", e),
+            KucoinError::UnexpectedTopic(topic) => write!(f, "unexpected topic {}", topic),
+            KucoinError::Rejected { code, msg } => write!(f, "KuCoin rejected the request: {} (code {})", msg, code),
+        }
+    }
+}
+
+impl Error for KucoinError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            KucoinError::Token(e) => Some(e),
+            KucoinError::Malformed(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<KucoinError> for FeedError {
+    fn from(error: KucoinError) -> Self {
+        match error {
+            KucoinError::Token(_) | KucoinError::NoInstanceServers => FeedError::connect(error),
+            KucoinError::Malformed(_) | KucoinError::UnexpectedTopic(_) => FeedError::decode(error),
+            KucoinError::Rejected { .. } => FeedError::subscribe(error),
+        }
+    }
+}
@@ -0,0 +1,211 @@
+mod error;
+
+pub use error::KucoinError;
+
+use scraper_core::{de_float_from_str, get_current_timestamp, FeedError, Frame, Quote, QuoteSource, Side, Trade};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::time::Duration;
+
+/// The fields of a `trade.ticker` we read; the last trade's `price` and
+/// `size` and the `sequence` are ignored.
+#[derive(Serialize, Deserialize, Debug)]
+struct KucoinTickerData {
+    #[serde(rename = "bestAsk", deserialize_with = "de_float_from_str")]
+    best_ask: f64,
+    #[serde(rename = "bestAskSize", deserialize_with = "de_float_from_str")]
+    best_ask_size: f64,
+    #[serde(rename = "bestBid", deserialize_with = "de_float_from_str")]
+    best_bid: f64,
+    #[serde(rename = "bestBidSize", deserialize_with = "de_float_from_str")]
+    best_bid_size: f64,
+    #[serde(rename = "Time")]
+    time: Option<u64>,
+}
+
+/// A `trade.l3match` from `/market/match`; `side` is the taker's. The order
+/// ids and `sequence` are ignored.
+#[derive(Serialize, Deserialize, Debug)]
+struct KucoinMatchData {
+    symbol: String,
+    side: Side,
+    #[serde(deserialize_with = "de_float_from_str")]
+    price: f64,
+    #[serde(deserialize_with = "de_float_from_str")]
+    size: f64,
+    #[serde(rename = "tradeId")]
+    trade_id: String,
+    /// Epoch nanos.
+    time: String,
+}
+
+/// A push on one of our topics; `data` is parsed once `topic` says which.
+#[derive(Serialize, Deserialize, Debug)]
+struct KucoinMarketMessage {
+    topic: String,
+    data: Value,
+}
+
+/// Every other message: `welcome`, `ack`, `pong`, and `error` with the
+/// reason in `data`.
+#[derive(Serialize, Deserialize, Debug)]
+struct KucoinReply {
+    r#type: String,
+    code: Option<i64>,
+    data: Option<Value>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct KucoinTokenResponse {
+    code: String,
+    data: KucoinTokenData,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct KucoinTokenData {
+    token: String,
+    #[serde(rename = "instanceServers")]
+    instance_servers: Vec<KucoinServer>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct KucoinServer {
+    endpoint: String,
+    #[serde(rename = "pingInterval")]
+    ping_interval: u64,
+    #[serde(rename = "pingTimeout")]
+    ping_timeout: u64,
+}
+
+const KUCOIN_TOKEN_API: &str = "https://api.kucoin.com/api/v1/bullet-public";
+const DEFAULT_PING_INTERVAL_MS: u64 = 18000;
+const DEFAULT_SYMBOL: &str = "BTC-USDT";
+
+fn get_websocket_endpoint(token_api: &str) -> Result<(String, u64), KucoinError> {
+    let client = reqwest::blocking::Client::new();
+    let response: KucoinTokenResponse = client
+        .post(token_api)
+        .send()
+        .and_then(|response| response.json())
+        .map_err(KucoinError::Token)?;
+
+    let server = response.data.instance_servers.first().ok_or(KucoinError::NoInstanceServers)?;
+    let token = &response.data.token;
+    let url = format!("{}?token={}&connectId={}", server.endpoint, token, get_current_timestamp());
+    Ok((url, server.ping_interval))
+}
+
+pub struct KucoinSource {
+    token_api: String,
+    symbols: Vec<String>,
+    ping_interval_ms: u64,
+}
+
+impl KucoinSource {
+    pub fn new(symbols: Vec<String>) -> Self {
+        KucoinSource { token_api: KUCOIN_TOKEN_API.to_string(), symbols, ping_interval_ms: DEFAULT_PING_INTERVAL_MS }
+    }
+
+    /// Fetches the WebSocket endpoint from `token_api` instead of KuCoin's
+    /// bullet-public, e.g. a mock exchange in tests.
+    pub fn with_token_api(mut self, token_api: impl Into<String>) -> Self {
+        self.token_api = token_api.into();
+        self
+    }
+
+    /// `subscribe` or `unsubscribe` requests for every symbol's ticker and
+    /// matches.
+    fn subscriptions(&self, op: &str) -> Vec<String> {
+        let id = get_current_timestamp();
+        ["/market/ticker", "/market/match"]
+            .into_iter()
+            .enumerate()
+            .map(|(i, topic)| {
+                let topic = format!("{}:{}", topic, self.symbols.join(","));
+                json!({"id": id + i as u64, "type": op, "topic": topic, "response": true}).to_string()
+            })
+            .collect()
+    }
+
+    fn parse_market(message: KucoinMarketMessage) -> Result<Frame, KucoinError> {
+        // /market/ticker:BTC-USDT
+        let (topic, symbol) = message.topic.split_once(':').unwrap_or((&message.topic, ""));
+        match topic {
+            "/market/ticker" => {
+                let data: KucoinTickerData = serde_json::from_value(message.data).map_err(KucoinError::Malformed)?;
+                Ok(Frame::Quotes(vec![Quote {
+                    symbol: symbol.to_string(),
+                    bid: data.best_bid,
+                    bid_vol: data.best_bid_size,
+                    ask: data.best_ask,
+                    ask_vol: data.best_ask_size,
+                    exchange_ts: data.time,
+                }]))
+            }
+            "/market/match" => {
+                let data: KucoinMatchData = serde_json::from_value(message.data).map_err(KucoinError::Malformed)?;
+                Ok(Frame::Trades(vec![Trade {
+                    exchange_ts: data.time.parse::<u64>().ok().map(|nanos| nanos / 1_000_000),
+                    symbol: data.symbol,
+                    price: data.price,
+                    size: data.size,
+                    side: data.side,
+                    trade_id: Some(data.trade_id),
+                }]))
+            }
+            _ => Err(KucoinError::UnexpectedTopic(message.topic)),
+        }
+    }
+}
+
+impl Default for KucoinSource {
+    fn default() -> Self {
+        Self::new(vec![DEFAULT_SYMBOL.to_string()])
+    }
+}
+
+impl QuoteSource for KucoinSource {
+    fn exchange(&self) -> &'static str {
+        "KUCOIN"
+    }
+
+    fn symbols(&self) -> &[String] {
+        &self.symbols
+    }
+
+    fn endpoint(&mut self) -> Result<String, FeedError> {
+        let (ws_url, ping_interval_ms) = get_websocket_endpoint(&self.token_api)?;
+        self.ping_interval_ms = ping_interval_ms;
+        Ok(ws_url)
+    }
+
+    fn subscribe_messages(&mut self) -> Vec<String> {
+        self.subscriptions("subscribe")
+    }
+
+    fn unsubscribe_messages(&mut self) -> Vec<String> {
+        self.subscriptions("unsubscribe")
+    }
+
+    fn parse(&mut self, text: &str) -> Frame {
+        match serde_json::from_str::<KucoinMarketMessage>(text) {
+            Ok(message) => Self::parse_market(message).unwrap_or_else(|e| Frame::Error(e.into())),
+            Err(e) => match serde_json::from_str::<KucoinReply>(text) {
+                Ok(KucoinReply { r#type, code, data }) if r#type == "error" => Frame::Error(
+                    KucoinError::Rejected {
+                        code: code.unwrap_or_default(),
+                        msg: data.as_ref().and_then(Value::as_str).unwrap_or_default().to_string(),
+                    }
+                    .into(),
+                ),
+                Ok(KucoinReply { r#type, .. }) if r#type == "pong" => Frame::Ignored,
+                Ok(KucoinReply { r#type, .. }) if r#type != "message" => Frame::Event(format!("Received {}", r#type)),
+                _ => Frame::Error(KucoinError::Malformed(e).into()),
+            },
+        }
+    }
+
+    fn ping_interval(&self) -> Duration {
+        Duration::from_millis(self.ping_interval_ms)
+    }
+}
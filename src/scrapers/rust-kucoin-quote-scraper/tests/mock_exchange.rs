@@ -0,0 +1,39 @@
+use mock_exchange::{spawn_feed, Dialect, Fault, MockExchange, MockQuote, MockRedis};
+use rust_kucoin_quote_scraper::KucoinSource;
+
+const BID_PRICE: &str = "KUCOIN:BTC-USDT:QUOTE:BUY:PRICE";
+
+fn quote(bid: f64, ts: u64) -> MockQuote {
+    MockQuote { bid, bid_vol: 0.5, ask: bid + 1.0, ask_vol: 0.25, ts }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn fetches_a_token_before_every_connection() {
+    let redis = MockRedis::start().await;
+    let mut exchange = MockExchange::start(Dialect::Kucoin).await;
+    let feed = spawn_feed(KucoinSource::default().with_token_api(exchange.token_api()), &redis).await;
+
+    let mut session = exchange.session().await;
+    assert!(session.path.starts_with("/?token=mock-token&connectId="), "{}", session.path);
+    let subscriptions = session.expect_subscriptions(2).await;
+    assert!(subscriptions[0].contains(r#""response":true,"topic":"/market/ticker:BTC-USDT","type":"subscribe""#));
+    assert!(subscriptions[1].contains(r#""response":true,"topic":"/market/match:BTC-USDT","type":"subscribe""#));
+    session.send_quote("BTC-USDT", quote(43000.0, 1_700_000_000_200)).await;
+    session.send_malformed().await;
+    session.send_quote("BTC-USDT", quote(43001.0, 1_700_000_000_300)).await;
+    let samples = redis.wait_for_samples(BID_PRICE, 2).await;
+    assert_eq!(samples, [(1_700_000_000_200, 43000.0), (1_700_000_000_300, 43001.0)]);
+    assert_eq!(redis.samples("KUCOIN:BTC-USDT:QUOTE:BUY:VOL")[0].1, 0.5);
+    session.fail(Fault::Close).await;
+
+    let mut session = exchange.session().await;
+    assert!(session.path.starts_with("/?token=mock-token"));
+    session.expect_subscriptions(2).await;
+    session.send_quote("BTC-USDT", quote(43002.0, 1_700_000_000_400)).await;
+    redis.wait_for_samples(BID_PRICE, 3).await;
+
+    let stopping = tokio::spawn(feed.stop());
+    let unsubscribes = session.expect_close().await;
+    assert!(unsubscribes[0].contains(r#""type":"unsubscribe""#));
+    stopping.await.unwrap().unwrap();
+}
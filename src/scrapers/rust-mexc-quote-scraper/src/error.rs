@@ -0,0 +1,45 @@
+use scraper_core::FeedError;
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug)]
+pub enum MexcError {
+    /// Neither a book ticker or deals push nor a response to a request.
+    Malformed(serde_json::Error),
+    /// A book ticker or deal field that isn't a number, e.g. an empty price.
+    BadNumber { field: &'static str, value: String },
+    /// A deal whose `tradetype` is neither 1 (buy) nor 2 (sell).
+    BadTradeType(i64),
+    /// A failed subscription, e.g. to a blocked or unknown channel.
+    Rejected { code: i64, msg: String },
+}
+
+impl fmt::Display for MexcError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MexcError::Malformed(e) => write!(f, "malformed message: {}", e),
+            MexcError::BadNumber { field, value } => write!(f, "{} {:?} is not a number", field, value),
+            MexcError::BadTradeType(trade_type) => write!(f, "unknown trade type {}", trade_type),
+            MexcError::Rejected { code, msg } => write!(f, "MEXC rejected the request: {} (code {})", msg, code),
+        }
+    }
+}
+
+impl Error for MexcError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            MexcError::Malformed(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<MexcError> for FeedError {
+    fn from(error: MexcError) -> Self {
+        match error {
+            MexcError::Malformed(_) | MexcError::BadTradeType(_) => FeedError::decode(error),
+            MexcError::BadNumber { .. } => FeedError::validation(error),
+            MexcError::Rejected { .. } => FeedError::subscribe(error),
+        }
+    }
+}
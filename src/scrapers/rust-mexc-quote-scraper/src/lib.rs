@@ -0,0 +1,197 @@
+mod error;
+
+pub use error::MexcError;
+
+use scraper_core::{FeedError, Frame, Quote, QuoteSource, Side, Trade};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+#[derive(Serialize, Deserialize, Debug)]
+struct MexcBookTickerData {
+    #[serde(rename = "bidprice")]
+    bid_price: String,
+    #[serde(rename = "bidquantity")]
+    bid_quantity: String,
+    #[serde(rename = "askprice")]
+    ask_price: String,
+    #[serde(rename = "askquantity")]
+    ask_quantity: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct MexcBookTickerMessage {
+    channel: String,
+    #[serde(rename = "publicbookticker")]
+    public_book_ticker: MexcBookTickerData,
+    symbol: String,
+    #[serde(rename = "sendtime")]
+    send_time: u64,
+}
+
+/// One deal; `tradetype` is the taker's side, 1 for buy and 2 for sell.
+/// MEXC sends no trade id.
+#[derive(Serialize, Deserialize, Debug)]
+struct MexcDeal {
+    price: String,
+    quantity: String,
+    #[serde(rename = "tradetype")]
+    trade_type: i64,
+    time: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct MexcDealsData {
+    #[serde(rename = "dealsList")]
+    deals: Vec<MexcDeal>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct MexcDealsMessage {
+    channel: String,
+    #[serde(rename = "publicdeals")]
+    public_deals: MexcDealsData,
+    symbol: String,
+}
+
+/// The response to a request: code `0` and the subscribed channels in `msg`,
+/// or an error code and message.
+#[derive(Serialize, Deserialize, Debug)]
+struct MexcResponse {
+    id: u64,
+    code: i64,
+    msg: String,
+}
+
+const MEXC_WS_API: &str = "wss://wbs.mexc.com/ws";
+
+/// How a response starts when a subscription failed.
+const NOT_SUBSCRIBED: &str = "Not Subscribed successfully";
+
+const DEFAULT_SYMBOL: &str = "BTCUSDT";
+
+pub struct MexcSource {
+    endpoint: String,
+    symbols: Vec<String>,
+}
+
+impl MexcSource {
+    pub fn new(symbols: Vec<String>) -> Self {
+        MexcSource { endpoint: MEXC_WS_API.to_string(), symbols }
+    }
+
+    /// Connects to `endpoint` instead of `wbs.mexc.com`, e.g. a mock exchange in tests.
+    pub fn with_endpoint(mut self, endpoint: impl Into<String>) -> Self {
+        self.endpoint = endpoint.into();
+        self
+    }
+
+    /// A `SUBSCRIPTION` or `UNSUBSCRIPTION` request for every symbol's book
+    /// ticker and deals.
+    fn subscription(&self, op: &str) -> String {
+        let params: Vec<String> = self
+            .symbols
+            .iter()
+            .flat_map(|symbol| [format!("spot@public.bookTicker.v3.api@{}", symbol), format!("spot@public.deals.v3.api@{}", symbol)])
+            .collect();
+        json!({"method": op, "params": params, "id": 1}).to_string()
+    }
+}
+
+fn number(field: &'static str, value: &str) -> Result<f64, MexcError> {
+    value.parse().map_err(|_| MexcError::BadNumber { field, value: value.to_string() })
+}
+
+impl MexcBookTickerMessage {
+    fn quote(self) -> Result<Quote, MexcError> {
+        let ticker = &self.public_book_ticker;
+        Ok(Quote {
+            bid: number("bidprice", &ticker.bid_price)?,
+            bid_vol: number("bidquantity", &ticker.bid_quantity)?,
+            ask: number("askprice", &ticker.ask_price)?,
+            ask_vol: number("askquantity", &ticker.ask_quantity)?,
+            symbol: self.symbol,
+            exchange_ts: Some(self.send_time),
+        })
+    }
+}
+
+impl MexcDealsMessage {
+    fn trades(self) -> Result<Vec<Trade>, MexcError> {
+        let symbol = self.symbol;
+        self.public_deals
+            .deals
+            .into_iter()
+            .map(|deal| {
+                let side = match deal.trade_type {
+                    1 => Side::Buy,
+                    2 => Side::Sell,
+                    other => return Err(MexcError::BadTradeType(other)),
+                };
+                Ok(Trade {
+                    symbol: symbol.clone(),
+                    price: number("price", &deal.price)?,
+                    size: number("quantity", &deal.quantity)?,
+                    side,
+                    trade_id: None,
+                    exchange_ts: Some(deal.time),
+                })
+            })
+            .collect()
+    }
+}
+
+impl Default for MexcSource {
+    fn default() -> Self {
+        Self::new(vec![DEFAULT_SYMBOL.to_string()])
+    }
+}
+
+impl QuoteSource for MexcSource {
+    fn exchange(&self) -> &'static str {
+        "MEXC"
+    }
+
+    fn symbols(&self) -> &[String] {
+        &self.symbols
+    }
+
+    fn endpoint(&mut self) -> Result<String, FeedError> {
+        Ok(self.endpoint.clone())
+    }
+
+    fn subscribe_messages(&mut self) -> Vec<String> {
+        vec![self.subscription("SUBSCRIPTION")]
+    }
+
+    fn unsubscribe_messages(&mut self) -> Vec<String> {
+        vec![self.subscription("UNSUBSCRIPTION")]
+    }
+
+    fn parse(&mut self, text: &str) -> Frame {
+        if text.contains("\"method\":\"PING\"") {
+            return Frame::Reply("{\"method\":\"PONG\"}".to_string());
+        }
+
+        if let Ok(deals) = serde_json::from_str::<MexcDealsMessage>(text) {
+            return match deals.trades() {
+                Ok(trades) => Frame::Trades(trades),
+                Err(e) => Frame::Error(e.into()),
+            };
+        }
+
+        match serde_json::from_str::<MexcBookTickerMessage>(text) {
+            Ok(data) => match data.quote() {
+                Ok(quote) => Frame::Quotes(vec![quote]),
+                Err(e) => Frame::Error(e.into()),
+            },
+            Err(e) => match serde_json::from_str::<MexcResponse>(text) {
+                // Blocked or unknown channels still come back with code 0
+                Ok(MexcResponse { code, msg, .. }) if code != 0 || msg.starts_with(NOT_SUBSCRIBED) => {
+                    Frame::Error(MexcError::Rejected { code, msg }.into())
+                }
+                Ok(MexcResponse { msg, .. }) => Frame::Event(format!("Subscribed to {}", msg)),
+                Err(_) => Frame::Error(MexcError::Malformed(e).into()),
+            },
+        }
+    }
+}
@@ -0,0 +1,39 @@
+use mock_exchange::{spawn_feed, Dialect, Fault, MockExchange, MockQuote, MockRedis};
+use rust_mexc_quote_scraper::MexcSource;
+
+const BID_PRICE: &str = "MEXC:BTC-USDT:QUOTE:BUY:PRICE";
+
+fn quote(bid: f64, ts: u64) -> MockQuote {
+    MockQuote { bid, bid_vol: 0.5, ask: bid + 1.0, ask_vol: 0.25, ts }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn answers_pings_and_keeps_exchange_time() {
+    let redis = MockRedis::start().await;
+    let mut exchange = MockExchange::start(Dialect::Mexc).await;
+    let feed = spawn_feed(MexcSource::default().with_endpoint(exchange.endpoint()), &redis).await;
+
+    let mut session = exchange.session().await;
+    let subscriptions = session.expect_subscriptions(1).await;
+    assert_eq!(subscriptions, [r#"{"id":1,"method":"SUBSCRIPTION","params":["spot@public.bookTicker.v3.api@BTCUSDT","spot@public.deals.v3.api@BTCUSDT"]}"#]);
+    session.send_heartbeat(0).await;
+    assert_eq!(session.recv_text().await.unwrap(), r#"{"method":"PONG"}"#);
+
+    session.send_quote("BTCUSDT", quote(43000.0, 1_700_000_000_200)).await;
+    session.send_malformed().await;
+    session.send_quote("BTCUSDT", quote(43001.0, 1_700_000_000_300)).await;
+    let samples = redis.wait_for_samples(BID_PRICE, 2).await;
+    assert_eq!(samples, [(1_700_000_000_200, 43000.0), (1_700_000_000_300, 43001.0)]);
+    assert_eq!(redis.samples("MEXC:BTC-USDT:QUOTE:SELL:VOL")[1], (1_700_000_000_300, 0.25));
+    session.fail(Fault::Close).await;
+
+    let mut session = exchange.session().await;
+    session.expect_subscriptions(1).await;
+    session.send_quote("BTCUSDT", quote(43002.0, 1_700_000_000_400)).await;
+    redis.wait_for_samples(BID_PRICE, 3).await;
+
+    let stopping = tokio::spawn(feed.stop());
+    let unsubscribes = session.expect_close().await;
+    assert_eq!(unsubscribes, [r#"{"id":1,"method":"UNSUBSCRIPTION","params":["spot@public.bookTicker.v3.api@BTCUSDT","spot@public.deals.v3.api@BTCUSDT"]}"#]);
+    stopping.await.unwrap().unwrap();
+}
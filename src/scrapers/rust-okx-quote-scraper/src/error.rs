@@ -0,0 +1,43 @@
+use scraper_core::FeedError;
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug)]
+pub enum OkxError {
+    /// Neither a push nor an event, or a push whose data isn't shaped like
+    /// its channel's.
+    Malformed(serde_json::Error),
+    /// A push on a channel other than `tickers`, `trades`, `funding-rate`,
+    /// `mark-price` or, with books on, `books`.
+    UnexpectedChannel(String),
+    /// An `error` event, e.g. for an instrument OKX doesn't list.
+    Rejected { code: String, msg: String },
+}
+
+impl fmt::Display for OkxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OkxError::Malformed(e) => write!(f, "malformed message: {}", e),
+            OkxError::UnexpectedChannel(channel) => write!(f, "unexpected channel {}", channel),
+            OkxError::Rejected { code, msg } => write!(f, "OKX rejected the request: {} (code {})", msg, code),
+        }
+    }
+}
+
+impl Error for OkxError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            OkxError::Malformed(e) => Some(e),
+            OkxError::UnexpectedChannel(_) | OkxError::Rejected { .. } => None,
+        }
+    }
+}
+
+impl From<OkxError> for FeedError {
+    fn from(error: OkxError) -> Self {
+        match error {
+            OkxError::Malformed(_) | OkxError::UnexpectedChannel(_) => FeedError::decode(error),
+            OkxError::Rejected { .. } => FeedError::subscribe(error),
+        }
+    }
+}
@@ -0,0 +1,414 @@
+mod error;
+
+pub use error::OkxError;
+
+use scraper_core::{
+    de_float_from_str, de_millis_from_str, BookLevel, FeedError, Frame, Instrument, InstrumentType, OrderBook, PerpStats,
+    Quote, QuoteSource, Side, Trade,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+#[derive(Serialize, Deserialize, Debug)]
+struct OkxTickerData {
+    #[serde(rename = "instId")]
+    inst_id: String,
+    #[serde(rename = "askPx", deserialize_with = "de_float_from_str")]
+    ask_px: f64,
+    #[serde(rename = "askSz", deserialize_with = "de_float_from_str")]
+    ask_sz: f64,
+    #[serde(rename = "bidPx", deserialize_with = "de_float_from_str")]
+    bid_px: f64,
+    #[serde(rename = "bidSz", deserialize_with = "de_float_from_str")]
+    bid_sz: f64,
+    #[serde(deserialize_with = "de_millis_from_str")]
+    ts: u64,
+}
+
+/// A `trades` push; `side` is the taker's. `count` is above 1 when OKX
+/// aggregates several fills at one price.
+#[derive(Serialize, Deserialize, Debug)]
+struct OkxTradeData {
+    #[serde(rename = "instId")]
+    inst_id: String,
+    #[serde(rename = "tradeId")]
+    trade_id: String,
+    #[serde(deserialize_with = "de_float_from_str")]
+    px: f64,
+    #[serde(deserialize_with = "de_float_from_str")]
+    sz: f64,
+    side: Side,
+    #[serde(deserialize_with = "de_millis_from_str")]
+    ts: u64,
+}
+
+/// A `books` push. Levels are `[price, size, "0", orders]` and a size of
+/// `"0"` removes the level. `seqId` chains every update to the one before
+/// through `prevSeqId`, and `checksum` covers the best 25 levels after it.
+#[derive(Serialize, Deserialize, Debug)]
+struct OkxBookData {
+    asks: Vec<Vec<String>>,
+    bids: Vec<Vec<String>>,
+    #[serde(deserialize_with = "de_millis_from_str")]
+    ts: u64,
+    checksum: i64,
+    #[serde(rename = "prevSeqId")]
+    prev_seq_id: i64,
+    #[serde(rename = "seqId")]
+    seq_id: i64,
+}
+
+/// A `funding-rate` push: the rate of the funding at `fundingTime`.
+#[derive(Serialize, Deserialize, Debug)]
+struct OkxFundingRateData {
+    #[serde(rename = "instId")]
+    inst_id: String,
+    #[serde(rename = "fundingRate", deserialize_with = "de_float_from_str")]
+    funding_rate: f64,
+    #[serde(deserialize_with = "de_millis_from_str")]
+    ts: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct OkxMarkPriceData {
+    #[serde(rename = "instId")]
+    inst_id: String,
+    #[serde(rename = "markPx", deserialize_with = "de_float_from_str")]
+    mark_px: f64,
+    #[serde(deserialize_with = "de_millis_from_str")]
+    ts: u64,
+}
+
+/// An `index-tickers` push for the index a swap tracks, e.g. `BTC-USDT` for
+/// `BTC-USDT-SWAP`.
+#[derive(Serialize, Deserialize, Debug)]
+struct OkxIndexTickerData {
+    #[serde(rename = "instId")]
+    inst_id: String,
+    #[serde(rename = "idxPx", deserialize_with = "de_float_from_str")]
+    idx_px: f64,
+    #[serde(deserialize_with = "de_millis_from_str")]
+    ts: u64,
+}
+
+/// A push on one of our channels; `data` is parsed once `arg` says which.
+/// `action` is only sent on `books`, as `snapshot` or `update`.
+#[derive(Serialize, Deserialize, Debug)]
+struct OkxMarketMessage {
+    arg: OkxChannelArg,
+    action: Option<String>,
+    data: Value,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct OkxChannelArg {
+    channel: String,
+    #[serde(rename = "instId")]
+    inst_id: String,
+}
+
+/// `subscribe` and `unsubscribe` confirmations, and `error` with a code and
+/// message.
+#[derive(Serialize, Deserialize, Debug)]
+struct OkxEvent {
+    event: String,
+    code: Option<String>,
+    msg: Option<String>,
+}
+
+const OKX_WS_API: &str = "wss://ws.okx.com:8443/ws/v5/public";
+
+const DEFAULT_SYMBOL: &str = "BTC-USDT";
+
+/// Levels per side OKX's `checksum` covers.
+const CHECKSUM_LEVELS: usize = 25;
+
+/// A local book and the `seqId` of the last update applied to it.
+struct OkxBook {
+    book: OrderBook,
+    seq_id: i64,
+}
+
+pub struct OkxSource {
+    endpoint: String,
+    symbols: Vec<String>,
+    /// Perpetual swaps, e.g. `BTC-USDT-SWAP`, whose funding rate, mark price
+    /// and index price are subscribed.
+    swaps: Vec<String>,
+    /// Levels per side written from each local book; `None` leaves books off.
+    book_levels: Option<usize>,
+    /// Books that have had their snapshot, by instId.
+    books: HashMap<String, OkxBook>,
+}
+
+impl OkxSource {
+    pub fn new(symbols: Vec<String>) -> Self {
+        OkxSource { endpoint: OKX_WS_API.to_string(), symbols, swaps: Vec::new(), book_levels: None, books: HashMap::new() }
+    }
+
+    /// Also keeps a local book per symbol from the `books` channel and writes
+    /// its best `levels` on each side.
+    pub fn with_book(mut self, levels: usize) -> Self {
+        self.book_levels = Some(levels);
+        self
+    }
+
+    /// Also subscribes `funding-rate` and `mark-price` for each of `swaps`,
+    /// perpetual swap instIds like `BTC-USDT-SWAP`, and `index-tickers` for
+    /// the index each tracks, on the same socket.
+    pub fn with_swaps(mut self, swaps: Vec<String>) -> Self {
+        self.swaps = swaps;
+        self
+    }
+
+    /// Connects to `endpoint` instead of `ws.okx.com`, e.g. a mock exchange in tests.
+    pub fn with_endpoint(mut self, endpoint: impl Into<String>) -> Self {
+        self.endpoint = endpoint.into();
+        self
+    }
+
+    /// A `subscribe` or `unsubscribe` request for every symbol's ticker and
+    /// trades, and its book if books are on, and every swap's funding rate,
+    /// mark price and index.
+    fn subscription(&self, op: &str) -> String {
+        let channels: &[&str] = if self.book_levels.is_some() { &["tickers", "trades", "books"] } else { &["tickers", "trades"] };
+        let indexes = self.swaps.iter().filter_map(|swap| swap.strip_suffix("-SWAP"));
+        let args: Vec<Value> = self
+            .symbols
+            .iter()
+            .flat_map(|symbol| channels.iter().map(move |channel| json!({"channel": channel, "instId": symbol})))
+            .chain(self.swaps.iter().flat_map(|swap| ["funding-rate", "mark-price"].map(|channel| json!({"channel": channel, "instId": swap}))))
+            .chain(indexes.map(|index| json!({"channel": "index-tickers", "instId": index})))
+            .collect();
+        json!({"op": op, "args": args}).to_string()
+    }
+
+    /// Drops `inst_id`'s book and asks for it again; OKX answers a fresh
+    /// `books` subscription with a snapshot.
+    fn resync(&mut self, inst_id: String, reason: String) -> Frame {
+        self.books.remove(&inst_id);
+        let messages = ["unsubscribe", "subscribe"]
+            .map(|op| json!({"op": op, "args": [{"channel": "books", "instId": inst_id}]}).to_string())
+            .to_vec();
+        Frame::Resync { symbol: inst_id, reason, messages }
+    }
+
+    fn parse_book(&mut self, inst_id: String, action: Option<&str>, data: Value, levels: usize) -> Result<Frame, OkxError> {
+        let updates: Vec<OkxBookData> = serde_json::from_value(data).map_err(OkxError::Malformed)?;
+        let Some(update) = updates.into_iter().next() else {
+            return Ok(Frame::Ignored);
+        };
+        if action == Some("snapshot") {
+            self.books.insert(inst_id.clone(), OkxBook { book: OrderBook::default(), seq_id: update.prev_seq_id });
+        }
+        // Updates sent between asking for a fresh book and its snapshot
+        let Some(synced) = self.books.get_mut(&inst_id) else {
+            return Ok(Frame::Ignored);
+        };
+        if update.prev_seq_id != synced.seq_id {
+            let reason = format!("sequence gap: prevSeqId {} after seqId {}", update.prev_seq_id, synced.seq_id);
+            return Ok(self.resync(inst_id, reason));
+        }
+        for (side, levels) in [(Side::Buy, update.bids), (Side::Sell, update.asks)] {
+            for level in levels {
+                let parsed = match level.as_slice() {
+                    [price, size, ..] => BookLevel::parse(price, size),
+                    _ => None,
+                };
+                let Some(parsed) = parsed else {
+                    return Ok(self.resync(inst_id, format!("bad level {:?}", level)));
+                };
+                synced.book.update(side, parsed);
+            }
+        }
+        synced.seq_id = update.seq_id;
+        let checksum = checksum(&synced.book);
+        if i64::from(checksum) != update.checksum {
+            let reason = format!("checksum {} doesn't match OKX's {}", checksum, update.checksum);
+            return Ok(self.resync(inst_id, reason));
+        }
+        let depth = synced.book.depth(&inst_id, levels, Some(update.ts));
+        // A crossed or one-sided book has fallen out of step with OKX's
+        if let Err(error) = depth.validate() {
+            return Ok(self.resync(inst_id, error.cause().to_string()));
+        }
+        Ok(Frame::Books(vec![depth]))
+    }
+
+    fn parse_market(&mut self, message: OkxMarketMessage) -> Result<Frame, OkxError> {
+        match message.arg.channel.as_str() {
+            "tickers" => {
+                let tickers: Vec<OkxTickerData> = serde_json::from_value(message.data).map_err(OkxError::Malformed)?;
+                Ok(Frame::Quotes(
+                    tickers
+                        .into_iter()
+                        .map(|ticker| Quote {
+                            symbol: ticker.inst_id,
+                            bid: ticker.bid_px,
+                            bid_vol: ticker.bid_sz,
+                            ask: ticker.ask_px,
+                            ask_vol: ticker.ask_sz,
+                            exchange_ts: Some(ticker.ts),
+                        })
+                        .collect(),
+                ))
+            }
+            "trades" => {
+                let trades: Vec<OkxTradeData> = serde_json::from_value(message.data).map_err(OkxError::Malformed)?;
+                Ok(Frame::Trades(
+                    trades
+                        .into_iter()
+                        .map(|trade| Trade {
+                            symbol: trade.inst_id,
+                            price: trade.px,
+                            size: trade.sz,
+                            side: trade.side,
+                            trade_id: Some(trade.trade_id),
+                            exchange_ts: Some(trade.ts),
+                        })
+                        .collect(),
+                ))
+            }
+            "funding-rate" => {
+                let rates: Vec<OkxFundingRateData> = serde_json::from_value(message.data).map_err(OkxError::Malformed)?;
+                Ok(Frame::Perps(
+                    rates
+                        .into_iter()
+                        .map(|rate| PerpStats {
+                            symbol: rate.inst_id,
+                            funding_rate: Some(rate.funding_rate),
+                            exchange_ts: Some(rate.ts),
+                            ..PerpStats::default()
+                        })
+                        .collect(),
+                ))
+            }
+            "mark-price" => {
+                let marks: Vec<OkxMarkPriceData> = serde_json::from_value(message.data).map_err(OkxError::Malformed)?;
+                Ok(Frame::Perps(
+                    marks
+                        .into_iter()
+                        .map(|mark| PerpStats {
+                            symbol: mark.inst_id,
+                            mark_price: Some(mark.mark_px),
+                            exchange_ts: Some(mark.ts),
+                            ..PerpStats::default()
+                        })
+                        .collect(),
+                ))
+            }
+            "index-tickers" => {
+                let indexes: Vec<OkxIndexTickerData> = serde_json::from_value(message.data).map_err(OkxError::Malformed)?;
+                Ok(Frame::Perps(
+                    indexes
+                        .into_iter()
+                        .map(|index| PerpStats {
+                            // A swap is named for the index it tracks
+                            symbol: format!("{}-SWAP", index.inst_id),
+                            index_price: Some(index.idx_px),
+                            exchange_ts: Some(index.ts),
+                            ..PerpStats::default()
+                        })
+                        .collect(),
+                ))
+            }
+            "books" => match self.book_levels {
+                Some(levels) => self.parse_book(message.arg.inst_id, message.action.as_deref(), message.data, levels),
+                None => Err(OkxError::UnexpectedChannel(message.arg.channel)),
+            },
+            _ => Err(OkxError::UnexpectedChannel(message.arg.channel)),
+        }
+    }
+}
+
+/// CRC32 of the best [`CHECKSUM_LEVELS`] bids and asks as OKX sent them,
+/// interleaved as `bidPx:bidSz:askPx:askSz:...`, read as a signed 32 bit
+/// number the way OKX sends it.
+fn checksum(book: &OrderBook) -> i32 {
+    let bids: Vec<&BookLevel> = book.bids().take(CHECKSUM_LEVELS).collect();
+    let asks: Vec<&BookLevel> = book.asks().take(CHECKSUM_LEVELS).collect();
+    let mut fields = Vec::new();
+    for index in 0..CHECKSUM_LEVELS {
+        for level in [bids.get(index), asks.get(index)].into_iter().flatten() {
+            fields.push(level.text.0.as_str());
+            fields.push(level.text.1.as_str());
+        }
+    }
+    crc32fast::hash(fields.join(":").as_bytes()) as i32
+}
+
+impl Default for OkxSource {
+    fn default() -> Self {
+        Self::new(vec![DEFAULT_SYMBOL.to_string()])
+    }
+}
+
+impl QuoteSource for OkxSource {
+    fn exchange(&self) -> &'static str {
+        "OKX"
+    }
+
+    fn symbols(&self) -> &[String] {
+        &self.symbols
+    }
+
+    fn instrument(&self, symbol: &str) -> Option<Instrument> {
+        match symbol.strip_suffix("-SWAP") {
+            Some(pair) => Instrument::parse(pair, InstrumentType::Perpetual),
+            None => Instrument::parse(symbol, InstrumentType::Spot),
+        }
+    }
+
+    fn endpoint(&mut self) -> Result<String, FeedError> {
+        Ok(self.endpoint.clone())
+    }
+
+    fn subscribe_messages(&mut self) -> Vec<String> {
+        self.books.clear();
+        vec![self.subscription("subscribe")]
+    }
+
+    fn unsubscribe_messages(&mut self) -> Vec<String> {
+        vec![self.subscription("unsubscribe")]
+    }
+
+    fn parse(&mut self, text: &str) -> Frame {
+        match serde_json::from_str::<OkxMarketMessage>(text) {
+            Ok(message) => self.parse_market(message).unwrap_or_else(|e| Frame::Error(e.into())),
+            Err(e) => match serde_json::from_str::<OkxEvent>(text) {
+                Ok(OkxEvent { event, code, msg }) if event == "error" => Frame::Error(
+                    OkxError::Rejected { code: code.unwrap_or_default(), msg: msg.unwrap_or_default() }.into(),
+                ),
+                Ok(OkxEvent { event, .. }) => Frame::Event(format!("Received {} confirmation", event)),
+                Err(_) => Frame::Error(OkxError::Malformed(e).into()),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn book(bids: &[[&str; 4]], asks: &[[&str; 4]]) -> OrderBook {
+        let mut book = OrderBook::default();
+        for (side, levels) in [(Side::Buy, bids), (Side::Sell, asks)] {
+            for [price, size, ..] in levels {
+                book.update(side, BookLevel::parse(price, size).unwrap());
+            }
+        }
+        book
+    }
+
+    /// The examples from OKX's order book checksum docs, with the text each
+    /// works out to.
+    #[test]
+    fn checksums_the_documented_books() {
+        let even = book(&[["3366.1", "7", "0", "3"], ["3366", "6", "3", "4"]], &[["3366.8", "9", "10", "3"], ["3368", "8", "3", "4"]]);
+        assert_eq!(checksum(&even), crc32fast::hash(b"3366.1:7:3366.8:9:3366:6:3368:8") as i32);
+        // More asks than bids: the asks past the last bid follow on their own
+        let uneven = book(&[["3366.1", "7", "0", "3"]], &[["3366.8", "9", "10", "3"], ["3368", "8", "3", "4"], ["3372", "8", "3", "4"]]);
+        assert_eq!(checksum(&uneven), crc32fast::hash(b"3366.1:7:3366.8:9:3368:8:3372:8") as i32);
+    }
+}
@@ -0,0 +1,26 @@
+use mock_exchange::{fixture_frames, parse_frames, parse_mangled};
+use rust_okx_quote_scraper::OkxSource;
+
+#[test]
+fn parses_fixtures() {
+    insta::glob!("fixtures/*.jsonl", |path| {
+        insta::assert_debug_snapshot!(parse_frames(&mut OkxSource::default(), &fixture_frames(path)));
+    });
+}
+
+#[test]
+fn parses_book_fixtures() {
+    insta::glob!("fixtures/book/*.jsonl", |path| {
+        insta::assert_debug_snapshot!(parse_frames(&mut OkxSource::default().with_book(3), &fixture_frames(path)));
+    });
+}
+
+#[test]
+fn never_panics_on_mangled_fixtures() {
+    insta::glob!("fixtures/*.jsonl", |path| {
+        parse_mangled(OkxSource::default, &fixture_frames(path));
+    });
+    insta::glob!("fixtures/book/*.jsonl", |path| {
+        parse_mangled(|| OkxSource::default().with_book(3), &fixture_frames(path));
+    });
+}
@@ -0,0 +1,95 @@
+use scraper_core::ReplaySpeed;
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+
+pub const USAGE: &str = "\
+Usage: rust-quote-replay [OPTIONS] <RECORDING>...
+
+Replays raw frame recordings through the exchange parsers into Redis.
+Each RECORDING is a .jsonl.gz file or a directory holding them, all from one exchange.
+
+Options:
+  --speed <SPEED>          1x for real time, 10x, 0.5x, or max [default: max]
+  --db <DB>                Redis database to write to [default: 0]
+  --namespace <PREFIX>     Write every key under PREFIX: and label series NAMESPACE=PREFIX
+  --retention-secs <SECS>  History each series keeps, 0 for all of it [default: 3600]
+  --exchange <EXCHANGE>    Exchange the recordings are from, e.g. BINANCE [default: from the file names]
+  --book-levels <LEVELS>   Rebuild order books and write LEVELS a side (Bitfinex, Kraken and OKX)
+  -h, --help               Print this help";
+
+#[derive(Debug)]
+pub struct Args {
+    pub speed: ReplaySpeed,
+    pub db: u32,
+    pub namespace: Option<String>,
+    pub retention: Option<Duration>,
+    pub exchange: Option<String>,
+    pub book_levels: Option<usize>,
+    /// Every recording file, oldest first.
+    pub files: Vec<PathBuf>,
+}
+
+impl Args {
+    /// `None` when `--help` was asked for.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Option<Self>, Box<dyn Error>> {
+        let mut parsed = Args { speed: ReplaySpeed::Max, db: 0, namespace: None, retention: None, exchange: None, book_levels: None, files: Vec::new() };
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or_else(|| format!("{} needs a value", arg));
+            match arg.as_str() {
+                "-h" | "--help" => return Ok(None),
+                "--speed" => parsed.speed = value()?.parse()?,
+                "--db" => parsed.db = value()?.parse()?,
+                "--namespace" => parsed.namespace = Some(value()?),
+                "--retention-secs" => parsed.retention = Some(Duration::from_secs(value()?.parse()?)),
+                "--exchange" => parsed.exchange = Some(value()?.to_uppercase()),
+                "--book-levels" => match value()?.parse()? {
+                    0 => return Err("--book-levels must be at least 1".into()),
+                    levels => parsed.book_levels = Some(levels),
+                },
+                flag if flag.starts_with('-') => return Err(format!("unknown option {}", flag).into()),
+                path => parsed.files.extend(recording_files(PathBuf::from(path))?),
+            }
+        }
+        if parsed.files.is_empty() {
+            return Err("no recordings given".into());
+        }
+        // Recordings are named after their start time, so name order is time order
+        parsed.files.sort_by(|a, b| a.file_name().cmp(&b.file_name()));
+        Ok(Some(parsed))
+    }
+
+    /// The `--exchange` given, or the `EXCHANGE` every file name starts with.
+    pub fn exchange(&self) -> Result<String, Box<dyn Error>> {
+        if let Some(exchange) = &self.exchange {
+            return Ok(exchange.clone());
+        }
+        let mut exchanges = self.files.iter().map(|path| {
+            let name = path.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+            name.split('-').next().unwrap_or_default().to_string()
+        });
+        let first = exchanges.next().unwrap_or_default();
+        if exchanges.any(|exchange| exchange != first) {
+            return Err("recordings are from more than one exchange, replay them separately".into());
+        }
+        Ok(first)
+    }
+}
+
+fn recording_files(path: PathBuf) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    if !path.is_dir() {
+        return Ok(vec![path]);
+    }
+    let mut files = Vec::new();
+    for entry in fs::read_dir(&path).map_err(|e| format!("Can't read {}: {}", path.display(), e))? {
+        let file = entry?.path();
+        if file.is_dir() {
+            files.extend(recording_files(file)?);
+        } else if file.to_string_lossy().ends_with(".jsonl.gz") {
+            files.push(file);
+        }
+    }
+    Ok(files)
+}
@@ -0,0 +1,139 @@
+mod args;
+
+use args::{Args, USAGE};
+use rust_binance_quote_scraper::BinanceSource;
+use rust_bitfinex_quote_scraper::BitfinexSource;
+use rust_bitget_quote_scraper::BitgetSource;
+use rust_bitmex_quote_scraper::BitmexSource;
+use rust_bybit_quote_scraper::BybitSource;
+use rust_coinbase_quote_scraper::CoinbaseSource;
+use rust_gateio_quote_scraper::GateioSource;
+use rust_htx_quote_scraper::HtxSource;
+use rust_kraken_quote_scraper::KrakenSource;
+use rust_kucoin_quote_scraper::KucoinSource;
+use rust_mexc_quote_scraper::MexcSource;
+use rust_okx_quote_scraper::OkxSource;
+use scraper_core::{init_logging, read_recording, QuoteSource, Recorded, RedisSink, Shutdown};
+use std::env;
+use std::error::Error;
+use std::path::PathBuf;
+use std::process;
+use tracing::{info, warn};
+
+/// Every recorded frame of `files` in order. A file cut short, e.g. one
+/// still being written, is replayed up to where it breaks off.
+fn recording(files: Vec<PathBuf>) -> impl Iterator<Item = Recorded> {
+    files.into_iter().flat_map(|path| {
+        info!(event = "replay_file", path = %path.display(), "Replaying recording");
+        let lines: Box<dyn Iterator<Item = std::io::Result<Recorded>>> = match read_recording(&path) {
+            Ok(lines) => Box::new(lines),
+            Err(e) => {
+                warn!(event = "replay_file_failed", path = %path.display(), error = %e, "Can't open recording, skipping it");
+                Box::new(std::iter::empty())
+            }
+        };
+        lines.map_while(move |line| {
+            line.map_err(|e| {
+                warn!(event = "replay_file_truncated", path = %path.display(), error = %e, "Recording breaks off here")
+            })
+            .ok()
+        })
+    })
+}
+
+async fn replay_with<S: QuoteSource>(mut source: S, sink: RedisSink, args: Args, shutdown: Shutdown) -> Result<(), Box<dyn Error>> {
+    let exchange = source.exchange();
+    let stats = scraper_core::replay(&mut source, sink, recording(args.files), args.speed, shutdown).await?;
+    info!(
+        event = "replay_finished",
+        exchange,
+        connections = stats.connections,
+        frames = stats.frames,
+        quotes = stats.quotes,
+        tickers = stats.tickers,
+        trades = stats.trades,
+        books = stats.books,
+        perps = stats.perps,
+        resyncs = stats.resyncs,
+        invalid = stats.invalid,
+        rejected = stats.rejected,
+        "Replay finished"
+    );
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    init_logging();
+    let args = match Args::parse(env::args().skip(1)) {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            println!("{}", USAGE);
+            return Ok(());
+        }
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            process::exit(2);
+        }
+    };
+    let exchange = args.exchange()?;
+
+    let redis_host = env::var("REDIS_HOST").unwrap_or("cache".to_string());
+    let redis_password = env::var("REDIS_PASSWORD").map_err(|_| "$REDIS_PASSWORD is not set")?;
+    let connection_string = format!("redis://default:{}@{}:6379/{}", redis_password, redis_host, args.db);
+    // Its own spool, so a replay never touches a live scraper's
+    let mut sink = RedisSink::connect(&connection_string)
+        .await?
+        .with_spool_dir(env::temp_dir().join("scraper-replay-spool"));
+    if let Some(namespace) = &args.namespace {
+        sink = sink.with_namespace(namespace);
+    }
+    if let Some(retention) = args.retention {
+        sink = sink.with_retention(retention);
+    }
+    let shutdown = Shutdown::on_signals();
+
+    if args.book_levels.is_some() {
+        match exchange.as_str() {
+            "BITFINEX" | "KRAKEN" | "OKX" => {}
+            "BINANCE" => return Err("Binance books start from REST snapshots, which aren't recorded, so --book-levels can't rebuild them".into()),
+            other => return Err(format!("{} keeps no order books, drop --book-levels", other).into()),
+        }
+    }
+    let book_levels = args.book_levels;
+    match exchange.as_str() {
+        "BINANCE" => replay_with(BinanceSource::default(), sink, args, shutdown).await,
+        "BINANCE_USDM" => replay_with(BinanceSource::default().usd_m(), sink, args, shutdown).await,
+        "BITFINEX" => {
+            let mut source = BitfinexSource::default();
+            if let Some(levels) = book_levels {
+                source = source.with_book(levels);
+            }
+            replay_with(source, sink, args, shutdown).await
+        }
+        "BITGET" => replay_with(BitgetSource::default(), sink, args, shutdown).await,
+        "BITMEX" => replay_with(BitmexSource::default(), sink, args, shutdown).await,
+        "BYBIT" => replay_with(BybitSource::default(), sink, args, shutdown).await,
+        "BYBIT_LINEAR" => replay_with(BybitSource::default().linear(), sink, args, shutdown).await,
+        "COINBASE" => replay_with(CoinbaseSource::default(), sink, args, shutdown).await,
+        "GATEIO" => replay_with(GateioSource::default(), sink, args, shutdown).await,
+        "HTX" => replay_with(HtxSource::default(), sink, args, shutdown).await,
+        "KRAKEN" => {
+            let mut source = KrakenSource::default();
+            if let Some(levels) = book_levels {
+                source = source.with_book(levels);
+            }
+            replay_with(source, sink, args, shutdown).await
+        }
+        "KUCOIN" => replay_with(KucoinSource::default(), sink, args, shutdown).await,
+        "MEXC" => replay_with(MexcSource::default(), sink, args, shutdown).await,
+        "OKX" => {
+            let mut source = OkxSource::default();
+            if let Some(levels) = book_levels {
+                source = source.with_book(levels);
+            }
+            replay_with(source, sink, args, shutdown).await
+        }
+        other => Err(format!("no parser for exchange {:?}", other).into()),
+    }
+}
@@ -0,0 +1,275 @@
+use scraper_core::{HttpOptions, RunOptions};
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum Exchange {
+    Binance,
+    /// Binance USDⓈ-M perpetuals, a feed of its own.
+    #[serde(rename = "binance_usdm")]
+    BinanceUsdm,
+    Bitfinex,
+    Bitget,
+    Bitmex,
+    Bybit,
+    /// Bybit linear perpetuals, a feed of its own.
+    #[serde(rename = "bybit_linear")]
+    BybitLinear,
+    Coinbase,
+    Gateio,
+    Htx,
+    Kraken,
+    Kucoin,
+    Mexc,
+    Okx,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct FeedConfig {
+    pub exchange: Exchange,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// Venue-native instruments, all subscribed on the feed's one socket.
+    /// Omit to use the exchange's default BTC instrument.
+    pub symbols: Option<Vec<String>>,
+    /// Seconds without market data before the feed is marked stale and
+    /// reconnected. Falls back to `$STALE_AFTER_SECS`, then the exchange's
+    /// own default.
+    pub stale_after_secs: Option<u64>,
+    /// Keeps a local L2 book per symbol and writes this many levels a side.
+    /// Binance, Bitfinex, Kraken and OKX only; omit to leave books off.
+    pub book_levels: Option<usize>,
+    /// Perpetual swaps whose funding rate, mark price and index are subscribed
+    /// next to `symbols`, e.g. `BTC-USDT-SWAP`. OKX only.
+    pub swaps: Option<Vec<String>>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct RedisConfig {
+    /// Falls back to `$REDIS_HOST`, then `cache`. The password is always read
+    /// from `$REDIS_PASSWORD` so it never has to live in the config file.
+    pub host: Option<String>,
+    #[serde(default = "default_redis_port")]
+    pub port: u16,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct SpoolConfig {
+    /// Where quotes are spooled while Redis is down. Falls back to
+    /// `$SPOOL_DIR`, then `scraper-spool` under the system temp dir.
+    pub dir: Option<PathBuf>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct SinksConfig {
+    #[serde(default)]
+    pub redis: RedisConfig,
+    #[serde(default)]
+    pub spool: SpoolConfig,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct HttpConfig {
+    /// Port `/metrics`, `/healthz` and `/readyz` are served on. Falls back to
+    /// `$HTTP_PORT`, then 9000.
+    pub port: Option<u16>,
+    /// Seconds within which every feed must have had market data for `/readyz`
+    /// to pass. Falls back to `$READY_WITHIN_SECS`, then 60.
+    pub ready_within_secs: Option<u64>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct RecorderConfig {
+    /// Records every inbound frame of every feed under this directory. Falls
+    /// back to `$RECORD_DIR`; unset means nothing is recorded.
+    pub dir: Option<PathBuf>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Config {
+    #[serde(default)]
+    pub sinks: SinksConfig,
+    #[serde(default)]
+    pub http: HttpConfig,
+    #[serde(default)]
+    pub recorder: RecorderConfig,
+    #[serde(default)]
+    pub feeds: Vec<FeedConfig>,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+fn default_redis_port() -> u16 {
+    6379
+}
+
+impl RedisConfig {
+    pub fn host(&self) -> String {
+        self.host
+            .clone()
+            .unwrap_or_else(|| env::var("REDIS_HOST").unwrap_or("cache".to_string()))
+    }
+}
+
+impl HttpConfig {
+    pub fn options(&self) -> HttpOptions {
+        let defaults = HttpOptions::from_env();
+        HttpOptions {
+            port: self.port.unwrap_or(defaults.port),
+            ready_within: self.ready_within_secs.map_or(defaults.ready_within, Duration::from_secs),
+        }
+    }
+}
+
+impl Exchange {
+    /// Whether the exchange's source can keep local order books.
+    pub fn has_books(self) -> bool {
+        matches!(self, Exchange::Binance | Exchange::Bitfinex | Exchange::Kraken | Exchange::Okx)
+    }
+}
+
+impl FeedConfig {
+    pub fn run_options(&self, recorder: &RecorderConfig) -> RunOptions {
+        let defaults = RunOptions::from_env();
+        RunOptions {
+            stale_after: self.stale_after_secs.map(Duration::from_secs).or(defaults.stale_after),
+            record_dir: recorder.dir.clone().or(defaults.record_dir),
+        }
+    }
+}
+
+impl Config {
+    pub fn load(path: &str) -> Result<Self, Box<dyn Error>> {
+        let contents = fs::read_to_string(path).map_err(|e| format!("Can't read config {}: {}", path, e))?;
+        Self::parse(&contents)
+    }
+
+    fn parse(contents: &str) -> Result<Self, Box<dyn Error>> {
+        let config: Config = toml::from_str(contents)?;
+        let mut exchanges = HashSet::new();
+        // The exchange names a feed's spool, health entry, metrics and latency keys
+        for feed in config.enabled_feeds() {
+            if !exchanges.insert(feed.exchange) {
+                return Err(format!("{:?} feed: more than one is enabled, list all its symbols in one feed", feed.exchange).into());
+            }
+        }
+        for feed in &config.feeds {
+            match feed.book_levels {
+                Some(0) => return Err(format!("{:?} feed: book_levels must be at least 1", feed.exchange).into()),
+                Some(_) if !feed.exchange.has_books() => {
+                    return Err(format!("{:?} feed: book_levels isn't supported for this exchange", feed.exchange).into())
+                }
+                _ => {}
+            }
+            if feed.swaps.is_some() && feed.exchange != Exchange::Okx {
+                return Err(format!("{:?} feed: swaps is only supported for OKX", feed.exchange).into());
+            }
+        }
+        Ok(config)
+    }
+
+    pub fn enabled_feeds(&self) -> impl Iterator<Item = &FeedConfig> {
+        self.feeds.iter().filter(|feed| feed.enabled)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rejection(contents: &str) -> String {
+        Config::parse(contents).expect_err("config was accepted").to_string()
+    }
+
+    #[test]
+    fn parses_the_sample_config() {
+        let config = Config::parse(include_str!("../scraper.toml")).unwrap();
+        assert_eq!(config.sinks.redis.port, 6379);
+        assert_eq!(config.feeds.len(), 14);
+        assert_eq!(config.enabled_feeds().count(), 14);
+
+        let okx = config.feeds.iter().find(|feed| feed.exchange == Exchange::Okx).unwrap();
+        assert_eq!(okx.symbols.as_deref().unwrap(), ["BTC-USDT", "ETH-USDT", "SOL-USDT"]);
+        assert_eq!(okx.swaps.as_deref().unwrap(), ["BTC-USDT-SWAP", "ETH-USDT-SWAP", "SOL-USDT-SWAP"]);
+        assert_eq!(okx.book_levels, None);
+    }
+
+    #[test]
+    fn parses_every_feed_setting() {
+        let config = Config::parse(
+            r#"
+            [sinks.redis]
+            host = "redis.internal"
+            port = 6380
+
+            [[feeds]]
+            exchange = "kraken"
+            symbols = ["BTC/USD"]
+            stale_after_secs = 30
+            book_levels = 25
+
+            [[feeds]]
+            exchange = "bybit_linear"
+            enabled = false
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.sinks.redis.host(), "redis.internal");
+        assert_eq!(config.sinks.redis.port, 6380);
+
+        let kraken = &config.feeds[0];
+        assert_eq!(kraken.exchange, Exchange::Kraken);
+        assert_eq!(kraken.book_levels, Some(25));
+        let options = kraken.run_options(&config.recorder);
+        assert_eq!(options.stale_after, Some(Duration::from_secs(30)));
+
+        assert_eq!(config.feeds[1].exchange, Exchange::BybitLinear);
+        assert_eq!(config.feeds[1].symbols, None);
+        let enabled: Vec<Exchange> = config.enabled_feeds().map(|feed| feed.exchange).collect();
+        assert_eq!(enabled, [Exchange::Kraken]);
+    }
+
+    #[test]
+    fn rejects_zero_book_levels() {
+        let error = rejection("[[feeds]]\nexchange = \"binance\"\nbook_levels = 0\n");
+        assert_eq!(error, "Binance feed: book_levels must be at least 1");
+    }
+
+    #[test]
+    fn rejects_book_levels_without_books() {
+        let error = rejection("[[feeds]]\nexchange = \"bybit\"\nbook_levels = 10\n");
+        assert_eq!(error, "Bybit feed: book_levels isn't supported for this exchange");
+    }
+
+    #[test]
+    fn rejects_swaps_outside_okx() {
+        let error = rejection("[[feeds]]\nexchange = \"binance_usdm\"\nswaps = [\"BTC-USDT-SWAP\"]\n");
+        assert_eq!(error, "BinanceUsdm feed: swaps is only supported for OKX");
+    }
+
+    #[test]
+    fn rejects_duplicate_exchanges_unless_disabled() {
+        let duplicated = r#"
+            [[feeds]]
+            exchange = "binance"
+            symbols = ["BTCUSDT"]
+
+            [[feeds]]
+            exchange = "binance"
+            symbols = ["ETHUSDT"]
+            "#;
+        let error = rejection(duplicated);
+        assert!(error.starts_with("Binance feed: more than one is enabled"), "{}", error);
+
+        let config = Config::parse(&format!("{}enabled = false\n", duplicated)).unwrap();
+        assert_eq!(config.enabled_feeds().count(), 1);
+    }
+}
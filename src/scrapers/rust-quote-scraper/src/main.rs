@@ -0,0 +1,137 @@
+mod config;
+
+use config::{Config, Exchange, FeedConfig, RecorderConfig};
+use rust_binance_quote_scraper::BinanceSource;
+use rust_bitfinex_quote_scraper::BitfinexSource;
+use rust_bitget_quote_scraper::BitgetSource;
+use rust_bitmex_quote_scraper::BitmexSource;
+use rust_bybit_quote_scraper::BybitSource;
+use rust_coinbase_quote_scraper::CoinbaseSource;
+use rust_gateio_quote_scraper::GateioSource;
+use rust_htx_quote_scraper::HtxSource;
+use rust_kraken_quote_scraper::KrakenSource;
+use rust_kucoin_quote_scraper::KucoinSource;
+use rust_mexc_quote_scraper::MexcSource;
+use rust_okx_quote_scraper::OkxSource;
+use scraper_core::{init_logging, spawn_http_server, QuoteSource, RedisSink, RunOptions, Shutdown};
+use std::env;
+use tokio::task::JoinHandle;
+use tracing::{error, info};
+
+const DEFAULT_CONFIG_PATH: &str = "scraper.toml";
+
+/// Runs one feed until shutdown; the handle yields whether it stopped cleanly.
+fn spawn_feed<S: QuoteSource + Send + 'static>(
+    mut source: S,
+    sink: RedisSink,
+    options: RunOptions,
+    shutdown: Shutdown,
+) -> JoinHandle<bool> {
+    let exchange = source.exchange();
+    info!(event = "feed_starting", exchange, "Starting feed");
+    tokio::spawn(async move {
+        match scraper_core::run(&mut source, sink, options, shutdown).await {
+            Ok(()) => {
+                info!(event = "feed_stopped", exchange, "Feed stopped");
+                true
+            }
+            Err(e) => {
+                error!(event = "feed_failed", exchange, error = %e, "Feed failed");
+                false
+            }
+        }
+    })
+}
+
+fn start(feed: &FeedConfig, recorder: &RecorderConfig, sink: RedisSink, shutdown: Shutdown) -> JoinHandle<bool> {
+    let symbols = feed.symbols.clone();
+    let options = feed.run_options(recorder);
+    // Config::load has already turned book_levels and swaps down for the other exchanges
+    let book_levels = feed.book_levels;
+    match feed.exchange {
+        Exchange::Binance => {
+            let mut source = symbols.map(BinanceSource::new).unwrap_or_default();
+            if let Some(levels) = book_levels {
+                source = source.with_book(levels);
+            }
+            spawn_feed(source, sink, options, shutdown)
+        }
+        Exchange::BinanceUsdm => spawn_feed(symbols.map(BinanceSource::new).unwrap_or_default().usd_m(), sink, options, shutdown),
+        Exchange::Bitfinex => {
+            let mut source = symbols.map(BitfinexSource::new).unwrap_or_default();
+            if let Some(levels) = book_levels {
+                source = source.with_book(levels);
+            }
+            spawn_feed(source, sink, options, shutdown)
+        }
+        Exchange::Bitget => spawn_feed(symbols.map(BitgetSource::new).unwrap_or_default(), sink, options, shutdown),
+        Exchange::Bitmex => spawn_feed(symbols.map(BitmexSource::new).unwrap_or_default(), sink, options, shutdown),
+        Exchange::Bybit => spawn_feed(symbols.map(BybitSource::new).unwrap_or_default(), sink, options, shutdown),
+        Exchange::BybitLinear => spawn_feed(symbols.map(BybitSource::new).unwrap_or_default().linear(), sink, options, shutdown),
+        Exchange::Coinbase => spawn_feed(symbols.map(CoinbaseSource::new).unwrap_or_default(), sink, options, shutdown),
+        Exchange::Gateio => spawn_feed(symbols.map(GateioSource::new).unwrap_or_default(), sink, options, shutdown),
+        Exchange::Htx => spawn_feed(symbols.map(HtxSource::new).unwrap_or_default(), sink, options, shutdown),
+        Exchange::Kraken => {
+            let mut source = symbols.map(KrakenSource::new).unwrap_or_default();
+            if let Some(levels) = book_levels {
+                source = source.with_book(levels);
+            }
+            spawn_feed(source, sink, options, shutdown)
+        }
+        Exchange::Kucoin => spawn_feed(symbols.map(KucoinSource::new).unwrap_or_default(), sink, options, shutdown),
+        Exchange::Mexc => spawn_feed(symbols.map(MexcSource::new).unwrap_or_default(), sink, options, shutdown),
+        Exchange::Okx => {
+            let mut source = symbols.map(OkxSource::new).unwrap_or_default();
+            if let Some(levels) = book_levels {
+                source = source.with_book(levels);
+            }
+            if let Some(swaps) = feed.swaps.clone() {
+                source = source.with_swaps(swaps);
+            }
+            spawn_feed(source, sink, options, shutdown)
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    init_logging();
+    let config_path = env::args()
+        .nth(1)
+        .or_else(|| env::var("SCRAPER_CONFIG").ok())
+        .unwrap_or(DEFAULT_CONFIG_PATH.to_string());
+    let config = Config::load(&config_path)?;
+    spawn_http_server(config.http.options());
+
+    let redis = &config.sinks.redis;
+    let mut sink = RedisSink::connect_to_host(&redis.host(), redis.port).await?;
+    if let Some(dir) = &config.sinks.spool.dir {
+        sink = sink.with_spool_dir(dir);
+    }
+    let shutdown = Shutdown::on_signals();
+
+    let handles: Vec<JoinHandle<bool>> = config
+        .enabled_feeds()
+        .map(|feed| start(feed, &config.recorder, sink.clone(), shutdown.clone()))
+        .collect();
+
+    if handles.is_empty() {
+        return Err(format!("No enabled feeds in {}", config_path).into());
+    }
+
+    let mut failed = 0;
+    for handle in handles {
+        match handle.await {
+            Ok(true) => {}
+            Ok(false) => failed += 1,
+            Err(_) => {
+                error!(event = "feed_panicked", "A feed task panicked");
+                failed += 1;
+            }
+        }
+    }
+    if failed > 0 {
+        return Err(format!("{} feed(s) did not shut down cleanly", failed).into());
+    }
+    Ok(())
+}
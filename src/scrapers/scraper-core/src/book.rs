@@ -0,0 +1,193 @@
+use crate::error::FeedError;
+use crate::quote::imbalance;
+use crate::trade::Side;
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+
+/// How far from the mid, in basis points, [`OrderBook::depth`] sums each
+/// side's resting size.
+pub const DEPTH_BANDS_BPS: [u32; 3] = [10, 25, 50];
+
+/// A book key; prices are finite, so `total_cmp` orders them as numbers.
+#[derive(Clone, Copy, Debug)]
+struct Price(f64);
+
+impl PartialEq for Price {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Price {}
+
+impl PartialOrd for Price {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Price {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+/// One price level of an [`OrderBook`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct BookLevel {
+    pub price: f64,
+    pub size: f64,
+    /// Price and size as the venue wrote them. Checksums are computed over
+    /// these rather than the parsed numbers.
+    pub text: (String, String),
+}
+
+impl BookLevel {
+    /// `None` unless both are finite numbers, and the price a positive one.
+    pub fn parse(price: &str, size: &str) -> Option<Self> {
+        let parsed_price: f64 = price.parse().ok()?;
+        let parsed_size: f64 = size.parse().ok()?;
+        if !(parsed_price.is_finite() && parsed_price > 0.0 && parsed_size.is_finite()) {
+            return None;
+        }
+        Some(BookLevel { price: parsed_price, size: parsed_size, text: (price.to_string(), size.to_string()) })
+    }
+}
+
+/// A venue's L2 book for one symbol, rebuilt locally from a snapshot and the
+/// updates after it. Bids are [`Side::Buy`] and asks [`Side::Sell`]. Keeping
+/// it in step with the venue, by sequence numbers or checksums, is up to the
+/// source.
+#[derive(Clone, Debug, Default)]
+pub struct OrderBook {
+    bids: BTreeMap<Price, BookLevel>,
+    asks: BTreeMap<Price, BookLevel>,
+}
+
+impl OrderBook {
+    pub fn clear(&mut self) {
+        self.bids.clear();
+        self.asks.clear();
+    }
+
+    fn side_mut(&mut self, side: Side) -> &mut BTreeMap<Price, BookLevel> {
+        match side {
+            Side::Buy => &mut self.bids,
+            Side::Sell => &mut self.asks,
+        }
+    }
+
+    /// Replaces the level at `level.price`; a size of zero or less removes it.
+    pub fn update(&mut self, side: Side, level: BookLevel) {
+        let levels = self.side_mut(side);
+        if level.size > 0.0 {
+            levels.insert(Price(level.price), level);
+        } else {
+            levels.remove(&Price(level.price));
+        }
+    }
+
+    /// Drops every level past the best `depth` on each side, for venues that
+    /// stop sending updates for levels that fall out of the subscribed depth.
+    pub fn truncate(&mut self, depth: usize) {
+        while self.bids.len() > depth {
+            self.bids.pop_first();
+        }
+        while self.asks.len() > depth {
+            self.asks.pop_last();
+        }
+    }
+
+    /// Best first.
+    pub fn bids(&self) -> impl Iterator<Item = &BookLevel> {
+        self.bids.values().rev()
+    }
+
+    /// Best first.
+    pub fn asks(&self) -> impl Iterator<Item = &BookLevel> {
+        self.asks.values()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bids.is_empty() && self.asks.is_empty()
+    }
+
+    /// The best `levels` on each side and, when both sides have a level, the
+    /// size resting within each of [`DEPTH_BANDS_BPS`] of the mid.
+    pub fn depth(&self, symbol: &str, levels: usize, exchange_ts: Option<u64>) -> BookDepth {
+        let bands = match (self.bids().next(), self.asks().next()) {
+            (Some(bid), Some(ask)) => {
+                let mid = (bid.price + ask.price) / 2.0;
+                DEPTH_BANDS_BPS
+                    .iter()
+                    .map(|&bps| {
+                        let reach = mid * bps as f64 / 10_000.0;
+                        DepthBand {
+                            bps,
+                            bid_vol: self.bids().take_while(|level| level.price >= mid - reach).map(|level| level.size).sum(),
+                            ask_vol: self.asks().take_while(|level| level.price <= mid + reach).map(|level| level.size).sum(),
+                        }
+                    })
+                    .collect()
+            }
+            _ => Vec::new(),
+        };
+        BookDepth {
+            symbol: symbol.to_string(),
+            bids: self.bids().take(levels).map(|level| (level.price, level.size)).collect(),
+            asks: self.asks().take(levels).map(|level| (level.price, level.size)).collect(),
+            bands,
+            exchange_ts,
+        }
+    }
+}
+
+/// Size resting within `bps` basis points of the mid on each side.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DepthBand {
+    pub bps: u32,
+    pub bid_vol: f64,
+    pub ask_vol: f64,
+}
+
+impl DepthBand {
+    /// As [`crate::Quote::imbalance`], over the size within the band.
+    pub fn imbalance(&self) -> Option<f64> {
+        imbalance(self.bid_vol, self.ask_vol)
+    }
+}
+
+/// What gets written of an [`OrderBook`] after each update.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BookDepth {
+    /// Instrument as the venue names it, as in [`crate::Quote::symbol`].
+    pub symbol: String,
+    /// `(price, size)`, best first.
+    pub bids: Vec<(f64, f64)>,
+    /// `(price, size)`, best first.
+    pub asks: Vec<(f64, f64)>,
+    /// One per [`DEPTH_BANDS_BPS`], computed over the whole local book.
+    pub bands: Vec<DepthBand>,
+    /// When the venue says the book last changed, in epoch millis.
+    pub exchange_ts: Option<u64>,
+}
+
+impl BookDepth {
+    /// Both sides need a level and the book mustn't be crossed; a crossed
+    /// book is one that has fallen out of step with the venue.
+    pub fn validate(&self) -> Result<(), FeedError> {
+        let (Some(&(bid, _)), Some(&(ask, _))) = (self.bids.first(), self.asks.first()) else {
+            return Err(FeedError::validation(format!("book for {} has an empty side", self.symbol)));
+        };
+        if bid >= ask {
+            return Err(FeedError::validation(format!("book for {} is crossed: bid {} ask {}", self.symbol, bid, ask)));
+        }
+        Ok(())
+    }
+
+    /// As [`crate::Quote::imbalance`], over every level in `bids` and `asks`.
+    pub fn imbalance(&self) -> Option<f64> {
+        let side = |levels: &[(f64, f64)]| levels.iter().map(|(_, size)| size).sum::<f64>();
+        imbalance(side(&self.bids), side(&self.asks))
+    }
+}
@@ -0,0 +1,102 @@
+use std::error::Error;
+use std::fmt;
+
+/// The underlying error, kept so it can be matched on or reported with its
+/// own message.
+pub type Cause = Box<dyn Error + Send + Sync>;
+
+/// Why a feed couldn't do something, by the stage that failed. Exchanges
+/// define their own error enums and convert them into this; the stage picks
+/// what the runner does about it, see [`FeedError::policy`].
+#[derive(Debug)]
+pub enum FeedError {
+    /// Fetching the endpoint or a session token, opening the socket or
+    /// writing to it.
+    Connect(Cause),
+    /// The venue refused a subscription, e.g. for a symbol it doesn't list.
+    Subscribe(Cause),
+    /// A frame in no format the venue is known to send.
+    Decode(Cause),
+    /// A well-formed frame carrying values that can't be stored, such as an
+    /// empty or zero price.
+    Validation(Cause),
+    /// The Redis writer has stopped.
+    Sink(Cause),
+}
+
+/// What the runner does about a [`FeedError`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Policy {
+    /// Drop the frame or quote, count it and read on.
+    Skip,
+    /// Drop the connection and reconnect with backoff.
+    Reconnect,
+    /// Stop the feed; [`crate::run`] returns the error.
+    Abort,
+}
+
+impl FeedError {
+    pub fn connect(cause: impl Into<Cause>) -> Self {
+        FeedError::Connect(cause.into())
+    }
+
+    pub fn subscribe(cause: impl Into<Cause>) -> Self {
+        FeedError::Subscribe(cause.into())
+    }
+
+    pub fn decode(cause: impl Into<Cause>) -> Self {
+        FeedError::Decode(cause.into())
+    }
+
+    pub fn validation(cause: impl Into<Cause>) -> Self {
+        FeedError::Validation(cause.into())
+    }
+
+    pub fn sink(cause: impl Into<Cause>) -> Self {
+        FeedError::Sink(cause.into())
+    }
+
+    /// Bad frames and bad values are skipped, connection trouble is retried,
+    /// and a refused subscription or a dead Redis writer stops the feed,
+    /// since retrying can't fix either.
+    pub fn policy(&self) -> Policy {
+        match self {
+            FeedError::Decode(_) | FeedError::Validation(_) => Policy::Skip,
+            FeedError::Connect(_) => Policy::Reconnect,
+            FeedError::Subscribe(_) | FeedError::Sink(_) => Policy::Abort,
+        }
+    }
+
+    /// Value of the `kind` label, e.g. `decode`.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            FeedError::Connect(_) => "connect",
+            FeedError::Subscribe(_) => "subscribe",
+            FeedError::Decode(_) => "decode",
+            FeedError::Validation(_) => "validation",
+            FeedError::Sink(_) => "sink",
+        }
+    }
+
+    pub fn cause(&self) -> &Cause {
+        match self {
+            FeedError::Connect(cause)
+            | FeedError::Subscribe(cause)
+            | FeedError::Decode(cause)
+            | FeedError::Validation(cause)
+            | FeedError::Sink(cause) => cause,
+        }
+    }
+}
+
+impl fmt::Display for FeedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} failed: {}", self.kind(), self.cause())
+    }
+}
+
+impl Error for FeedError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(self.cause().as_ref())
+    }
+}
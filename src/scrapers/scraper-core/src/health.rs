@@ -0,0 +1,51 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+/// What `/readyz` knows about one feed.
+#[derive(Default)]
+struct FeedHealth {
+    subscribed: bool,
+    last_data: Option<Instant>,
+}
+
+/// Every feed running in this process, by exchange.
+static FEEDS: LazyLock<Mutex<BTreeMap<&'static str, FeedHealth>>> = LazyLock::new(Default::default);
+
+pub(crate) fn register(exchange: &'static str) {
+    FEEDS.lock().unwrap().entry(exchange).or_default();
+}
+
+/// Called once the subscriptions are sent, and with `false` whenever the
+/// connection drops.
+pub(crate) fn set_subscribed(exchange: &'static str, subscribed: bool) {
+    FEEDS.lock().unwrap().entry(exchange).or_default().subscribed = subscribed;
+}
+
+pub(crate) fn data_received(exchange: &'static str) {
+    FEEDS.lock().unwrap().entry(exchange).or_default().last_data = Some(Instant::now());
+}
+
+/// Whether every feed is subscribed and has had market data within
+/// `ready_within`, plus one status line per feed. Not ready before any feed
+/// has started.
+pub(crate) fn readiness(ready_within: Duration) -> (bool, String) {
+    let feeds = FEEDS.lock().unwrap();
+    let mut ready = !feeds.is_empty();
+    let mut report = String::new();
+    for (exchange, health) in feeds.iter() {
+        let status = if !health.subscribed {
+            "not subscribed".to_string()
+        } else {
+            match health.last_data.map(|at| at.elapsed()) {
+                None => "no data yet".to_string(),
+                Some(age) if age > ready_within => format!("no data for {}s", age.as_secs()),
+                Some(_) => "ok".to_string(),
+            }
+        };
+        ready &= status == "ok";
+        let _ = writeln!(report, "{} {}", exchange, status);
+    }
+    (ready, report)
+}
@@ -0,0 +1,72 @@
+use crate::{health, metrics};
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use std::convert::Infallible;
+use std::env;
+use std::net::SocketAddr;
+use std::time::Duration;
+use tracing::{error, info};
+
+const DEFAULT_HTTP_PORT: u16 = 9000;
+const DEFAULT_READY_WITHIN: Duration = Duration::from_secs(60);
+
+/// Where the HTTP endpoints are served and what `/readyz` demands.
+#[derive(Clone, Copy, Debug)]
+pub struct HttpOptions {
+    pub port: u16,
+    /// How recently every feed must have delivered market data to be ready.
+    pub ready_within: Duration,
+}
+
+impl HttpOptions {
+    /// `$HTTP_PORT` (9000 by default) and `$READY_WITHIN_SECS` (60 by
+    /// default) for the standalone binaries.
+    pub fn from_env() -> Self {
+        HttpOptions {
+            port: env::var("HTTP_PORT").ok().and_then(|port| port.parse().ok()).unwrap_or(DEFAULT_HTTP_PORT),
+            ready_within: env::var("READY_WITHIN_SECS")
+                .ok()
+                .and_then(|secs| secs.parse().ok())
+                .map_or(DEFAULT_READY_WITHIN, Duration::from_secs),
+        }
+    }
+}
+
+async fn handle(request: Request<Body>, options: HttpOptions) -> Result<Response<Body>, Infallible> {
+    let response = match (request.method(), request.uri().path()) {
+        (&Method::GET, "/metrics") => Response::builder()
+            .header("Content-Type", "text/plain; version=0.0.4")
+            .body(Body::from(metrics::render())),
+        (&Method::GET, "/healthz") => Response::builder().body(Body::from("ok\n")),
+        (&Method::GET, "/readyz") => {
+            let (ready, report) = health::readiness(options.ready_within);
+            let status = if ready { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+            Response::builder().status(status).body(Body::from(report))
+        }
+        _ => Response::builder().status(StatusCode::NOT_FOUND).body(Body::empty()),
+    };
+    Ok(response.expect("static response parts are valid"))
+}
+
+/// Serves `/metrics`, `/healthz` and `/readyz` in the background for as long
+/// as the process runs. `/healthz` answers as long as the process is up;
+/// `/readyz` only once every feed is subscribed and receiving market data.
+pub fn spawn_http_server(options: HttpOptions) {
+    let address = SocketAddr::from(([0, 0, 0, 0], options.port));
+    tokio::spawn(async move {
+        let service = make_service_fn(move |_| async move {
+            Ok::<_, Infallible>(service_fn(move |request| handle(request, options)))
+        });
+        let server = match Server::try_bind(&address) {
+            Ok(server) => server,
+            Err(e) => {
+                error!(event = "http_failed", %address, error = %e, "Can't bind the HTTP server");
+                return;
+            }
+        };
+        info!(event = "http_listening", %address, "Serving metrics and health checks");
+        if let Err(e) = server.serve(service).await {
+            error!(event = "http_failed", %address, error = %e, "HTTP server failed");
+        }
+    });
+}
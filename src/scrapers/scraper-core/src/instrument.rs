@@ -0,0 +1,100 @@
+use std::collections::HashMap;
+
+/// Quote currencies we recognise at the end of unseparated symbols such as
+/// `BTCUSDT`. Longer codes are tried first so `USDT` wins over `USD`.
+const QUOTE_CURRENCIES: &[&str] = &[
+    "FDUSD", "USDT", "USDC", "TUSD", "BUSD", "USD", "EUR", "GBP", "JPY", "TRY", "DAI", "BTC", "ETH",
+];
+
+/// Venue-specific asset codes and what everyone else calls them.
+const ASSET_ALIASES: &[(&str, &str)] = &[("XBT", "BTC"), ("XDG", "DOGE")];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum InstrumentType {
+    Spot,
+    Perpetual,
+}
+
+impl InstrumentType {
+    /// Value of the `TYPE` label.
+    pub fn label(&self) -> &'static str {
+        match self {
+            InstrumentType::Spot => "SPOT",
+            InstrumentType::Perpetual => "PERP",
+        }
+    }
+}
+
+/// Exchange independent identity of a market, e.g. BTC quoted in USDT, spot.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Instrument {
+    pub base: String,
+    pub quote: String,
+    pub kind: InstrumentType,
+}
+
+fn canonical_asset(code: &str) -> String {
+    ASSET_ALIASES
+        .iter()
+        .find(|(alias, _)| *alias == code)
+        .map(|(_, asset)| asset.to_string())
+        .unwrap_or(code.to_string())
+}
+
+impl Instrument {
+    pub fn new(base: &str, quote: &str, kind: InstrumentType) -> Self {
+        Instrument {
+            base: canonical_asset(&base.to_uppercase()),
+            quote: canonical_asset(&quote.to_uppercase()),
+            kind,
+        }
+    }
+
+    /// Best effort split of a venue symbol into base and quote. Handles
+    /// separated (`BTC-USDT`, `BTC_USDT`, `BTC/USD`) and concatenated
+    /// (`BTCUSDT`, `btcusdt`, `XBTUSD`) forms; anything after a second
+    /// separator (`BTC-USDT-SWAP`) is dropped.
+    pub fn parse(venue_symbol: &str, kind: InstrumentType) -> Option<Self> {
+        let upper = venue_symbol.to_uppercase();
+        let parts: Vec<&str> = upper.split(['-', '_', '/', ':']).filter(|part| !part.is_empty()).collect();
+        match parts.as_slice() {
+            [base, quote, ..] => Some(Instrument::new(base, quote, kind)),
+            [concatenated] => QUOTE_CURRENCIES
+                .iter()
+                .find(|quote| concatenated.len() > quote.len() && concatenated.ends_with(*quote))
+                .map(|quote| Instrument::new(&concatenated[..concatenated.len() - quote.len()], quote, kind)),
+            [] => None,
+        }
+    }
+
+    /// Identifier used in keys and the `SYMBOL` label: `BTC-USDT` for spot,
+    /// `BTC-USD-PERP` for perpetual swaps.
+    pub fn symbol(&self) -> String {
+        match self.kind {
+            InstrumentType::Spot => format!("{}-{}", self.base, self.quote),
+            InstrumentType::Perpetual => format!("{}-{}-PERP", self.base, self.quote),
+        }
+    }
+}
+
+/// Maps a feed's venue-native symbols to canonical instruments. Each symbol is
+/// resolved once; symbols that can't be mapped are remembered as such so they
+/// are only reported the first time.
+#[derive(Default)]
+pub struct InstrumentRegistry {
+    instruments: HashMap<String, Option<Instrument>>,
+}
+
+impl InstrumentRegistry {
+    /// Resolves `venue_symbol`, using `resolve` the first time it is seen.
+    /// Returns `None` for symbols that could not be mapped.
+    pub fn get_or_resolve<F>(&mut self, venue_symbol: &str, resolve: F) -> Option<&Instrument>
+    where
+        F: FnOnce(&str) -> Option<Instrument>,
+    {
+        self.instruments
+            .entry(venue_symbol.to_string())
+            .or_insert_with(|| resolve(venue_symbol))
+            .as_ref()
+    }
+}
@@ -0,0 +1,43 @@
+//! Plumbing shared by every quote scraper: the Redis sink, the WebSocket
+//! read/ping/reconnect loop and the helpers each `main.rs` used to carry.
+//! An exchange crate only implements [`QuoteSource`] for its protocol and
+//! hands it to [`run`].
+
+mod book;
+mod error;
+mod health;
+mod http;
+mod instrument;
+mod logging;
+mod metrics;
+mod perp;
+mod quote;
+mod recorder;
+mod replay;
+mod runner;
+mod shutdown;
+mod sink;
+mod source;
+mod spool;
+mod supervisor;
+mod trade;
+mod utils;
+
+pub use book::{BookDepth, BookLevel, DepthBand, OrderBook, DEPTH_BANDS_BPS};
+pub use error::{Cause, FeedError, Policy};
+pub use http::{spawn_http_server, HttpOptions};
+pub use instrument::{Instrument, InstrumentRegistry, InstrumentType};
+pub use logging::init_logging;
+pub use perp::PerpStats;
+pub use quote::{Quote, TickerStats};
+pub use recorder::{read_recording, Recorded, RecordedEvent};
+pub use replay::{replay, ReplaySpeed, ReplayStats};
+pub use runner::{run, RunOptions};
+pub use shutdown::{Shutdown, ShutdownTrigger};
+pub use sink::RedisSink;
+pub use source::{Frame, QuoteSource};
+pub use trade::{Side, Trade};
+pub use utils::{
+    book_levels_from_env, de_float_from_str, de_millis_from_str, de_opt_float_from_str, get_current_timestamp, rfc3339_millis,
+    swaps_from_env, symbols_from_env,
+};
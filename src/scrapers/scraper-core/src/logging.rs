@@ -0,0 +1,18 @@
+use tracing_subscriber::EnvFilter;
+
+const DEFAULT_LOG_LEVEL: &str = "info";
+
+/// Installs the global JSON logger, one object per line on stdout. Verbosity
+/// comes from `$LOG_LEVEL`, which takes `tracing` filter directives such as
+/// `debug` or `info,scraper_core=trace`; `info` by default. Ping/pong traffic
+/// is only logged at `trace`.
+pub fn init_logging() {
+    let filter = EnvFilter::try_from_env("LOG_LEVEL").unwrap_or_else(|_| EnvFilter::new(DEFAULT_LOG_LEVEL));
+    tracing_subscriber::fmt()
+        .json()
+        .flatten_event(true)
+        .with_current_span(false)
+        .with_span_list(false)
+        .with_env_filter(filter)
+        .init();
+}
@@ -0,0 +1,132 @@
+use prometheus::{
+    exponential_buckets, register_gauge_vec, register_histogram_vec, register_int_counter_vec, Encoder, GaugeVec,
+    HistogramVec, IntCounterVec, TextEncoder,
+};
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+use std::time::Instant;
+
+macro_rules! counter {
+    ($name:expr, $help:expr, $labels:expr) => {
+        LazyLock::new(|| register_int_counter_vec!($name, $help, $labels).expect("metric registers once"))
+    };
+}
+
+macro_rules! gauge {
+    ($name:expr, $help:expr, $labels:expr) => {
+        LazyLock::new(|| register_gauge_vec!($name, $help, $labels).expect("metric registers once"))
+    };
+}
+
+pub static MESSAGES_RECEIVED: LazyLock<IntCounterVec> =
+    counter!("scraper_messages_received_total", "Frames read off the socket", &["exchange"]);
+
+pub static PARSE_FAILURES: LazyLock<IntCounterVec> =
+    counter!("scraper_parse_failures_total", "Frames that failed to decode or parse", &["exchange"]);
+
+/// `kind` is the [`crate::FeedError`] stage: `connect`, `subscribe`, `decode`,
+/// `validation` or `sink`.
+pub static FEED_ERRORS: LazyLock<IntCounterVec> =
+    counter!("scraper_feed_errors_total", "Feed errors by stage", &["exchange", "kind"]);
+
+pub static REDIS_WRITE_ERRORS: LazyLock<IntCounterVec> =
+    counter!("scraper_redis_write_errors_total", "Redis writes that returned an error", &["exchange"]);
+
+pub static RECONNECTS: LazyLock<IntCounterVec> =
+    counter!("scraper_reconnects_total", "Times the feed dropped its socket and reconnected", &["exchange"]);
+
+/// `direction` is `sent` or `received`.
+pub static PINGS: LazyLock<IntCounterVec> =
+    counter!("scraper_pings_total", "WebSocket pings sent and received", &["exchange", "direction"]);
+
+/// `direction` is `sent` or `received`.
+pub static PONGS: LazyLock<IntCounterVec> =
+    counter!("scraper_pongs_total", "WebSocket pongs sent and received", &["exchange", "direction"]);
+
+pub static BOOK_RESYNCS: LazyLock<IntCounterVec> =
+    counter!("scraper_book_resyncs_total", "Local order books rebuilt after a sequence gap or checksum mismatch", &["exchange"]);
+
+pub static RECORDER_DROPPED: LazyLock<IntCounterVec> =
+    counter!("scraper_recorder_dropped_total", "Frames the raw frame recorder failed to write", &["exchange"]);
+
+static LAST_MESSAGE_AGE: LazyLock<GaugeVec> =
+    gauge!("scraper_last_message_age_seconds", "Seconds since the feed last read a frame", &["exchange"]);
+
+pub static BID: LazyLock<GaugeVec> = gauge!("scraper_bid", "Latest best bid", &["exchange", "symbol"]);
+
+pub static ASK: LazyLock<GaugeVec> = gauge!("scraper_ask", "Latest best ask", &["exchange", "symbol"]);
+
+/// When each exchange last read a frame. Turned into ages at scrape time so
+/// a feed that is stuck reconnecting still shows its age growing.
+static LAST_MESSAGE_AT: LazyLock<Mutex<HashMap<&'static str, Instant>>> = LazyLock::new(Default::default);
+
+pub fn message_received(exchange: &'static str) {
+    MESSAGES_RECEIVED.with_label_values(&[exchange]).inc();
+    LAST_MESSAGE_AT.lock().unwrap().insert(exchange, Instant::now());
+}
+
+/// Everything registered, in the Prometheus text format.
+pub fn render() -> Vec<u8> {
+    for (exchange, at) in LAST_MESSAGE_AT.lock().unwrap().iter() {
+        LAST_MESSAGE_AGE.with_label_values(&[exchange]).set(at.elapsed().as_secs_f64());
+    }
+    let mut buffer = Vec::new();
+    TextEncoder::new()
+        .encode(&prometheus::gather(), &mut buffer)
+        .expect("text encoding into a Vec can't fail");
+    buffer
+}
+
+/// 1ms doubling up to ~16s.
+fn latency_buckets() -> Vec<f64> {
+    exponential_buckets(1.0, 2.0, 15).expect("valid bucket layout")
+}
+
+/// Venue event time to local receive time, per exchange. Only observed for
+/// venues that send an event time.
+pub static EXCHANGE_TO_RECEIVE: LazyLock<HistogramVec> = LazyLock::new(|| {
+    register_histogram_vec!(
+        "scraper_exchange_to_receive_latency_milliseconds",
+        "Time from the venue's event timestamp to the frame being read off the socket",
+        &["exchange"],
+        latency_buckets()
+    )
+    .expect("metric registers once")
+});
+
+/// Local receive time to the batch holding the quote being acknowledged by Redis.
+pub static RECEIVE_TO_COMMIT: LazyLock<HistogramVec> = LazyLock::new(|| {
+    register_histogram_vec!(
+        "scraper_receive_to_commit_latency_milliseconds",
+        "Time from the frame being read off the socket to its samples being committed to Redis",
+        &["exchange"],
+        latency_buckets()
+    )
+    .expect("metric registers once")
+});
+
+/// Latency samples since the last report, for exact quantiles over a short
+/// window. The histograms above only ever accumulate.
+#[derive(Default)]
+pub(crate) struct LatencyWindow {
+    samples: Vec<f64>,
+}
+
+impl LatencyWindow {
+    pub fn observe(&mut self, latency_ms: f64) {
+        self.samples.push(latency_ms);
+    }
+
+    /// p50 and p99 of the window, then starts a new one. `None` if nothing was
+    /// observed.
+    pub fn take_quantiles(&mut self) -> Option<(f64, f64)> {
+        if self.samples.is_empty() {
+            return None;
+        }
+        self.samples.sort_by(f64::total_cmp);
+        let quantile = |q: f64| self.samples[((self.samples.len() - 1) as f64 * q).round() as usize];
+        let quantiles = (quantile(0.5), quantile(0.99));
+        self.samples.clear();
+        Some(quantiles)
+    }
+}
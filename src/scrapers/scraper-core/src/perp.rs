@@ -0,0 +1,107 @@
+use crate::error::FeedError;
+
+/// Funding and reference prices of a perpetual swap. Venues spread these over
+/// several channels and send deltas with only what changed, so every figure is
+/// optional and only those present are written.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PerpStats {
+    /// Instrument as the venue names it, as in [`crate::Quote::symbol`].
+    pub symbol: String,
+    /// Rate paid from longs to shorts at the next funding, e.g. `0.0001` for
+    /// 0.01%.
+    pub funding_rate: Option<f64>,
+    /// Rate settled at the funding just paid, for venues that publish
+    /// settlements apart from the rate for the next one.
+    pub funding_rate_paid: Option<f64>,
+    /// The venue's fair price, which margin and liquidations are marked to.
+    pub mark_price: Option<f64>,
+    /// The spot index the swap tracks.
+    pub index_price: Option<f64>,
+    /// Open contracts, in the venue's
+    /// [`QuoteSource::open_interest_unit`](crate::QuoteSource::open_interest_unit).
+    pub open_interest: Option<f64>,
+    /// When the venue says the figures were computed, in epoch millis.
+    pub exchange_ts: Option<u64>,
+}
+
+impl PerpStats {
+    /// At least one figure must be present. Prices must be positive and open
+    /// interest non-negative; a funding rate may be negative but not NaN.
+    pub fn validate(&self) -> Result<(), FeedError> {
+        if self.is_empty() {
+            return Err(FeedError::validation(format!("no perpetual figures for {}", self.symbol)));
+        }
+        let rates = [("funding_rate", self.funding_rate), ("funding_rate_paid", self.funding_rate_paid)];
+        if let Some((field, Some(rate))) = rates.iter().find(|(_, rate)| rate.is_some_and(|rate| !rate.is_finite())) {
+            return Err(FeedError::validation(format!("{} {} for {} is not a valid rate", field, rate, self.symbol)));
+        }
+        let prices = [("mark_price", self.mark_price), ("index_price", self.index_price)];
+        if let Some((field, Some(value))) = prices.iter().find(|(_, value)| value.is_some_and(|value| !(value.is_finite() && value > 0.0))) {
+            return Err(FeedError::validation(format!("{} {} for {} is not a positive price", field, value, self.symbol)));
+        }
+        if let Some(open) = self.open_interest.filter(|open| !(open.is_finite() && *open >= 0.0)) {
+            return Err(FeedError::validation(format!("open_interest {} for {} is not a valid size", open, self.symbol)));
+        }
+        Ok(())
+    }
+
+    /// Whether the update carried none of the figures, e.g. a delta that only
+    /// moved a venue's 24h stats.
+    pub fn is_empty(&self) -> bool {
+        self.funding_rate.is_none()
+            && self.funding_rate_paid.is_none()
+            && self.mark_price.is_none()
+            && self.index_price.is_none()
+            && self.open_interest.is_none()
+    }
+}
+
+/// The latest mark and index price of one perpetual. Venues often send them
+/// apart, so the basis is worked out from the last of each.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct PerpPrices {
+    mark: Option<f64>,
+    index: Option<f64>,
+}
+
+impl PerpPrices {
+    /// Takes in whichever prices `perp` carries and returns the mark's premium
+    /// over the index, as a fraction of the index like the funding rate.
+    /// `None` if `perp` moved neither, or until both have been seen.
+    pub(crate) fn update(&mut self, perp: &PerpStats) -> Option<f64> {
+        if perp.mark_price.is_none() && perp.index_price.is_none() {
+            return None;
+        }
+        self.mark = perp.mark_price.or(self.mark);
+        self.index = perp.index_price.or(self.index);
+        Some((self.mark? - self.index?) / self.index?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn perp(mark_price: Option<f64>, index_price: Option<f64>) -> PerpStats {
+        PerpStats { symbol: "BTCUSDT".to_string(), mark_price, index_price, ..PerpStats::default() }
+    }
+
+    #[test]
+    fn basis_comes_from_the_latest_mark_and_index() {
+        let mut prices = PerpPrices::default();
+        assert_eq!(prices.update(&perp(Some(43043.0), None)), None);
+        let basis = prices.update(&perp(None, Some(43000.0))).unwrap();
+        assert!((basis - 0.001).abs() < 1e-12);
+        // The index stays at 43000 for a later mark
+        let basis = prices.update(&perp(Some(42957.0), None)).unwrap();
+        assert!((basis + 0.001).abs() < 1e-12);
+    }
+
+    #[test]
+    fn no_basis_for_updates_without_prices() {
+        let mut prices = PerpPrices::default();
+        prices.update(&perp(Some(43043.0), Some(43000.0)));
+        let funding = PerpStats { funding_rate: Some(0.0001), ..perp(None, None) };
+        assert_eq!(prices.update(&funding), None);
+    }
+}
@@ -0,0 +1,80 @@
+use crate::error::FeedError;
+
+/// Top of book as every scraper writes it: best bid and ask with their sizes.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Quote {
+    /// Instrument as the venue names it, e.g. `BTCUSDT`, `BTC-USDT`, `tBTCUSD`.
+    pub symbol: String,
+    pub bid: f64,
+    pub bid_vol: f64,
+    pub ask: f64,
+    pub ask_vol: f64,
+    /// When the venue says the update happened, in epoch millis. `None` for
+    /// venues whose messages carry no usable time.
+    pub exchange_ts: Option<u64>,
+}
+
+impl Quote {
+    /// Checked before anything is written: prices must be positive and sizes
+    /// non-negative, all of them finite. A zero or NaN that slipped through a
+    /// parser would otherwise land in the series as a real price.
+    pub fn validate(&self) -> Result<(), FeedError> {
+        let prices = [("bid", self.bid), ("ask", self.ask)];
+        if let Some((field, value)) = prices.iter().find(|(_, value)| !(value.is_finite() && *value > 0.0)) {
+            return Err(FeedError::validation(format!("{} {} for {} is not a positive price", field, value, self.symbol)));
+        }
+        let sizes = [("bid_vol", self.bid_vol), ("ask_vol", self.ask_vol)];
+        if let Some((field, value)) = sizes.iter().find(|(_, value)| !(value.is_finite() && *value >= 0.0)) {
+            return Err(FeedError::validation(format!("{} {} for {} is not a valid size", field, value, self.symbol)));
+        }
+        Ok(())
+    }
+
+    /// `(bid_vol - ask_vol) / (bid_vol + ask_vol)`: 1 with only bids resting
+    /// at the top, -1 with only asks. `None` when both sizes are zero.
+    pub fn imbalance(&self) -> Option<f64> {
+        imbalance(self.bid_vol, self.ask_vol)
+    }
+
+    /// The mid weighted by the size on the other side,
+    /// `(bid * ask_vol + ask * bid_vol) / (bid_vol + ask_vol)`, so it leans
+    /// toward the side more likely to be taken out. `None` when both sizes are zero.
+    pub fn microprice(&self) -> Option<f64> {
+        let total = self.bid_vol + self.ask_vol;
+        (total > 0.0).then(|| (self.bid * self.ask_vol + self.ask * self.bid_vol) / total)
+    }
+}
+
+/// `(bid - ask) / (bid + ask)` of two non-negative sizes, `None` when both are zero.
+pub(crate) fn imbalance(bid: f64, ask: f64) -> Option<f64> {
+    let total = bid + ask;
+    (total > 0.0).then(|| (bid - ask) / total)
+}
+
+/// Rolling 24h figures from a venue's ticker. Kept apart from [`Quote`]: a
+/// last trade price or a day's volume is no stand-in for the book.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TickerStats {
+    /// Instrument as the venue names it, as in [`Quote::symbol`].
+    pub symbol: String,
+    /// Price of the last trade.
+    pub last: f64,
+    /// Traded over the last 24h, in the base currency.
+    pub volume: f64,
+    /// When the venue says the figures were computed, in epoch millis.
+    pub exchange_ts: Option<u64>,
+}
+
+impl TickerStats {
+    /// The same checks as [`Quote::validate`]: a positive last price and a
+    /// non-negative volume, both finite.
+    pub fn validate(&self) -> Result<(), FeedError> {
+        if !(self.last.is_finite() && self.last > 0.0) {
+            return Err(FeedError::validation(format!("last {} for {} is not a positive price", self.last, self.symbol)));
+        }
+        if !(self.volume.is_finite() && self.volume >= 0.0) {
+            return Err(FeedError::validation(format!("volume {} for {} is not a valid size", self.volume, self.symbol)));
+        }
+        Ok(())
+    }
+}
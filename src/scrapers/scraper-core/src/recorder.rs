@@ -0,0 +1,176 @@
+use crate::metrics::RECORDER_DROPPED;
+use crate::utils::get_current_timestamp;
+use chrono::Utc;
+use flate2::read::MultiGzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use std::fmt::Write as _;
+use std::fs::{self, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError, SyncSender, TrySendError};
+use std::thread;
+use std::time::Duration;
+use tracing::{error, info};
+
+/// Frames queued for the recorder thread before new ones are dropped.
+const QUEUE_CAPACITY: usize = 100_000;
+/// Start a new file once this much uncompressed JSON has gone into the current one.
+const MAX_FILE_BYTES: u64 = 256 << 20;
+/// Flush the gzip stream this often, so a crash loses at most this much.
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// One line of a recording.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Recorded {
+    /// Local epoch millis the frame was read off the socket.
+    pub received_at: u64,
+    /// Which socket the frame came from. Numbered per process, so every
+    /// reconnect shows up as a new id.
+    pub connection: u64,
+    #[serde(flatten)]
+    pub event: RecordedEvent,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum RecordedEvent {
+    /// The socket opened and its subscriptions were sent.
+    Connected { endpoint: String },
+    Text { frame: String },
+    /// A binary frame, after [`crate::QuoteSource::decode_binary`].
+    Binary { frame: String },
+    /// A binary frame that failed to decode, as hex.
+    Undecodable { hex: String },
+}
+
+impl RecordedEvent {
+    pub fn undecodable(data: &[u8]) -> Self {
+        let mut hex = String::with_capacity(data.len() * 2);
+        for byte in data {
+            let _ = write!(hex, "{:02x}", byte);
+        }
+        RecordedEvent::Undecodable { hex }
+    }
+}
+
+/// Reads back a recording written by [`Recorder`], one [`Recorded`] per
+/// line. A file still being written, or cut short by a crash, yields an
+/// error after its last complete flush.
+pub fn read_recording(path: &Path) -> io::Result<impl Iterator<Item = io::Result<Recorded>>> {
+    let file = fs::File::open(path)?;
+    let lines = BufReader::new(MultiGzDecoder::new(file)).lines();
+    Ok(lines.map(|line| Ok(serde_json::from_str(&line?)?)))
+}
+
+/// Writes every inbound frame of one feed as JSON lines to gzipped files
+/// under `{dir}/{EXCHANGE}/`, starting a new file every UTC hour and whenever
+/// one grows past [`MAX_FILE_BYTES`]. Compression and disk writes happen on
+/// a thread of their own; if that falls behind, frames are dropped and
+/// counted rather than holding up the socket.
+pub(crate) struct Recorder {
+    exchange: &'static str,
+    queue: SyncSender<Recorded>,
+    thread: thread::JoinHandle<()>,
+}
+
+impl Recorder {
+    pub fn start(dir: &Path, exchange: &'static str) -> Self {
+        let (queue, frames) = mpsc::sync_channel(QUEUE_CAPACITY);
+        let dir = dir.join(exchange);
+        info!(event = "recorder_started", exchange, dir = %dir.display(), "Recording raw frames");
+        let thread = thread::spawn(move || {
+            let mut file: Option<RecordingFile> = None;
+            loop {
+                let recorded = match frames.recv_timeout(FLUSH_INTERVAL) {
+                    Ok(recorded) => recorded,
+                    Err(RecvTimeoutError::Timeout) => {
+                        if let Some(file) = &mut file {
+                            if let Err(e) = file.writer.flush() {
+                                error!(event = "recorder_failed", exchange, error = %e, "Failed to flush recording");
+                            }
+                        }
+                        continue;
+                    }
+                    Err(RecvTimeoutError::Disconnected) => break,
+                };
+                if let Err(e) = write_recorded(&dir, exchange, &mut file, &recorded) {
+                    RECORDER_DROPPED.with_label_values(&[exchange]).inc();
+                    error!(event = "recorder_failed", exchange, error = %e, "Failed to record frame");
+                    // Start over with a fresh file rather than keep writing to a broken one
+                    file = None;
+                }
+            }
+            if let Some(file) = file {
+                file.finish(exchange);
+            }
+        });
+        Recorder { exchange, queue, thread }
+    }
+
+    pub fn record(&self, connection: u64, event: RecordedEvent) {
+        let recorded = Recorded { received_at: get_current_timestamp(), connection, event };
+        match self.queue.try_send(recorded) {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) | Err(TrySendError::Disconnected(_)) => {
+                RECORDER_DROPPED.with_label_values(&[self.exchange]).inc();
+            }
+        }
+    }
+
+    /// Writes out whatever is still queued and closes the current file.
+    pub async fn finish(self) {
+        let Recorder { queue, thread, .. } = self;
+        drop(queue);
+        let _ = tokio::task::spawn_blocking(move || thread.join()).await;
+    }
+}
+
+struct RecordingFile {
+    path: PathBuf,
+    hour: i64,
+    bytes: u64,
+    writer: GzEncoder<BufWriter<fs::File>>,
+}
+
+impl RecordingFile {
+    fn create(dir: &Path, exchange: &str) -> io::Result<Self> {
+        fs::create_dir_all(dir)?;
+        let now = Utc::now();
+        let path = dir.join(format!("{}-{}.jsonl.gz", exchange, now.format("%Y%m%dT%H%M%S%.3fZ")));
+        let file = OpenOptions::new().create_new(true).write(true).open(&path)?;
+        Ok(RecordingFile {
+            path,
+            hour: now.timestamp() / 3600,
+            bytes: 0,
+            writer: GzEncoder::new(BufWriter::new(file), Compression::default()),
+        })
+    }
+
+    fn finish(self, exchange: &str) {
+        let finished = self.writer.finish().and_then(|mut file| file.flush());
+        if let Err(e) = finished {
+            error!(event = "recorder_failed", exchange, path = %self.path.display(), error = %e, "Failed to close recording");
+        }
+    }
+}
+
+fn write_recorded(dir: &Path, exchange: &str, file: &mut Option<RecordingFile>, recorded: &Recorded) -> io::Result<()> {
+    let rotate = match file {
+        Some(current) => current.bytes >= MAX_FILE_BYTES || current.hour != Utc::now().timestamp() / 3600,
+        None => true,
+    };
+    if rotate {
+        if let Some(previous) = file.take() {
+            previous.finish(exchange);
+        }
+        *file = Some(RecordingFile::create(dir, exchange)?);
+    }
+    let current = file.as_mut().expect("opened above");
+    let mut line = serde_json::to_vec(recorded)?;
+    line.push(b'\n');
+    current.writer.write_all(&line)?;
+    current.bytes += line.len() as u64;
+    Ok(())
+}
@@ -0,0 +1,180 @@
+use crate::instrument::InstrumentRegistry;
+use crate::recorder::{Recorded, RecordedEvent};
+use crate::runner::{tick, MarketData};
+use crate::shutdown::Shutdown;
+use crate::sink::{FeedKind, RedisSink, SinkMessage};
+use crate::error::FeedError;
+use crate::source::{Frame, QuoteSource};
+use std::str::FromStr;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::time::Instant;
+use tracing::warn;
+
+/// How fast [`replay`] plays a recording back.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ReplaySpeed {
+    /// Keeps the recorded gaps between frames, divided by the factor; 1.0 is
+    /// real time.
+    Scaled(f64),
+    /// No waiting at all.
+    Max,
+}
+
+impl FromStr for ReplaySpeed {
+    type Err = String;
+
+    /// `max`, or a factor such as `1`, `10x` or `0.5x`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("max") {
+            return Ok(ReplaySpeed::Max);
+        }
+        let factor: f64 = s
+            .trim_end_matches(['x', 'X'])
+            .parse()
+            .map_err(|_| format!("invalid speed {:?}, expected e.g. 1x, 10x or max", s))?;
+        if !(factor.is_finite() && factor > 0.0) {
+            return Err(format!("invalid speed {:?}, the factor must be positive", s));
+        }
+        Ok(ReplaySpeed::Scaled(factor))
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ReplayStats {
+    /// Connections opened in the recording.
+    pub connections: u64,
+    /// Text and binary frames played back.
+    pub frames: u64,
+    /// Quotes handed to Redis.
+    pub quotes: u64,
+    /// Sets of 24h ticker stats handed to Redis.
+    pub tickers: u64,
+    /// Trades handed to Redis.
+    pub trades: u64,
+    /// Order book updates handed to Redis.
+    pub books: u64,
+    /// Perpetual swap updates handed to Redis.
+    pub perps: u64,
+    /// Order books rebuilt after a gap or checksum mismatch. Nothing is sent;
+    /// the fresh snapshot the live feed asked for is further on in the recording.
+    pub resyncs: u64,
+    /// Frames that failed to decode or parse, or that the venue sent as an
+    /// error.
+    pub invalid: u64,
+    /// Quotes, ticker stats, trades, books and perpetual figures dropped for
+    /// prices or sizes that can't be stored.
+    pub rejected: u64,
+}
+
+/// Plays recorded frames back through `source`'s parser and into `sink`,
+/// exactly as [`crate::run`] would have stored them live: samples keep their
+/// exchange timestamp where the venue sent one and the recorded receive time
+/// otherwise. Every recorded connection resets the source through
+/// [`QuoteSource::subscribe_messages`], as a reconnect would.
+///
+/// Stops early, after storing what it has already parsed, once `shutdown`
+/// trips.
+pub async fn replay<S, I>(
+    source: &mut S,
+    sink: RedisSink,
+    recording: I,
+    speed: ReplaySpeed,
+    mut shutdown: Shutdown,
+) -> Result<ReplayStats, FeedError>
+where
+    S: QuoteSource,
+    I: IntoIterator<Item = Recorded>,
+{
+    let exchange = source.exchange();
+    let mut instruments = InstrumentRegistry::default();
+    let (writes, writer) = sink.spawn_writer(exchange, source.open_interest_unit(), Vec::new(), FeedKind::Replay);
+    let mut stats = ReplayStats::default();
+    // First recorded receive time and when it was played back
+    let mut clock: Option<(u64, Instant)> = None;
+
+    for recorded in recording {
+        if shutdown.is_requested() {
+            break;
+        }
+        if let ReplaySpeed::Scaled(factor) = speed {
+            let (first, started) = *clock.get_or_insert((recorded.received_at, Instant::now()));
+            let offset = recorded.received_at.saturating_sub(first) as f64 / factor;
+            let due = started + Duration::from_secs_f64(offset / 1000.0);
+            tokio::select! {
+                _ = tokio::time::sleep_until(due) => {}
+                _ = shutdown.requested() => break,
+            }
+        }
+
+        let text = match recorded.event {
+            RecordedEvent::Connected { .. } => {
+                stats.connections += 1;
+                source.subscribe_messages();
+                continue;
+            }
+            RecordedEvent::Text { frame } | RecordedEvent::Binary { frame } => frame,
+            RecordedEvent::Undecodable { .. } => {
+                stats.frames += 1;
+                stats.invalid += 1;
+                continue;
+            }
+        };
+        stats.frames += 1;
+        match source.parse(&text) {
+            Frame::Quotes(quotes) => {
+                stats.quotes += store(source, &mut instruments, &writes, quotes, &text, recorded.received_at, &mut stats.rejected).await?;
+            }
+            Frame::Stats(tickers) => {
+                stats.tickers += store(source, &mut instruments, &writes, tickers, &text, recorded.received_at, &mut stats.rejected).await?;
+            }
+            Frame::Trades(trades) => {
+                stats.trades += store(source, &mut instruments, &writes, trades, &text, recorded.received_at, &mut stats.rejected).await?;
+            }
+            Frame::Books(books) => {
+                stats.books += store(source, &mut instruments, &writes, books, &text, recorded.received_at, &mut stats.rejected).await?;
+            }
+            Frame::Perps(perps) => {
+                stats.perps += store(source, &mut instruments, &writes, perps, &text, recorded.received_at, &mut stats.rejected).await?;
+            }
+            Frame::Resync { .. } => stats.resyncs += 1,
+            // Nothing to reconnect to or abort; every error is just a bad frame here
+            Frame::Error(e) => {
+                stats.invalid += 1;
+                warn!(event = "parse_failed", exchange, error = %e, frame = %text, "Parsing failed");
+            }
+            Frame::Reply(_) | Frame::Event(_) | Frame::Ignored => {}
+        }
+    }
+
+    drop(writes);
+    writer.await.map_err(|e| FeedError::sink(format!("Redis writer failed: {}", e)))?;
+    Ok(stats)
+}
+
+/// Checks `items` and hands them to the Redis writer, counting what fails the
+/// checks in `rejected`. Returns how many were stored.
+async fn store<S: QuoteSource, T: MarketData>(
+    source: &S,
+    instruments: &mut InstrumentRegistry,
+    writes: &mpsc::Sender<SinkMessage>,
+    items: Vec<T>,
+    frame: &str,
+    received_at: u64,
+    rejected: &mut u64,
+) -> Result<u64, FeedError> {
+    let exchange = source.exchange();
+    let mut stored = 0;
+    for item in items {
+        if let Err(e) = item.check() {
+            *rejected += 1;
+            warn!(event = "invalid_quote", exchange, error = %e, frame, "Dropping invalid market data");
+            continue;
+        }
+        let Some(tick) = tick(source, instruments, item, received_at) else { continue };
+        // Unlike a live feed, wait for Redis rather than drop anything
+        writes.send(T::message(tick)).await.map_err(|_| FeedError::sink("Redis writer has stopped"))?;
+        stored += 1;
+    }
+    Ok(stored)
+}
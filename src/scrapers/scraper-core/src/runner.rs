@@ -0,0 +1,688 @@
+use crate::book::BookDepth;
+use crate::error::{FeedError, Policy};
+use crate::instrument::{Instrument, InstrumentRegistry};
+use crate::perp::PerpStats;
+use crate::quote::{Quote, TickerStats};
+use crate::recorder::{Recorder, RecordedEvent};
+use crate::{health, metrics};
+use crate::shutdown::Shutdown;
+use crate::sink::{FeedKind, RedisSink, SinkMessage, Tick};
+use crate::source::{Frame, QuoteSource};
+use crate::supervisor::Supervisor;
+use crate::trade::Trade;
+use crate::utils::get_current_timestamp;
+use futures_util::stream::{SplitSink, SplitStream};
+use futures_util::{SinkExt, StreamExt};
+use std::env;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::net::TcpStream;
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+use tokio_tungstenite::tungstenite::protocol::CloseFrame;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{connect_async, MaybeTlsStream, WebSocketStream};
+use tracing::{debug, error, info, trace, warn};
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// How often the timer task checks for a due ping or a silent socket.
+const TIMER_TICK: Duration = Duration::from_millis(500);
+/// How long a graceful shutdown waits for the server to answer our close frame.
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(2);
+/// How long a graceful shutdown waits for queued quotes to reach Redis.
+const FLUSH_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Source of [`Connection::id`], shared by every feed in the process.
+static NEXT_CONNECTION_ID: AtomicU64 = AtomicU64::new(1);
+
+/// When the socket last produced a frame and when it last produced market
+/// data, as millis since the connection opened. Written by the read loop and
+/// watched by the timer task.
+struct Activity {
+    opened: Instant,
+    last_frame: AtomicU64,
+    last_data: AtomicU64,
+}
+
+impl Activity {
+    fn new() -> Self {
+        Activity { opened: Instant::now(), last_frame: AtomicU64::new(0), last_data: AtomicU64::new(0) }
+    }
+
+    fn now(&self) -> u64 {
+        self.opened.elapsed().as_millis() as u64
+    }
+
+    fn frame(&self) {
+        self.last_frame.store(self.now(), Ordering::Relaxed);
+    }
+
+    fn data(&self) {
+        self.last_data.store(self.now(), Ordering::Relaxed);
+    }
+
+    fn since(&self, mark: &AtomicU64) -> Duration {
+        Duration::from_millis(self.now().saturating_sub(mark.load(Ordering::Relaxed)))
+    }
+}
+
+/// An open socket. The runner keeps the read half; writes go through
+/// `outbound` to a writer task, and a timer task pings the socket when it goes
+/// quiet and reports on `dead` when it stops answering or stops delivering
+/// market data.
+struct Connection {
+    id: u64,
+    read: SplitStream<Socket>,
+    outbound: mpsc::UnboundedSender<Message>,
+    activity: Arc<Activity>,
+    dead: oneshot::Receiver<String>,
+    writer: JoinHandle<()>,
+    timer: JoinHandle<()>,
+}
+
+impl Connection {
+    fn send(&self, message: Message) -> Result<(), FeedError> {
+        self.outbound.send(message).map_err(|_| FeedError::connect("socket writer has stopped"))
+    }
+
+    /// Market data arrived: holds off the ping and staleness timers and keeps
+    /// the feed ready.
+    fn data_received(&self, exchange: &'static str) {
+        self.activity.data();
+        health::data_received(exchange);
+    }
+
+    /// Sends `goodbye` and a close frame, then waits for the server to close
+    /// its side.
+    async fn close(mut self, exchange: &'static str, goodbye: Vec<String>) {
+        // A ping or a dead-socket report mid-close would only get in the way
+        self.timer.abort();
+        for message in goodbye {
+            info!(event = "unsubscribe", exchange, frame = %message, "Sending unsubscribe");
+            let _ = self.send(Message::Text(message));
+        }
+        let close = CloseFrame { code: CloseCode::Normal, reason: "shutting down".into() };
+        if self.send(Message::Close(Some(close))).is_err() {
+            return;
+        }
+        let answered = tokio::time::timeout(CLOSE_TIMEOUT, async {
+            while let Some(Ok(message)) = self.read.next().await {
+                if let Message::Close(_) = message {
+                    return;
+                }
+            }
+        })
+        .await;
+        if answered.is_err() {
+            warn!(event = "close_timeout", exchange, "Server didn't answer the close frame");
+        }
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        self.writer.abort();
+        self.timer.abort();
+    }
+}
+
+async fn write_frames(
+    exchange: &'static str,
+    mut write: SplitSink<Socket, Message>,
+    mut outbound: mpsc::UnboundedReceiver<Message>,
+) {
+    while let Some(message) = outbound.recv().await {
+        if let Err(e) = write.send(message).await {
+            warn!(event = "socket_write_failed", exchange, error = %e, "Failed to write to socket");
+            break;
+        }
+    }
+}
+
+struct Timers {
+    ping_interval: Duration,
+    read_timeout: Duration,
+    stale_after: Duration,
+}
+
+async fn heartbeat(
+    exchange: &'static str,
+    outbound: mpsc::UnboundedSender<Message>,
+    activity: Arc<Activity>,
+    dead: oneshot::Sender<String>,
+    timers: Timers,
+) {
+    let Timers { ping_interval, read_timeout, stale_after } = timers;
+    let mut ticker = tokio::time::interval(TIMER_TICK);
+    let mut last_ping = Instant::now();
+    loop {
+        ticker.tick().await;
+        if activity.since(&activity.last_frame) >= read_timeout {
+            let _ = dead.send(format!("nothing received for {:?}", read_timeout));
+            return;
+        }
+        // Heartbeats and pongs keep `last_frame` fresh on a feed whose subscription has silently gone away
+        if activity.since(&activity.last_data) >= stale_after {
+            let _ = dead.send(format!("no market data for {:?}, feed is stale", stale_after));
+            return;
+        }
+        if activity.since(&activity.last_data) >= ping_interval && last_ping.elapsed() >= ping_interval {
+            trace!(event = "ping_sent", exchange, "Sending Ping");
+            if outbound.send(Message::Ping("ping".as_bytes().to_vec())).is_err() {
+                return;
+            }
+            metrics::PINGS.with_label_values(&[exchange, "sent"]).inc();
+            last_ping = Instant::now();
+        }
+    }
+}
+
+async fn connect_and_subscribe<S: QuoteSource>(
+    source: &mut S,
+    options: &RunOptions,
+    recorder: Option<&Recorder>,
+) -> Result<Connection, FeedError> {
+    // Some venues hand out session tokens over blocking HTTP
+    let endpoint = tokio::task::block_in_place(|| source.endpoint())?;
+    let (mut socket, _) = connect_async(endpoint.as_str()).await.map_err(FeedError::connect)?;
+    info!(event = "connected", exchange = source.exchange(), %endpoint, "Connected");
+
+    for subscription_message in source.subscribe_messages() {
+        info!(event = "subscribe", exchange = source.exchange(), frame = %subscription_message, "Sending subscription");
+        socket.send(Message::Text(subscription_message)).await.map_err(FeedError::connect)?;
+    }
+
+    let id = NEXT_CONNECTION_ID.fetch_add(1, Ordering::Relaxed);
+    if let Some(recorder) = recorder {
+        recorder.record(id, RecordedEvent::Connected { endpoint });
+    }
+
+    let (write, read) = socket.split();
+    let (outbound, outbound_queue) = mpsc::unbounded_channel();
+    let activity = Arc::new(Activity::new());
+    let (report_dead, dead) = oneshot::channel();
+    let timers = Timers {
+        ping_interval: source.ping_interval(),
+        read_timeout: source.read_timeout(),
+        stale_after: options.stale_after.unwrap_or(source.stale_after()),
+    };
+    let writer = tokio::spawn(write_frames(source.exchange(), write, outbound_queue));
+    let timer = tokio::spawn(heartbeat(source.exchange(), outbound.clone(), activity.clone(), report_dead, timers));
+    Ok(Connection { id, read, outbound, activity, dead, writer, timer })
+}
+
+fn resolve<'a, S: QuoteSource>(instruments: &'a mut InstrumentRegistry, source: &S, symbol: &str) -> Option<&'a Instrument> {
+    instruments.get_or_resolve(symbol, |symbol| {
+        let instrument = source.instrument(symbol);
+        if instrument.is_none() {
+            warn!(
+                event = "unknown_instrument",
+                exchange = source.exchange(),
+                symbol,
+                "Unknown instrument, not storing its quotes"
+            );
+        }
+        instrument
+    })
+}
+
+/// What the runner and replay do the same way for quotes, ticker stats and
+/// trades: check them, map them to an instrument and its keys, and queue them.
+pub(crate) trait MarketData: Sized {
+    fn symbol(&self) -> &str;
+    fn check(&self) -> Result<(), FeedError>;
+    fn key_prefix<S: QuoteSource>(source: &S, instrument: &Instrument) -> String;
+    fn message(tick: Tick<Self>) -> SinkMessage;
+}
+
+impl MarketData for Quote {
+    fn symbol(&self) -> &str {
+        &self.symbol
+    }
+
+    fn check(&self) -> Result<(), FeedError> {
+        self.validate()
+    }
+
+    fn key_prefix<S: QuoteSource>(source: &S, instrument: &Instrument) -> String {
+        source.key_prefix(instrument)
+    }
+
+    fn message(tick: Tick<Self>) -> SinkMessage {
+        SinkMessage::Quote(tick)
+    }
+}
+
+impl MarketData for TickerStats {
+    fn symbol(&self) -> &str {
+        &self.symbol
+    }
+
+    fn check(&self) -> Result<(), FeedError> {
+        self.validate()
+    }
+
+    fn key_prefix<S: QuoteSource>(source: &S, instrument: &Instrument) -> String {
+        source.stats_prefix(instrument)
+    }
+
+    fn message(tick: Tick<Self>) -> SinkMessage {
+        SinkMessage::Stats(tick)
+    }
+}
+
+impl MarketData for Trade {
+    fn symbol(&self) -> &str {
+        &self.symbol
+    }
+
+    fn check(&self) -> Result<(), FeedError> {
+        self.validate()
+    }
+
+    fn key_prefix<S: QuoteSource>(source: &S, instrument: &Instrument) -> String {
+        source.trade_prefix(instrument)
+    }
+
+    fn message(tick: Tick<Self>) -> SinkMessage {
+        SinkMessage::Trade(tick)
+    }
+}
+
+impl MarketData for BookDepth {
+    fn symbol(&self) -> &str {
+        &self.symbol
+    }
+
+    fn check(&self) -> Result<(), FeedError> {
+        self.validate()
+    }
+
+    fn key_prefix<S: QuoteSource>(source: &S, instrument: &Instrument) -> String {
+        source.book_prefix(instrument)
+    }
+
+    fn message(tick: Tick<Self>) -> SinkMessage {
+        SinkMessage::Book(tick)
+    }
+}
+
+impl MarketData for PerpStats {
+    fn symbol(&self) -> &str {
+        &self.symbol
+    }
+
+    fn check(&self) -> Result<(), FeedError> {
+        self.validate()
+    }
+
+    fn key_prefix<S: QuoteSource>(source: &S, instrument: &Instrument) -> String {
+        source.perp_prefix(instrument)
+    }
+
+    fn message(tick: Tick<Self>) -> SinkMessage {
+        SinkMessage::Perp(tick)
+    }
+}
+
+/// `data` ready for the Redis writer, or `None` if its symbol doesn't map to
+/// an instrument.
+pub(crate) fn tick<S: QuoteSource, T: MarketData>(
+    source: &S,
+    instruments: &mut InstrumentRegistry,
+    data: T,
+    received_at: u64,
+) -> Option<Tick<T>> {
+    let instrument = resolve(instruments, source, data.symbol())?;
+    Some(Tick { key_prefix: T::key_prefix(source, instrument), received_at, data, instrument: instrument.clone() })
+}
+
+/// Per-feed overrides of the timings a [`QuoteSource`] picks for itself.
+#[derive(Clone, Debug, Default)]
+pub struct RunOptions {
+    /// Replaces [`QuoteSource::stale_after`].
+    pub stale_after: Option<Duration>,
+    /// Records every inbound frame under this directory; see [`Recorded`](crate::Recorded).
+    pub record_dir: Option<PathBuf>,
+}
+
+impl RunOptions {
+    /// `$STALE_AFTER_SECS` and `$RECORD_DIR` for the standalone binaries.
+    pub fn from_env() -> Self {
+        RunOptions {
+            stale_after: env::var("STALE_AFTER_SECS").ok().and_then(|secs| secs.parse().ok()).map(Duration::from_secs),
+            record_dir: env::var("RECORD_DIR").ok().filter(|dir| !dir.is_empty()).map(PathBuf::from),
+        }
+    }
+}
+
+/// Why a connection's read loop gave up.
+enum Disconnect {
+    /// The socket is unusable; the supervisor reconnects.
+    Socket(String),
+    /// An error retrying can't fix, e.g. the venue refused a subscription or
+    /// the Redis writer is gone; the connection is still open.
+    Abort(FeedError),
+    /// The process is shutting down; the connection is still open.
+    Shutdown,
+}
+
+/// Counts `error` and logs it with the frame that caused it, then applies its
+/// [`Policy`]: `None` to read on, or why the read loop should stop.
+fn on_error(exchange: &'static str, error: FeedError, frame: &str) -> Option<Disconnect> {
+    let kind = error.kind();
+    metrics::FEED_ERRORS.with_label_values(&[exchange, kind]).inc();
+    match error.policy() {
+        Policy::Skip => {
+            if let FeedError::Validation(_) = error {
+                warn!(event = "invalid_quote", exchange, error = %error, frame, "Dropping invalid quote");
+            } else {
+                metrics::PARSE_FAILURES.with_label_values(&[exchange]).inc();
+                error!(event = "parse_failed", exchange, error = %error, frame, "Parsing failed");
+            }
+            None
+        }
+        Policy::Reconnect => {
+            warn!(event = "feed_error", exchange, kind, error = %error, frame, "Reconnecting");
+            Some(Disconnect::Socket(error.to_string()))
+        }
+        Policy::Abort => {
+            error!(event = "feed_error", exchange, kind, error = %error, frame, "Stopping feed");
+            Some(Disconnect::Abort(error))
+        }
+    }
+}
+
+/// Hands `message` to the Redis writer without waiting. If the writer is
+/// behind the message is dropped; `Some` if it has stopped.
+fn queue(exchange: &'static str, writes: &mpsc::Sender<SinkMessage>, message: SinkMessage) -> Option<Disconnect> {
+    match writes.try_send(message) {
+        Ok(()) => None,
+        Err(mpsc::error::TrySendError::Full(SinkMessage::Quote(Tick { instrument, .. })))
+        | Err(mpsc::error::TrySendError::Full(SinkMessage::Stats(Tick { instrument, .. })))
+        | Err(mpsc::error::TrySendError::Full(SinkMessage::Trade(Tick { instrument, .. })))
+        | Err(mpsc::error::TrySendError::Full(SinkMessage::Book(Tick { instrument, .. })))
+        | Err(mpsc::error::TrySendError::Full(SinkMessage::Perp(Tick { instrument, .. }))) => {
+            warn!(event = "quote_dropped", exchange, symbol = %instrument.symbol(), "Redis is behind, dropping market data");
+            None
+        }
+        Err(mpsc::error::TrySendError::Full(SinkMessage::Stale)) => None,
+        Err(mpsc::error::TrySendError::Closed(_)) => Some(Disconnect::Abort(FeedError::sink("Redis writer has stopped"))),
+    }
+}
+
+/// Checks and queues everything one frame carried, stamped with the time it
+/// arrived. `Some` if the read loop should stop.
+fn queue_all<S: QuoteSource, T: MarketData>(
+    source: &S,
+    instruments: &mut InstrumentRegistry,
+    writes: &mpsc::Sender<SinkMessage>,
+    items: Vec<T>,
+    frame: &str,
+) -> Option<Disconnect> {
+    let exchange = source.exchange();
+    let received_at = get_current_timestamp();
+    for item in items {
+        if let Err(error) = item.check() {
+            match on_error(exchange, error, frame) {
+                Some(disconnect) => return Some(disconnect),
+                None => continue,
+            }
+        }
+        let Some(tick) = tick(source, instruments, item, received_at) else { continue };
+        if let Some(disconnect) = queue(exchange, writes, T::message(tick)) {
+            return Some(disconnect);
+        }
+    }
+    None
+}
+
+/// Reads, parses and queues quotes from one connection until it fails.
+async fn read_until_disconnect<S: QuoteSource>(
+    source: &mut S,
+    connection: &mut Connection,
+    instruments: &mut InstrumentRegistry,
+    writes: &mpsc::Sender<SinkMessage>,
+    recorder: Option<&Recorder>,
+    shutdown: &mut Shutdown,
+) -> Disconnect {
+    loop {
+        let next = tokio::select! {
+            next = connection.read.next() => next,
+            _ = shutdown.requested() => return Disconnect::Shutdown,
+            reason = &mut connection.dead => {
+                return Disconnect::Socket(reason.unwrap_or_else(|_| "timer task stopped".to_string()));
+            }
+        };
+        connection.activity.frame();
+        let exchange = source.exchange();
+        if matches!(next, Some(Ok(_))) {
+            metrics::message_received(exchange);
+        }
+
+        let message_string = match next {
+            Some(Ok(Message::Text(s))) => {
+                if let Some(recorder) = recorder {
+                    recorder.record(connection.id, RecordedEvent::Text { frame: s.clone() });
+                }
+                s
+            }
+            Some(Ok(Message::Binary(data))) => match source.decode_binary(&data) {
+                Ok(decoded) => {
+                    if let Some(recorder) = recorder {
+                        recorder.record(connection.id, RecordedEvent::Binary { frame: decoded.clone() });
+                    }
+                    decoded
+                }
+                Err(error) => {
+                    if let Some(recorder) = recorder {
+                        recorder.record(connection.id, RecordedEvent::undecodable(&data));
+                    }
+                    match on_error(exchange, error, &format!("<{} binary bytes>", data.len())) {
+                        Some(disconnect) => return disconnect,
+                        None => continue,
+                    }
+                }
+            },
+            Some(Ok(Message::Ping(data))) => {
+                trace!(event = "ping_received", exchange, "Received Ping");
+                metrics::PINGS.with_label_values(&[exchange, "received"]).inc();
+                if let Err(e) = connection.send(Message::Pong(data)) {
+                    return Disconnect::Socket(e.to_string());
+                }
+                metrics::PONGS.with_label_values(&[exchange, "sent"]).inc();
+                trace!(event = "pong_sent", exchange, "Sent Pong");
+                continue;
+            }
+            Some(Ok(Message::Pong(_))) => {
+                trace!(event = "pong_received", exchange, "Received Pong");
+                metrics::PONGS.with_label_values(&[exchange, "received"]).inc();
+                continue;
+            }
+            Some(Ok(Message::Close(frame))) => return Disconnect::Socket(format!("closed by server: {:?}", frame)),
+            Some(Ok(message)) => {
+                warn!(event = "unexpected_frame", exchange, frame = ?message, "Unexpected frame");
+                continue;
+            }
+            Some(Err(error)) => return Disconnect::Socket(error.to_string()),
+            None => return Disconnect::Socket("socket closed".to_string()),
+        };
+
+        match source.parse(&message_string) {
+            Frame::Quotes(quotes) => {
+                if !quotes.is_empty() {
+                    connection.data_received(exchange);
+                }
+                if let Some(disconnect) = queue_all(source, instruments, writes, quotes, &message_string) {
+                    return disconnect;
+                }
+            }
+            // Not market data as far as staleness goes: 24h stats keep coming on a stuck feed
+            Frame::Stats(tickers) => {
+                if let Some(disconnect) = queue_all(source, instruments, writes, tickers, &message_string) {
+                    return disconnect;
+                }
+            }
+            Frame::Trades(trades) => {
+                if !trades.is_empty() {
+                    connection.data_received(exchange);
+                }
+                if let Some(disconnect) = queue_all(source, instruments, writes, trades, &message_string) {
+                    return disconnect;
+                }
+            }
+            Frame::Books(books) => {
+                if !books.is_empty() {
+                    connection.data_received(exchange);
+                }
+                if let Some(disconnect) = queue_all(source, instruments, writes, books, &message_string) {
+                    return disconnect;
+                }
+            }
+            Frame::Perps(perps) => {
+                if !perps.is_empty() {
+                    connection.data_received(exchange);
+                }
+                if let Some(disconnect) = queue_all(source, instruments, writes, perps, &message_string) {
+                    return disconnect;
+                }
+            }
+            Frame::Resync { symbol, reason, messages } => {
+                metrics::BOOK_RESYNCS.with_label_values(&[exchange]).inc();
+                warn!(event = "book_resync", exchange, symbol, reason, frame = %message_string, "Resyncing order book");
+                for message in messages {
+                    info!(event = "resubscribe", exchange, frame = %message, "Requesting a fresh book");
+                    if let Err(e) = connection.send(Message::Text(message)) {
+                        return Disconnect::Socket(e.to_string());
+                    }
+                }
+            }
+            Frame::Reply(reply) => {
+                if let Err(e) = connection.send(Message::Text(reply.clone())) {
+                    return Disconnect::Socket(e.to_string());
+                }
+                debug!(event = "reply", exchange, frame = %reply, "Sent reply");
+            }
+            Frame::Event(event) => debug!(event = "venue_event", exchange, "{}", event),
+            Frame::Ignored => {}
+            Frame::Error(error) => {
+                if let Some(disconnect) = on_error(exchange, error, &message_string) {
+                    return disconnect;
+                }
+            }
+        }
+    }
+}
+
+/// Connects `source`, then reads, parses and stores its quotes. Any socket
+/// failure, including failing to connect in the first place, is retried
+/// forever with jittered backoff. Redis writes are batched on their own task so
+/// a slow Redis never holds up reading the socket. Bad frames and quotes are
+/// counted and skipped; see [`FeedError::policy`].
+///
+/// Once `shutdown` trips, the feed stops reading, unsubscribes, closes the
+/// socket with a close frame and waits for queued quotes to reach Redis. That
+/// returns `Ok`. An error with [`Policy::Abort`] shuts the feed down the same
+/// way and is returned, as is failing to drain the Redis writer in time.
+pub async fn run<S: QuoteSource>(
+    source: &mut S,
+    sink: RedisSink,
+    options: RunOptions,
+    mut shutdown: Shutdown,
+) -> Result<(), FeedError> {
+    let exchange = source.exchange();
+    let mut instruments = InstrumentRegistry::default();
+    let mut series = Vec::new();
+    for symbol in source.symbols() {
+        if let Some(instrument) = resolve(&mut instruments, source, symbol) {
+            series.push((source.key_prefix(instrument), instrument.clone()));
+        }
+    }
+    let (writes, writer) = sink.spawn_writer(exchange, source.open_interest_unit(), series, FeedKind::Live);
+    let mut supervisor = Supervisor::new(exchange);
+    health::register(exchange);
+    let recorder = options.record_dir.as_deref().map(|dir| Recorder::start(dir, exchange));
+
+    let mut failure = None;
+
+    loop {
+        let connected = tokio::select! {
+            connected = connect_and_subscribe(source, &options, recorder.as_ref()) => connected,
+            _ = shutdown.requested() => break,
+        };
+        let mut connection = match connected {
+            Ok(connection) => connection,
+            Err(error) if error.policy() == Policy::Abort => {
+                error!(event = "feed_error", exchange, kind = error.kind(), error = %error, "Stopping feed");
+                failure = Some(error);
+                break;
+            }
+            Err(error) => {
+                metrics::FEED_ERRORS.with_label_values(&[exchange, error.kind()]).inc();
+                tokio::select! {
+                    _ = supervisor.disconnected(error.to_string()) => continue,
+                    _ = shutdown.requested() => break,
+                }
+            }
+        };
+        supervisor.connected();
+        health::set_subscribed(exchange, true);
+
+        let disconnect = read_until_disconnect(
+            source,
+            &mut connection,
+            &mut instruments,
+            &writes,
+            recorder.as_ref(),
+            &mut shutdown,
+        )
+        .await;
+        health::set_subscribed(exchange, false);
+        match disconnect {
+            Disconnect::Socket(error) => {
+                drop(connection);
+                // Whatever was last written is now getting old; cleared again by the next quote
+                if writes.send(SinkMessage::Stale).await.is_err() {
+                    return Err(FeedError::sink("Redis writer has stopped"));
+                }
+                tokio::select! {
+                    _ = supervisor.disconnected(error) => {}
+                    _ = shutdown.requested() => break,
+                }
+            }
+            Disconnect::Shutdown => {
+                connection.close(exchange, source.unsubscribe_messages()).await;
+                break;
+            }
+            Disconnect::Abort(error) => {
+                connection.close(exchange, source.unsubscribe_messages()).await;
+                failure = Some(error);
+                break;
+            }
+        }
+    }
+
+    if let Some(recorder) = recorder {
+        recorder.finish().await;
+    }
+
+    // Mark the feed stale, then close the queue so the writer flushes what's left and exits
+    let drained = tokio::time::timeout(FLUSH_TIMEOUT, async move {
+        writes.send(SinkMessage::Stale).await.map_err(|_| FeedError::sink("Redis writer has stopped"))?;
+        drop(writes);
+        writer.await.map_err(|e| FeedError::sink(format!("Redis writer failed: {}", e)))
+    })
+    .await
+    .unwrap_or_else(|_| Err(FeedError::sink(format!("quotes still queued for Redis after {:?}", FLUSH_TIMEOUT))));
+    // What stopped the feed matters more than anything left undrained because of it
+    if let Some(error) = failure {
+        return Err(error);
+    }
+    drained?;
+    info!(event = "feed_shutdown", exchange, "Flushed to Redis and shut down cleanly");
+    Ok(())
+}
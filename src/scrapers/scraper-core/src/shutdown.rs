@@ -0,0 +1,65 @@
+use std::future;
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::sync::watch;
+use tracing::{error, info};
+
+/// Resolves once the process has been asked to stop. Cheap to clone; every
+/// feed holds its own copy.
+#[derive(Clone, Debug)]
+pub struct Shutdown {
+    stopping: watch::Receiver<bool>,
+}
+
+/// Trips a [`Shutdown`] made by [`Shutdown::manual`].
+#[derive(Debug)]
+pub struct ShutdownTrigger {
+    trip: watch::Sender<bool>,
+}
+
+impl ShutdownTrigger {
+    pub fn trip(&self) {
+        let _ = self.trip.send(true);
+    }
+}
+
+impl Shutdown {
+    /// Trips only when the returned trigger says so, e.g. at the end of a test.
+    pub fn manual() -> (ShutdownTrigger, Self) {
+        let (trip, stopping) = watch::channel(false);
+        (ShutdownTrigger { trip }, Shutdown { stopping })
+    }
+
+    /// Trips on SIGTERM, as sent by `docker stop`, or SIGINT. Must be called
+    /// inside the runtime, before any feed starts.
+    pub fn on_signals() -> Self {
+        let (trip, stopping) = watch::channel(false);
+        match (signal(SignalKind::terminate()), signal(SignalKind::interrupt())) {
+            (Ok(mut terminate), Ok(mut interrupt)) => {
+                tokio::spawn(async move {
+                    let name = tokio::select! {
+                        _ = terminate.recv() => "SIGTERM",
+                        _ = interrupt.recv() => "SIGINT",
+                    };
+                    info!(event = "shutdown_requested", signal = name, "Shutting down");
+                    let _ = trip.send(true);
+                });
+            }
+            (Err(e), _) | (_, Err(e)) => {
+                error!(event = "signal_handler_failed", error = %e, "Can't listen for shutdown signals");
+            }
+        }
+        Shutdown { stopping }
+    }
+
+    pub fn is_requested(&self) -> bool {
+        *self.stopping.borrow()
+    }
+
+    /// Waits until shutdown is requested.
+    pub async fn requested(&mut self) {
+        if self.stopping.wait_for(|stopping| *stopping).await.is_err() {
+            // The signal task is gone without tripping, so shutdown never comes
+            future::pending::<()>().await;
+        }
+    }
+}
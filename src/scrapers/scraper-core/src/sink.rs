@@ -0,0 +1,923 @@
+use crate::book::{BookDepth, DEPTH_BANDS_BPS};
+use crate::instrument::Instrument;
+use crate::metrics::{LatencyWindow, ASK, BID, EXCHANGE_TO_RECEIVE, RECEIVE_TO_COMMIT, REDIS_WRITE_ERRORS};
+use crate::perp::{PerpPrices, PerpStats};
+use crate::quote::{Quote, TickerStats};
+use crate::spool::Spool;
+use crate::trade::Trade;
+use crate::utils::get_current_timestamp;
+use futures_util::future::join_all;
+use redis::aio::ConnectionManager;
+use redis::{ErrorKind, RedisError, RedisResult};
+use redis_ts::{AsyncTsCommands, TsDuplicatePolicy, TsOptions};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::env;
+use std::io;
+use std::mem;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, OnceCell};
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+use tracing::{error, info, warn};
+
+const RETENTION_TIME: u64 = 3600000;
+/// Quotes queued for Redis before new ones are dropped instead of holding up reads.
+const QUEUE_SIZE: usize = 10_000;
+/// A batch is flushed once it holds this many samples...
+const MAX_BATCH_SAMPLES: usize = 2_000;
+/// ...or once its oldest sample has waited this long, whichever comes first.
+const MAX_BATCH_LATENCY: Duration = Duration::from_millis(50);
+/// Samples per TS.MADD; a flush pipelines as many of these as it needs.
+const MADD_CHUNK: usize = 500;
+/// How often each feed writes the p50/p99 of its latency windows.
+const LATENCY_REPORT_INTERVAL: Duration = Duration::from_secs(10);
+/// Redis commands that take longer than this count as failed.
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(5);
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+/// Attempts per (re)connect, 100ms apart and doubling, before a command fails.
+const CONNECT_RETRIES: usize = 3;
+/// While Redis is down, how often a feed checks whether it is back.
+const RECOVERY_INTERVAL: Duration = Duration::from_secs(5);
+/// Spooled samples replayed per pipeline once Redis is back.
+pub(crate) const REPLAY_CHUNK: usize = 10_000;
+/// How far behind a prefix's latest trade its per-millisecond totals are
+/// kept, so a trade that arrives late still adds to its millisecond.
+const TRADE_WINDOW_MS: u64 = 60_000;
+
+/// One quote, set of ticker stats or trade on its way to Redis.
+pub(crate) struct Tick<T> {
+    pub key_prefix: String,
+    /// Local receive time in epoch millis.
+    pub received_at: u64,
+    pub data: T,
+    pub instrument: Instrument,
+}
+
+/// The trades a venue stamped with one millisecond, summed. Redis keeps one
+/// sample per millisecond, so each trade rewrites the totals so far.
+struct TradeBucket {
+    /// Price of the last trade in the millisecond.
+    price: f64,
+    volume: f64,
+    signed_volume: f64,
+}
+
+/// What a [`FeedWriter`] is storing.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum FeedKind {
+    Live,
+    /// Recorded frames played back by [`crate::replay`]. Its latency is
+    /// history, not this process's, so none is reported.
+    Replay,
+}
+
+pub(crate) enum SinkMessage {
+    Quote(Tick<Quote>),
+    Stats(Tick<TickerStats>),
+    Trade(Tick<Trade>),
+    Book(Tick<BookDepth>),
+    Perp(Tick<PerpStats>),
+    /// The feed lost its data; every series it writes is now out of date.
+    Stale,
+}
+
+/// Cheap to clone; every clone writes through the same multiplexed Redis
+/// connection so a process running several feeds only holds one. The
+/// connection is opened on first use and reopened whenever it drops, so a
+/// scraper can start, and keep running, while Redis is down; see [`FeedWriter`]
+/// for what happens to quotes meanwhile.
+#[derive(Clone)]
+pub struct RedisSink {
+    client: redis::Client,
+    con: Arc<OnceCell<ConnectionManager>>,
+    spool_dir: PathBuf,
+    namespace: Option<String>,
+    retention_time: u64,
+}
+
+impl RedisSink {
+    /// Only fails on a malformed connection string. If Redis can't be reached
+    /// yet, that is logged and the sink spools until it can.
+    pub async fn connect(connection_string: &str) -> redis::RedisResult<Self> {
+        let client = redis::Client::open(connection_string)?;
+        let sink = RedisSink {
+            client,
+            con: Arc::default(),
+            spool_dir: spool_dir_from_env(),
+            namespace: None,
+            retention_time: RETENTION_TIME,
+        };
+        if let Err(e) = sink.connection().await {
+            warn!(event = "redis_unavailable", error = %e, "Redis is unreachable, spooling quotes to disk until it is back");
+        }
+        Ok(sink)
+    }
+
+    pub async fn from_env() -> redis::RedisResult<Self> {
+        let redis_host = env::var("REDIS_HOST").unwrap_or("cache".to_string());
+        Self::connect_to_host(&redis_host, 6379).await
+    }
+
+    pub async fn connect_to_host(redis_host: &str, redis_port: u16) -> redis::RedisResult<Self> {
+        let redis_password = env::var("REDIS_PASSWORD")
+            .map_err(|_| RedisError::from((ErrorKind::InvalidClientConfig, "$REDIS_PASSWORD is not set")))?;
+        let connection_string = format!("redis://default:{}@{}:{}", redis_password, redis_host, redis_port);
+        Self::connect(&connection_string).await
+    }
+
+    /// Where each feed spools samples while Redis is down. `$SPOOL_DIR` by
+    /// default, falling back to `scraper-spool` under the system temp dir.
+    pub fn with_spool_dir(mut self, spool_dir: impl Into<PathBuf>) -> Self {
+        self.spool_dir = spool_dir.into();
+        self
+    }
+
+    /// Writes every key under `{namespace}:` and labels every series
+    /// `NAMESPACE={namespace}`, keeping them apart from the live keys.
+    pub fn with_namespace(mut self, namespace: impl Into<String>) -> Self {
+        self.namespace = Some(namespace.into());
+        self
+    }
+
+    /// How much history every series keeps, an hour by default. Zero keeps
+    /// everything.
+    pub fn with_retention(mut self, retention: Duration) -> Self {
+        self.retention_time = retention.as_millis() as u64;
+        self
+    }
+
+    fn namespaced(&self, key: &str) -> String {
+        match &self.namespace {
+            Some(namespace) => format!("{}:{}", namespace, key),
+            None => key.to_string(),
+        }
+    }
+
+    fn series_options(&self, exchange: &str) -> TsOptions {
+        let options = Self::options(exchange).retention_time(self.retention_time);
+        match &self.namespace {
+            Some(namespace) => options.label("NAMESPACE", namespace),
+            None => options,
+        }
+    }
+
+    async fn connection(&self) -> RedisResult<ConnectionManager> {
+        self.con
+            .get_or_try_init(|| {
+                ConnectionManager::new_with_backoff_and_timeouts(
+                    self.client.clone(),
+                    2,
+                    100,
+                    CONNECT_RETRIES,
+                    RESPONSE_TIMEOUT,
+                    CONNECT_TIMEOUT,
+                )
+            })
+            .await
+            .cloned()
+    }
+
+    pub fn options(exchange: &str) -> TsOptions {
+        TsOptions::default()
+            .duplicate_policy(TsDuplicatePolicy::Last)
+            .retention_time(RETENTION_TIME)
+            .label("EXCHANGE", exchange)
+    }
+
+    /// The four `:BUY:PRICE` ... `:SELL:VOL` series under `key_prefix`, the
+    /// `:IMBALANCE` and `:MICROPRICE` derived from them, plus its `:LATENCY`
+    /// companion and `:STALE` marker, each with its full label set.
+    fn quote_series(key_prefix: &str, instrument: &Instrument, options: &TsOptions, stale: bool) -> Vec<(String, TsOptions)> {
+        let options = options
+            .clone()
+            .label("SYMBOL", &instrument.symbol())
+            .label("BASE", &instrument.base)
+            .label("QUOTE", &instrument.quote)
+            .label("TYPE", instrument.kind.label())
+            .label("SUB", "QUOTE")
+            .label("STALE", if stale { "1" } else { "0" });
+        let mut series = Vec::new();
+        for side in ["BUY", "SELL"] {
+            let side_options = options.clone().label("SIDE", side);
+            for group in ["PRICE", "VOL"] {
+                series.push((format!("{}:{}:{}", key_prefix, side, group), side_options.clone().label("GROUP", group)));
+            }
+        }
+        for group in ["IMBALANCE", "MICROPRICE"] {
+            series.push((format!("{}:{}", key_prefix, group), options.clone().label("GROUP", group)));
+        }
+        series.push((format!("{}:LATENCY", key_prefix), options.clone().label("GROUP", "LATENCY")));
+        series.push((format!("{}:STALE", key_prefix), options.label("GROUP", "STALE")));
+        series
+    }
+
+    /// The `:LAST` and `:VOL` series under a [`QuoteSource::stats_prefix`](crate::QuoteSource::stats_prefix).
+    /// They carry no `STALE` label; a ticker says how old it is by itself.
+    fn stats_series(key_prefix: &str, instrument: &Instrument, options: &TsOptions) -> Vec<(String, TsOptions)> {
+        let options = options
+            .clone()
+            .label("SYMBOL", &instrument.symbol())
+            .label("BASE", &instrument.base)
+            .label("QUOTE", &instrument.quote)
+            .label("TYPE", instrument.kind.label())
+            .label("SUB", "STATS")
+            .label("WINDOW", "24H");
+        ["LAST", "VOL"]
+            .into_iter()
+            .map(|group| (format!("{}:{}", key_prefix, group), options.clone().label("GROUP", group)))
+            .collect()
+    }
+
+    /// The `:PRICE`, `:VOL` and `:SIGNED_VOL` series under a
+    /// [`QuoteSource::trade_prefix`](crate::QuoteSource::trade_prefix). Like
+    /// stats, they carry no `STALE` label.
+    fn trade_series(key_prefix: &str, instrument: &Instrument, options: &TsOptions) -> Vec<(String, TsOptions)> {
+        let options = options
+            .clone()
+            .label("SYMBOL", &instrument.symbol())
+            .label("BASE", &instrument.base)
+            .label("QUOTE", &instrument.quote)
+            .label("TYPE", instrument.kind.label())
+            .label("SUB", "TRADE");
+        ["PRICE", "VOL", "SIGNED_VOL"]
+            .into_iter()
+            .map(|group| (format!("{}:{}", key_prefix, group), options.clone().label("GROUP", group)))
+            .collect()
+    }
+
+    /// `levels` levels of `:{BUY|SELL}:L{n}:PRICE` and `:VOL`, plus
+    /// `:{BUY|SELL}:DEPTH:{bps}BPS` for every band and the `:IMBALANCE`
+    /// over the levels and `:IMBALANCE:{bps}BPS` within each band, under a
+    /// [`QuoteSource::book_prefix`](crate::QuoteSource::book_prefix). Like
+    /// stats, they carry no `STALE` label.
+    fn book_series(key_prefix: &str, instrument: &Instrument, options: &TsOptions, levels: usize) -> Vec<(String, TsOptions)> {
+        let options = options
+            .clone()
+            .label("SYMBOL", &instrument.symbol())
+            .label("BASE", &instrument.base)
+            .label("QUOTE", &instrument.quote)
+            .label("TYPE", instrument.kind.label())
+            .label("SUB", "BOOK");
+        let mut series = Vec::new();
+        for side in ["BUY", "SELL"] {
+            let side_options = options.clone().label("SIDE", side);
+            for level in 1..=levels {
+                let level_options = side_options.clone().label("LEVEL", &level.to_string());
+                for group in ["PRICE", "VOL"] {
+                    let key = format!("{}:{}:L{}:{}", key_prefix, side, level, group);
+                    series.push((key, level_options.clone().label("GROUP", group)));
+                }
+            }
+            for bps in DEPTH_BANDS_BPS {
+                let key = format!("{}:{}:DEPTH:{}BPS", key_prefix, side, bps);
+                series.push((key, side_options.clone().label("GROUP", "DEPTH").label("BPS", &bps.to_string())));
+            }
+        }
+        series.push((format!("{}:IMBALANCE", key_prefix), options.clone().label("GROUP", "IMBALANCE")));
+        for bps in DEPTH_BANDS_BPS {
+            let key = format!("{}:IMBALANCE:{}BPS", key_prefix, bps);
+            series.push((key, options.clone().label("GROUP", "IMBALANCE").label("BPS", &bps.to_string())));
+        }
+        series
+    }
+
+    /// The `groups` series under a
+    /// [`QuoteSource::perp_prefix`](crate::QuoteSource::perp_prefix), out of
+    /// `:FUNDING_RATE`, `:FUNDING_RATE_PAID`, `:MARK_PRICE`, `:INDEX_PRICE`,
+    /// `:OPEN_INTEREST` and `:BASIS`. Like stats, they carry no `STALE` label;
+    /// `:OPEN_INTEREST` is labelled `UNIT=<open_interest_unit>`.
+    fn perp_series(
+        key_prefix: &str,
+        instrument: &Instrument,
+        options: &TsOptions,
+        open_interest_unit: &str,
+        groups: &[&str],
+    ) -> Vec<(String, TsOptions)> {
+        let options = options
+            .clone()
+            .label("SYMBOL", &instrument.symbol())
+            .label("BASE", &instrument.base)
+            .label("QUOTE", &instrument.quote)
+            .label("TYPE", instrument.kind.label())
+            .label("SUB", "PERP");
+        groups
+            .iter()
+            .map(|&group| {
+                let series_options = options.clone().label("GROUP", group);
+                let series_options = match group {
+                    "OPEN_INTEREST" => series_options.label("UNIT", open_interest_unit),
+                    _ => series_options,
+                };
+                (format!("{}:{}", key_prefix, group), series_options)
+            })
+            .collect()
+    }
+
+    async fn create_book_series(
+        &self,
+        key_prefix: &str,
+        instrument: &Instrument,
+        options: &TsOptions,
+        levels: usize,
+    ) -> RedisResult<()> {
+        for (key, series_options) in Self::book_series(key_prefix, instrument, options, levels) {
+            self.create_series(&key, series_options).await?;
+        }
+        Ok(())
+    }
+
+    async fn create_perp_series(
+        &self,
+        key_prefix: &str,
+        instrument: &Instrument,
+        options: &TsOptions,
+        open_interest_unit: &str,
+        groups: &[&str],
+    ) -> RedisResult<()> {
+        for (key, series_options) in Self::perp_series(key_prefix, instrument, options, open_interest_unit, groups) {
+            self.create_series(&key, series_options).await?;
+        }
+        Ok(())
+    }
+
+    async fn create_stats_series(&self, key_prefix: &str, instrument: &Instrument, options: &TsOptions) -> RedisResult<()> {
+        for (key, series_options) in Self::stats_series(key_prefix, instrument, options) {
+            self.create_series(&key, series_options).await?;
+        }
+        Ok(())
+    }
+
+    async fn create_trade_series(&self, key_prefix: &str, instrument: &Instrument, options: &TsOptions) -> RedisResult<()> {
+        for (key, series_options) in Self::trade_series(key_prefix, instrument, options) {
+            self.create_series(&key, series_options).await?;
+        }
+        Ok(())
+    }
+
+    /// Creates the series under `key_prefix` with their labels. Series left
+    /// over from an earlier run get their labels and retention brought up to
+    /// date instead.
+    pub async fn create_quote_series(
+        &self,
+        key_prefix: &str,
+        instrument: &Instrument,
+        options: &TsOptions,
+        stale: bool,
+    ) -> RedisResult<()> {
+        for (key, series_options) in Self::quote_series(key_prefix, instrument, options, stale) {
+            self.create_series(&key, series_options).await?;
+        }
+        self.write_stale_marker(key_prefix, stale).await
+    }
+
+    async fn create_series(&self, key: &str, options: TsOptions) -> RedisResult<()> {
+        let mut con = self.connection().await?;
+        let created: RedisResult<()> = con.ts_create(key, options.clone()).await;
+        match created {
+            Err(e) if e.to_string().contains("already exists") => con.ts_alter(key, options).await,
+            other => other,
+        }
+    }
+
+    /// `{EXCHANGE}:FEED:LATENCY:{RECEIVE|COMMIT}:{P50|P99}` with their labels.
+    fn latency_series(exchange: &str, options: &TsOptions) -> Vec<(String, TsOptions)> {
+        let mut series = Vec::new();
+        for stage in ["RECEIVE", "COMMIT"] {
+            for quantile in ["P50", "P99"] {
+                let key = format!("{}:FEED:LATENCY:{}:{}", exchange, stage, quantile);
+                let series_options = options
+                    .clone()
+                    .label("SUB", "FEED")
+                    .label("GROUP", "LATENCY")
+                    .label("STAGE", stage)
+                    .label("QUANTILE", quantile);
+                series.push((key, series_options));
+            }
+        }
+        series
+    }
+
+    /// Flips the `STALE` label on every series under `key_prefix` and records
+    /// the change in its `:STALE` marker series (1 stale, 0 fresh).
+    pub async fn set_stale(&self, key_prefix: &str, instrument: &Instrument, options: &TsOptions, stale: bool) -> RedisResult<()> {
+        let mut con = self.connection().await?;
+        for (key, series_options) in Self::quote_series(key_prefix, instrument, options, stale) {
+            con.ts_alter::<_, ()>(&key, series_options).await?;
+        }
+        self.write_stale_marker(key_prefix, stale).await
+    }
+
+    async fn write_stale_marker(&self, key_prefix: &str, stale: bool) -> RedisResult<()> {
+        let value = if stale { 1.0 } else { 0.0 };
+        self.connection()
+            .await?
+            .ts_add(format!("{}:STALE", key_prefix), get_current_timestamp(), value)
+            .await
+    }
+
+    /// Writes `samples` and returns the error for each one Redis turned down,
+    /// e.g. for a key holding another type or a timestamp past retention.
+    /// Fails only if Redis didn't answer.
+    async fn flush(&self, samples: &[(String, u64, f64)]) -> RedisResult<Vec<RedisError>> {
+        let mut con = self.connection().await?;
+        let mut pipe = redis::pipe();
+        for chunk in samples.chunks(MADD_CHUNK) {
+            pipe.cmd("TS.MADD").arg(chunk).ignore();
+        }
+        match pipe.query_async::<_, ()>(&mut con).await {
+            Ok(()) => Ok(Vec::new()),
+            // redis-rs fails the whole pipeline on the first sample turned
+            // down, though Redis wrote the rest, so ask again one at a time
+            Err(e) if is_rejection(&e) => self.add_each(&con, samples).await,
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn add_each(&self, con: &ConnectionManager, samples: &[(String, u64, f64)]) -> RedisResult<Vec<RedisError>> {
+        let adds = samples.iter().map(|(key, ts, value)| {
+            let mut con = con.clone();
+            async move { con.ts_add::<_, _, _, ()>(key, *ts, *value).await }
+        });
+        let mut rejected = Vec::new();
+        for added in join_all(adds).await {
+            match added {
+                Ok(()) => {}
+                Err(e) if is_rejection(&e) => rejected.push(e),
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(rejected)
+    }
+
+    /// Starts the task that owns this sink for one feed and returns the queue
+    /// feeding it. The task flushes whatever is queued and exits once every
+    /// sender is dropped. See [`FeedWriter`].
+    pub(crate) fn spawn_writer(
+        self,
+        exchange: &str,
+        open_interest_unit: &'static str,
+        series: Vec<(String, Instrument)>,
+        kind: FeedKind,
+    ) -> (mpsc::Sender<SinkMessage>, JoinHandle<()>) {
+        let spool = Spool::open(&self.spool_dir, exchange);
+        let mut writer = FeedWriter {
+            exchange: exchange.to_string(),
+            key_root: self.namespaced(exchange),
+            options: self.series_options(exchange),
+            sink: self,
+            kind,
+            series: HashMap::new(),
+            stats_series: HashMap::new(),
+            trade_series: HashMap::new(),
+            trade_buckets: HashMap::new(),
+            book_series: HashMap::new(),
+            perp_series: HashMap::new(),
+            perp_prices: HashMap::new(),
+            open_interest_unit,
+            stale: HashSet::new(),
+            batch: Vec::with_capacity(MAX_BATCH_SAMPLES),
+            batch_received_at: Vec::new(),
+            receive_latency: LatencyWindow::default(),
+            commit_latency: LatencyWindow::default(),
+            last_report: Instant::now(),
+            // A spool left from an earlier run is replayed as soon as Redis answers
+            degraded: !spool.is_empty(),
+            spool,
+            last_recovery: None,
+        };
+        let (writes, mut queued) = mpsc::channel::<SinkMessage>(QUEUE_SIZE);
+        let task = tokio::spawn(async move {
+            writer.create_startup_series(series).await;
+            while let Some(first) = queued.recv().await {
+                let deadline = Instant::now() + MAX_BATCH_LATENCY;
+                let mut next = Some(first);
+                while let Some(message) = next.take() {
+                    match message {
+                        SinkMessage::Quote(tick) => writer.push(tick).await,
+                        SinkMessage::Stats(tick) => writer.push_stats(tick).await,
+                        SinkMessage::Trade(tick) => writer.push_trade(tick).await,
+                        SinkMessage::Book(tick) => writer.push_book(tick).await,
+                        SinkMessage::Perp(tick) => writer.push_perp(tick).await,
+                        SinkMessage::Stale => {
+                            writer.flush().await;
+                            writer.mark_all_stale().await;
+                        }
+                    }
+                    if writer.batch.len() < MAX_BATCH_SAMPLES {
+                        next = tokio::time::timeout_at(deadline, queued.recv()).await.ok().flatten();
+                    }
+                }
+                writer.flush().await;
+                if writer.kind == FeedKind::Live && writer.last_report.elapsed() >= LATENCY_REPORT_INTERVAL {
+                    writer.report_latency().await;
+                }
+            }
+        });
+        (writes, task)
+    }
+}
+
+/// Writes one feed's quotes, ticker stats and trades. Quote series for the configured
+/// symbols are created up front and start out stale until their first quote;
+/// prefixes that only show up later are created the first time they're seen. Quotes are flushed
+/// as pipelined TS.MADDs, bounded by [`MAX_BATCH_SAMPLES`] and
+/// [`MAX_BATCH_LATENCY`]. Also tracks exchange→receive and receive→commit
+/// latency, both as Prometheus histograms and as windowed p50/p99 series.
+///
+/// When a write fails the feed is degraded: its samples go to an on-disk
+/// [`Spool`] instead, and every [`RECOVERY_INTERVAL`] it checks whether Redis
+/// is back. Once it is, every series is recreated, in case Redis came back
+/// empty, and the spool is replayed, a chunk at a time and close to timestamp
+/// order, before live writes resume.
+struct FeedWriter {
+    sink: RedisSink,
+    kind: FeedKind,
+    exchange: String,
+    /// `exchange`, under the sink's namespace if it has one.
+    key_root: String,
+    options: TsOptions,
+    series: HashMap<String, Instrument>,
+    /// Ticker stats prefixes, created the first time they're seen.
+    stats_series: HashMap<String, Instrument>,
+    /// Trade prefixes, created the first time they're seen.
+    trade_series: HashMap<String, Instrument>,
+    /// The last [`TRADE_WINDOW_MS`] of trades under each trade prefix, by
+    /// millisecond.
+    trade_buckets: HashMap<String, BTreeMap<u64, TradeBucket>>,
+    /// Book prefixes, created the first time they're seen, with how many
+    /// levels have series so far.
+    book_series: HashMap<String, (Instrument, usize)>,
+    /// Perpetual swap prefixes, with the figures that have series so far.
+    /// Each is created the first time a value for it is seen, since no venue
+    /// sends them all.
+    perp_series: HashMap<String, (Instrument, Vec<&'static str>)>,
+    /// The latest mark and index price under each perpetual swap prefix.
+    perp_prices: HashMap<String, PerpPrices>,
+    /// See [`QuoteSource::open_interest_unit`](crate::QuoteSource::open_interest_unit).
+    open_interest_unit: &'static str,
+    stale: HashSet<String>,
+    batch: Vec<(String, u64, f64)>,
+    /// Receive time of every quote in `batch`.
+    batch_received_at: Vec<u64>,
+    receive_latency: LatencyWindow,
+    commit_latency: LatencyWindow,
+    last_report: Instant,
+    spool: Spool,
+    degraded: bool,
+    last_recovery: Option<Instant>,
+}
+
+impl FeedWriter {
+    async fn create_startup_series(&mut self, series: Vec<(String, Instrument)>) {
+        for (key_prefix, instrument) in series {
+            let key_prefix = self.sink.namespaced(&key_prefix);
+            self.stale.insert(key_prefix.clone());
+            self.series.insert(key_prefix, instrument);
+        }
+        if let Err(e) = self.create_all_series().await {
+            self.redis_failed("create_series", e);
+        }
+    }
+
+    /// Creates, or brings up to date, every series this feed writes, each
+    /// labelled with its current staleness.
+    async fn create_all_series(&self) -> RedisResult<()> {
+        if self.kind == FeedKind::Live {
+            for (key, series_options) in RedisSink::latency_series(&self.key_root, &self.options) {
+                self.sink.create_series(&key, series_options).await?;
+            }
+        }
+        for (key_prefix, instrument) in &self.series {
+            let stale = self.stale.contains(key_prefix);
+            self.sink.create_quote_series(key_prefix, instrument, &self.options, stale).await?;
+        }
+        for (key_prefix, instrument) in &self.stats_series {
+            self.sink.create_stats_series(key_prefix, instrument, &self.options).await?;
+        }
+        for (key_prefix, instrument) in &self.trade_series {
+            self.sink.create_trade_series(key_prefix, instrument, &self.options).await?;
+        }
+        for (key_prefix, (instrument, levels)) in &self.book_series {
+            self.sink.create_book_series(key_prefix, instrument, &self.options, *levels).await?;
+        }
+        for (key_prefix, (instrument, groups)) in &self.perp_series {
+            self.sink.create_perp_series(key_prefix, instrument, &self.options, self.open_interest_unit, groups).await?;
+        }
+        Ok(())
+    }
+
+    async fn push(&mut self, tick: Tick<Quote>) {
+        let prefix = &self.sink.namespaced(&tick.key_prefix);
+        // While degraded, series changes wait for `recover`, which recreates everything
+        if !self.series.contains_key(prefix) {
+            self.series.insert(prefix.clone(), tick.instrument.clone());
+            if !self.degraded {
+                if let Err(e) = self.sink.create_quote_series(prefix, &tick.instrument, &self.options, false).await {
+                    self.redis_failed("create_series", e);
+                }
+            }
+        } else if self.stale.remove(prefix) && !self.degraded {
+            if let Err(e) = self.sink.set_stale(prefix, &tick.instrument, &self.options, false).await {
+                self.redis_failed("set_stale", e);
+            }
+        }
+        let quote = &tick.data;
+        // Stamp samples with the venue's own time where it sends one, so our
+        // network jitter doesn't skew cross-exchange comparisons
+        let ts = quote.exchange_ts.unwrap_or(tick.received_at);
+        self.batch.push((format!("{}:BUY:PRICE", prefix), ts, quote.bid));
+        self.batch.push((format!("{}:BUY:VOL", prefix), ts, quote.bid_vol));
+        self.batch.push((format!("{}:SELL:PRICE", prefix), ts, quote.ask));
+        self.batch.push((format!("{}:SELL:VOL", prefix), ts, quote.ask_vol));
+        // With nothing resting on either side there is nothing to weigh
+        if let (Some(imbalance), Some(microprice)) = (quote.imbalance(), quote.microprice()) {
+            self.batch.push((format!("{}:IMBALANCE", prefix), ts, imbalance));
+            self.batch.push((format!("{}:MICROPRICE", prefix), ts, microprice));
+        }
+        let symbol = tick.instrument.symbol();
+        BID.with_label_values(&[&self.exchange, &symbol]).set(quote.bid);
+        ASK.with_label_values(&[&self.exchange, &symbol]).set(quote.ask);
+        if let Some(exchange_ts) = quote.exchange_ts {
+            // Signed: a venue clock running ahead of ours shows up as negative latency
+            let latency = tick.received_at as f64 - exchange_ts as f64;
+            self.batch.push((format!("{}:LATENCY", prefix), exchange_ts, latency));
+            EXCHANGE_TO_RECEIVE.with_label_values(&[&self.exchange]).observe(latency);
+            self.receive_latency.observe(latency);
+        }
+        self.batch_received_at.push(tick.received_at);
+    }
+
+    async fn push_stats(&mut self, tick: Tick<TickerStats>) {
+        let prefix = &self.sink.namespaced(&tick.key_prefix);
+        if !self.stats_series.contains_key(prefix) {
+            self.stats_series.insert(prefix.clone(), tick.instrument.clone());
+            if !self.degraded {
+                if let Err(e) = self.sink.create_stats_series(prefix, &tick.instrument, &self.options).await {
+                    self.redis_failed("create_series", e);
+                }
+            }
+        }
+        let ts = tick.data.exchange_ts.unwrap_or(tick.received_at);
+        self.batch.push((format!("{}:LAST", prefix), ts, tick.data.last));
+        self.batch.push((format!("{}:VOL", prefix), ts, tick.data.volume));
+    }
+
+    /// Adds a trade to its millisecond's totals and writes them. Trades can
+    /// arrive out of order, within one push or across a reconnect; one older
+    /// than [`TRADE_WINDOW_MS`] behind the latest is dropped, since writing it
+    /// would replace its millisecond's totals.
+    async fn push_trade(&mut self, tick: Tick<Trade>) {
+        let prefix = &self.sink.namespaced(&tick.key_prefix);
+        if !self.trade_series.contains_key(prefix) {
+            self.trade_series.insert(prefix.clone(), tick.instrument.clone());
+            if !self.degraded {
+                if let Err(e) = self.sink.create_trade_series(prefix, &tick.instrument, &self.options).await {
+                    self.redis_failed("create_series", e);
+                }
+            }
+        }
+        let trade = &tick.data;
+        let ts = trade.exchange_ts.unwrap_or(tick.received_at);
+        let buckets = self.trade_buckets.entry(prefix.clone()).or_default();
+        let latest = buckets.last_key_value().map_or(ts, |(latest, _)| ts.max(*latest));
+        let kept_from = latest.saturating_sub(TRADE_WINDOW_MS);
+        if ts < kept_from {
+            warn!(event = "late_trade_dropped", exchange = %self.exchange, key_prefix = %prefix, ts, "Trade is older than the totals kept, dropping it");
+            return;
+        }
+        // Forget the milliseconds that just fell out of the window
+        while let Some(oldest) = buckets.first_entry() {
+            if *oldest.key() >= kept_from {
+                break;
+            }
+            oldest.remove();
+        }
+        let bucket = buckets.entry(ts).or_insert(TradeBucket { price: trade.price, volume: 0.0, signed_volume: 0.0 });
+        bucket.price = trade.price;
+        bucket.volume += trade.size;
+        bucket.signed_volume += trade.side.sign() * trade.size;
+        self.batch.push((format!("{}:PRICE", prefix), ts, bucket.price));
+        self.batch.push((format!("{}:VOL", prefix), ts, bucket.volume));
+        self.batch.push((format!("{}:SIGNED_VOL", prefix), ts, bucket.signed_volume));
+    }
+
+    /// Writes the top of a book, its depth bands and their imbalances. A book that thinned out
+    /// leaves its missing levels without a sample rather than writing zeros.
+    async fn push_book(&mut self, tick: Tick<BookDepth>) {
+        let prefix = &self.sink.namespaced(&tick.key_prefix);
+        let book = &tick.data;
+        let levels = book.bids.len().max(book.asks.len());
+        let created = self.book_series.get(prefix).map_or(0, |(_, created)| *created);
+        if levels > created || !self.book_series.contains_key(prefix) {
+            self.book_series.insert(prefix.clone(), (tick.instrument.clone(), levels.max(created)));
+            if !self.degraded {
+                if let Err(e) = self.sink.create_book_series(prefix, &tick.instrument, &self.options, levels).await {
+                    self.redis_failed("create_series", e);
+                }
+            }
+        }
+        let ts = book.exchange_ts.unwrap_or(tick.received_at);
+        for (side, levels) in [("BUY", &book.bids), ("SELL", &book.asks)] {
+            for (level, (price, size)) in levels.iter().enumerate() {
+                self.batch.push((format!("{}:{}:L{}:PRICE", prefix, side, level + 1), ts, *price));
+                self.batch.push((format!("{}:{}:L{}:VOL", prefix, side, level + 1), ts, *size));
+            }
+        }
+        for band in &book.bands {
+            self.batch.push((format!("{}:BUY:DEPTH:{}BPS", prefix, band.bps), ts, band.bid_vol));
+            self.batch.push((format!("{}:SELL:DEPTH:{}BPS", prefix, band.bps), ts, band.ask_vol));
+            if let Some(imbalance) = band.imbalance() {
+                self.batch.push((format!("{}:IMBALANCE:{}BPS", prefix, band.bps), ts, imbalance));
+            }
+        }
+        if let Some(imbalance) = book.imbalance() {
+            self.batch.push((format!("{}:IMBALANCE", prefix), ts, imbalance));
+        }
+    }
+
+    /// Writes whichever perpetual swap figures the update carried, and the
+    /// basis whenever the mark or the index moves.
+    async fn push_perp(&mut self, tick: Tick<PerpStats>) {
+        let prefix = &self.sink.namespaced(&tick.key_prefix);
+        let perp = &tick.data;
+        let ts = perp.exchange_ts.unwrap_or(tick.received_at);
+        let basis = self.perp_prices.entry(prefix.clone()).or_default().update(perp);
+        let figures = [
+            ("FUNDING_RATE", perp.funding_rate),
+            ("FUNDING_RATE_PAID", perp.funding_rate_paid),
+            ("MARK_PRICE", perp.mark_price),
+            ("INDEX_PRICE", perp.index_price),
+            ("OPEN_INTEREST", perp.open_interest),
+            ("BASIS", basis),
+        ];
+        let (_, created) = self.perp_series.entry(prefix.clone()).or_insert_with(|| (tick.instrument.clone(), Vec::new()));
+        let new: Vec<&'static str> =
+            figures.iter().filter(|(group, value)| value.is_some() && !created.contains(group)).map(|(group, _)| *group).collect();
+        if !new.is_empty() {
+            created.extend(&new);
+            if !self.degraded {
+                if let Err(e) = self.sink.create_perp_series(prefix, &tick.instrument, &self.options, self.open_interest_unit, &new).await {
+                    self.redis_failed("create_series", e);
+                }
+            }
+        }
+        for (group, value) in figures {
+            if let Some(value) = value {
+                self.batch.push((format!("{}:{}", prefix, group), ts, value));
+            }
+        }
+    }
+
+    async fn report_latency(&mut self) {
+        self.last_report = Instant::now();
+        let now = get_current_timestamp();
+        let mut samples = Vec::new();
+        for (stage, window) in [("RECEIVE", &mut self.receive_latency), ("COMMIT", &mut self.commit_latency)] {
+            if let Some((p50, p99)) = window.take_quantiles() {
+                samples.push((format!("{}:FEED:LATENCY:{}:P50", self.key_root, stage), now, p50));
+                samples.push((format!("{}:FEED:LATENCY:{}:P99", self.key_root, stage), now, p99));
+            }
+        }
+        if !samples.is_empty() {
+            self.write_or_spool(&samples).await;
+        }
+    }
+
+    async fn mark_all_stale(&mut self) {
+        let newly_stale: Vec<String> = self.series.keys().filter(|key_prefix| !self.stale.contains(*key_prefix)).cloned().collect();
+        for key_prefix in newly_stale {
+            self.stale.insert(key_prefix.clone());
+            if self.degraded {
+                continue;
+            }
+            if let Err(e) = self.sink.set_stale(&key_prefix, &self.series[&key_prefix], &self.options, true).await {
+                self.redis_failed("set_stale", e);
+            }
+        }
+    }
+
+    async fn flush(&mut self) {
+        if self.batch.is_empty() {
+            return;
+        }
+        let batch = mem::take(&mut self.batch);
+        if self.write_or_spool(&batch).await && self.kind == FeedKind::Live {
+            let committed_at = get_current_timestamp();
+            let histogram = RECEIVE_TO_COMMIT.with_label_values(&[&self.exchange]);
+            for received_at in &self.batch_received_at {
+                let latency = committed_at.saturating_sub(*received_at) as f64;
+                histogram.observe(latency);
+                self.commit_latency.observe(latency);
+            }
+        }
+        // Hand the allocation back for the next batch
+        self.batch = batch;
+        self.batch.clear();
+        self.batch_received_at.clear();
+    }
+
+    /// Writes `samples` to Redis, or to the spool while Redis is down. True
+    /// if they reached Redis.
+    async fn write_or_spool(&mut self, samples: &[(String, u64, f64)]) -> bool {
+        if self.degraded && !self.recover().await {
+            self.spool(samples);
+            return false;
+        }
+        match self.sink.flush(samples).await {
+            Ok(rejected) => {
+                self.samples_rejected(rejected);
+                true
+            }
+            Err(e) => {
+                self.redis_failed("write", e);
+                self.spool(samples);
+                false
+            }
+        }
+    }
+
+    /// Brings Redis up to date after an outage: recreates every series, then
+    /// replays the spool oldest first, dropping any samples Redis turns down.
+    /// Tried at most every [`RECOVERY_INTERVAL`].
+    async fn recover(&mut self) -> bool {
+        if self.last_recovery.is_some_and(|at| at.elapsed() < RECOVERY_INTERVAL) {
+            return false;
+        }
+        self.last_recovery = Some(Instant::now());
+        if self.create_all_series().await.is_err() {
+            return false;
+        }
+        let chunks = match self.spool.chunks(REPLAY_CHUNK) {
+            Ok(chunks) => chunks,
+            Err(e) => return self.spool_read_failed(e),
+        };
+        let mut replayed = 0;
+        for chunk in chunks {
+            let chunk = match chunk {
+                Ok(chunk) => chunk,
+                Err(e) => return self.spool_read_failed(e),
+            };
+            // Anything already replayed is rewritten next time; DUPLICATE_POLICY LAST makes that harmless
+            match self.sink.flush(&chunk).await {
+                Ok(rejected) => {
+                    replayed += chunk.len() - rejected.len();
+                    self.samples_rejected(rejected);
+                }
+                Err(e) => {
+                    warn!(event = "spool_replay_failed", exchange = %self.exchange, error = %e, "Redis went away again mid-replay");
+                    return false;
+                }
+            }
+        }
+        if let Err(e) = self.spool.clear() {
+            error!(event = "spool_clear_failed", exchange = %self.exchange, error = %e, "Can't clear replayed spool, it will be replayed again");
+        }
+        info!(event = "redis_recovered", exchange = %self.exchange, replayed, "Redis is back, spool replayed");
+        self.degraded = false;
+        true
+    }
+
+    /// Logs a spool that couldn't be read back. It is left as it is, for the
+    /// next recovery to try again.
+    fn spool_read_failed(&self, e: io::Error) -> bool {
+        error!(event = "spool_read_failed", exchange = %self.exchange, path = %self.spool.path().display(), error = %e, "Can't read spool, leaving it for the next recovery");
+        false
+    }
+
+    fn spool(&mut self, samples: &[(String, u64, f64)]) {
+        if let Err(e) = self.spool.append(samples) {
+            error!(event = "spool_write_failed", exchange = %self.exchange, path = %self.spool.path().display(), error = %e, "Can't spool samples, dropping them");
+        }
+    }
+
+    /// Counts and logs samples Redis turned down. They are dropped: Redis
+    /// would only turn them down again.
+    fn samples_rejected(&self, rejected: Vec<RedisError>) {
+        let Some(first) = rejected.first() else {
+            return;
+        };
+        REDIS_WRITE_ERRORS.with_label_values(&[&self.exchange]).inc_by(rejected.len() as u64);
+        warn!(event = "redis_samples_rejected", exchange = %self.exchange, rejected = rejected.len(), error = %first, "Redis turned down samples, dropping them");
+    }
+
+    /// Counts and logs a failed Redis call, then degrades the feed so
+    /// samples are spooled until Redis is back.
+    fn redis_failed(&mut self, operation: &str, e: RedisError) {
+        REDIS_WRITE_ERRORS.with_label_values(&[&self.exchange]).inc();
+        if !self.degraded {
+            warn!(event = "redis_write_failed", exchange = %self.exchange, operation, error = %e, "Redis write failed, spooling to disk");
+        }
+        self.degraded = true;
+    }
+}
+
+/// Redis answered but turned the command down, as opposed to not answering.
+fn is_rejection(e: &RedisError) -> bool {
+    matches!(e.kind(), ErrorKind::ResponseError | ErrorKind::ExtensionError)
+}
+
+fn spool_dir_from_env() -> PathBuf {
+    env::var("SPOOL_DIR").map(PathBuf::from).unwrap_or_else(|_| env::temp_dir().join("scraper-spool"))
+}
@@ -0,0 +1,142 @@
+use crate::book::BookDepth;
+use crate::error::FeedError;
+use crate::instrument::{Instrument, InstrumentType};
+use crate::perp::PerpStats;
+use crate::quote::{Quote, TickerStats};
+use crate::trade::Trade;
+use std::time::Duration;
+
+/// What a single inbound text frame turned out to be.
+#[derive(Debug)]
+pub enum Frame {
+    /// Market data to write to Redis.
+    Quotes(Vec<Quote>),
+    /// 24h ticker figures, written to their own series next to the quotes.
+    Stats(Vec<TickerStats>),
+    /// Executions from the venue's public tape, oldest first.
+    Trades(Vec<Trade>),
+    /// The top of local order books after an update, for sources that keep them.
+    Books(Vec<BookDepth>),
+    /// Funding, mark and index figures of perpetual swaps.
+    Perps(Vec<PerpStats>),
+    /// A local order book fell out of step with the venue, on a sequence gap
+    /// or a checksum mismatch, and has been emptied. The runner logs it and
+    /// sends `messages`, which ask the venue for a fresh snapshot; venues whose
+    /// snapshots come over REST have none.
+    Resync { symbol: String, reason: String, messages: Vec<String> },
+    /// Application level message the venue expects back, e.g. HTX's `{"pong": ts}`.
+    Reply(String),
+    /// Control traffic worth a log line (acks, heartbeats, channel ids).
+    Event(String),
+    /// Traffic we deliberately drop without logging.
+    Ignored,
+    /// The frame couldn't be parsed, carried bad values or was the venue
+    /// reporting an error. The runner logs it with the raw content and acts
+    /// on [`FeedError::policy`].
+    Error(FeedError),
+}
+
+/// The per-exchange half of a scraper. Implementations only deal with the
+/// venue's protocol; connecting, pinging, reconnecting and writing to Redis
+/// are handled by [`crate::run`].
+pub trait QuoteSource {
+    /// Value of the `EXCHANGE` label, e.g. `BINANCE`. It also names the
+    /// feed's spool, health entry and metrics, so sources that run over
+    /// separate sockets need names of their own, e.g. `BINANCE_USDM`.
+    fn exchange(&self) -> &'static str;
+
+    /// Venue-native symbols this source subscribes to. Their series are
+    /// created up front, before the first quote arrives.
+    fn symbols(&self) -> &[String];
+
+    /// Canonical instrument for a venue-native symbol, or `None` if it can't be
+    /// mapped. Venues whose symbols carry decoration or that only list
+    /// derivatives override this.
+    fn instrument(&self, symbol: &str) -> Option<Instrument> {
+        Instrument::parse(symbol, InstrumentType::Spot)
+    }
+
+    /// Prefix the `:BUY:PRICE`, `:SELL:VOL`, ... keys for `instrument` are
+    /// written under, e.g. `BINANCE:BTC-USDT:QUOTE`.
+    fn key_prefix(&self, instrument: &Instrument) -> String {
+        format!("{}:{}:QUOTE", self.exchange(), instrument.symbol())
+    }
+
+    /// Prefix the `:LAST` and `:VOL` keys of [`Frame::Stats`] for
+    /// `instrument` are written under, e.g. `HTX:BTC-USDT:STATS:24H`.
+    fn stats_prefix(&self, instrument: &Instrument) -> String {
+        format!("{}:{}:STATS:24H", self.exchange(), instrument.symbol())
+    }
+
+    /// Prefix the `:PRICE`, `:VOL` and `:SIGNED_VOL` keys of
+    /// [`Frame::Trades`] for `instrument` are written under, e.g.
+    /// `BINANCE:BTC-USDT:TRADE`.
+    fn trade_prefix(&self, instrument: &Instrument) -> String {
+        format!("{}:{}:TRADE", self.exchange(), instrument.symbol())
+    }
+
+    /// Prefix the `:BUY:L1:PRICE`, `:SELL:DEPTH:10BPS`, ... keys of
+    /// [`Frame::Books`] for `instrument` are written under, e.g.
+    /// `OKX:BTC-USDT:BOOK`.
+    fn book_prefix(&self, instrument: &Instrument) -> String {
+        format!("{}:{}:BOOK", self.exchange(), instrument.symbol())
+    }
+
+    /// Prefix the `:FUNDING_RATE`, `:MARK_PRICE`, ... keys of
+    /// [`Frame::Perps`] for `instrument` are written under, e.g.
+    /// `BITMEX:BTC-USD-PERP:PERP`.
+    fn perp_prefix(&self, instrument: &Instrument) -> String {
+        format!("{}:{}:PERP", self.exchange(), instrument.symbol())
+    }
+
+    /// What [`PerpStats::open_interest`](crate::PerpStats::open_interest) is
+    /// counted in, written as the `UNIT` label of `:OPEN_INTEREST`: `CONTRACTS`
+    /// unless the venue gives it in the base asset, `BASE`.
+    fn open_interest_unit(&self) -> &'static str {
+        "CONTRACTS"
+    }
+
+    /// WebSocket URL to connect to. Called before every (re)connect, so venues
+    /// handing out per-session tokens can fetch a fresh one here. Runs on a
+    /// blocking-capable worker, so a synchronous HTTP call is fine.
+    fn endpoint(&mut self) -> Result<String, FeedError>;
+
+    /// Messages sent as soon as the socket is open. Also called after every
+    /// reconnect, which is where per-connection state should be reset.
+    fn subscribe_messages(&mut self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Messages sent on a graceful shutdown, just before the close frame, so
+    /// the venue sees a clean unsubscribe. Venues subscribed through the URL
+    /// have nothing to send.
+    fn unsubscribe_messages(&mut self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Turns a binary frame into text. Only venues that compress their frames
+    /// need to override this.
+    fn decode_binary(&self, data: &[u8]) -> Result<String, FeedError> {
+        Err(FeedError::decode(format!("unexpected binary frame of {} bytes", data.len())))
+    }
+
+    fn parse(&mut self, text: &str) -> Frame;
+
+    /// How long the socket may go without market data before we ping it.
+    fn ping_interval(&self) -> Duration {
+        Duration::from_secs(5)
+    }
+
+    /// How long the socket may go without any frame at all, pongs included,
+    /// before it is treated as dead and reconnected.
+    fn read_timeout(&self) -> Duration {
+        self.ping_interval() * 3
+    }
+
+    /// How long the feed may go without market data, even while heartbeats
+    /// keep arriving, before it is marked stale and reconnected. Venues that
+    /// only publish on change want a longer window.
+    fn stale_after(&self) -> Duration {
+        Duration::from_secs(60)
+    }
+}
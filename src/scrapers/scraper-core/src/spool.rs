@@ -0,0 +1,211 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// Stop spooling past this, rather than fill the disk during a long outage.
+const MAX_SPOOL_BYTES: u64 = 1 << 30;
+
+/// One feed's append-only file of samples Redis couldn't take, one
+/// `key\tts\tvalue` line each. A spool left over from an earlier run is picked
+/// up and replayed like any other.
+pub(crate) struct Spool {
+    path: PathBuf,
+    bytes: u64,
+    /// [`MAX_SPOOL_BYTES`] outside of tests.
+    max_bytes: u64,
+    full: bool,
+}
+
+impl Spool {
+    pub fn open(dir: &Path, exchange: &str) -> Self {
+        let path = dir.join(format!("{}.spool", exchange));
+        let bytes = fs::metadata(&path).map(|metadata| metadata.len()).unwrap_or(0);
+        Spool { path, bytes, max_bytes: MAX_SPOOL_BYTES, full: false }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes == 0
+    }
+
+    /// Appends `samples`. Once the spool is full this fails once, then drops
+    /// everything silently until it is cleared.
+    pub fn append(&mut self, samples: &[(String, u64, f64)]) -> io::Result<()> {
+        if self.full {
+            return Ok(());
+        }
+        if self.bytes >= self.max_bytes {
+            self.full = true;
+            return Err(io::Error::other(format!("spool is over {} bytes, dropping samples", self.max_bytes)));
+        }
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        let mut writer = BufWriter::new(file);
+        let mut written = 0;
+        for (key, ts, value) in samples {
+            let line = format!("{}\t{}\t{}\n", key, ts, value);
+            writer.write_all(line.as_bytes())?;
+            written += line.len() as u64;
+        }
+        writer.flush()?;
+        self.bytes += written;
+        Ok(())
+    }
+
+    /// Everything spooled, streamed back in chunks of at most `size`
+    /// samples, each sorted oldest first. Chunks come in the order they were
+    /// spooled, which is close to timestamp order already, so a spool of any
+    /// size is replayed holding no more than a chunk in memory. Lines that
+    /// don't parse, such as one torn by a crash mid-write, are skipped.
+    pub fn chunks(&self, size: usize) -> io::Result<SpoolChunks> {
+        let reader = match File::open(&self.path) {
+            Ok(file) => Some(BufReader::new(file)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e),
+        };
+        Ok(SpoolChunks { reader, size, line: Vec::new() })
+    }
+
+    pub fn clear(&mut self) -> io::Result<()> {
+        match fs::remove_file(&self.path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+            _ => {}
+        }
+        self.bytes = 0;
+        self.full = false;
+        Ok(())
+    }
+}
+
+/// See [`Spool::chunks`].
+pub(crate) struct SpoolChunks {
+    /// `None` once the spool is read to the end, or failed.
+    reader: Option<BufReader<File>>,
+    size: usize,
+    line: Vec<u8>,
+}
+
+impl Iterator for SpoolChunks {
+    type Item = io::Result<Vec<(String, u64, f64)>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let reader = self.reader.as_mut()?;
+        let mut chunk = Vec::new();
+        while chunk.len() < self.size {
+            self.line.clear();
+            match reader.read_until(b'\n', &mut self.line) {
+                Ok(0) => break,
+                Ok(_) => chunk.extend(parse_line(&self.line)),
+                Err(e) => {
+                    self.reader = None;
+                    return Some(Err(e));
+                }
+            }
+        }
+        if chunk.is_empty() {
+            self.reader = None;
+            return None;
+        }
+        chunk.sort_by_key(|(_, ts, _)| *ts);
+        Some(Ok(chunk))
+    }
+}
+
+fn parse_line(line: &[u8]) -> Option<(String, u64, f64)> {
+    let line = std::str::from_utf8(line).ok()?.strip_suffix('\n')?;
+    let mut fields = line.split('\t');
+    let key = fields.next()?;
+    let ts = fields.next()?.parse().ok()?;
+    let value = fields.next()?.parse().ok()?;
+    Some((key.to_string(), ts, value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sink::REPLAY_CHUNK;
+
+    fn spool(name: &str) -> Spool {
+        let dir = std::env::temp_dir().join("scraper-core-spool-tests").join(format!("{}-{}", std::process::id(), name));
+        let mut spool = Spool::open(&dir, "TEST");
+        spool.clear().unwrap();
+        spool
+    }
+
+    fn sample(key: &str, ts: u64, value: f64) -> (String, u64, f64) {
+        (key.to_string(), ts, value)
+    }
+
+    fn read_all(spool: &Spool) -> Vec<(String, u64, f64)> {
+        spool.chunks(REPLAY_CHUNK).unwrap().flat_map(Result::unwrap).collect()
+    }
+
+    #[test]
+    fn reads_back_oldest_first() {
+        let mut spool = spool("order");
+        assert!(spool.is_empty());
+        spool.append(&[sample("A", 30, 3.0), sample("B", 10, 1.0)]).unwrap();
+        spool.append(&[sample("A", 20, 2.0), sample("B", 40, 4.5)]).unwrap();
+        assert!(!spool.is_empty());
+        let samples = read_all(&spool);
+        assert_eq!(samples, [sample("B", 10, 1.0), sample("A", 20, 2.0), sample("A", 30, 3.0), sample("B", 40, 4.5)]);
+
+        // A spool left by an earlier run is picked up as it was
+        let reopened = Spool::open(spool.path().parent().unwrap(), "TEST");
+        assert!(!reopened.is_empty());
+        assert_eq!(read_all(&reopened), samples);
+
+        spool.clear().unwrap();
+        assert!(spool.is_empty());
+        assert!(read_all(&spool).is_empty());
+    }
+
+    #[test]
+    fn replays_more_than_a_chunk_a_chunk_at_a_time() {
+        let mut spool = spool("chunks");
+        let total = REPLAY_CHUNK * 2 + 7;
+        // Spooled in batches that each arrive newest first
+        let samples: Vec<_> = (0..total as u64).map(|ts| sample("A", ts, ts as f64)).collect();
+        for batch in samples.chunks(100) {
+            let reversed: Vec<_> = batch.iter().rev().cloned().collect();
+            spool.append(&reversed).unwrap();
+        }
+        let chunks: Vec<_> = spool.chunks(REPLAY_CHUNK).unwrap().map(Result::unwrap).collect();
+        let sizes: Vec<usize> = chunks.iter().map(Vec::len).collect();
+        assert_eq!(sizes, [REPLAY_CHUNK, REPLAY_CHUNK, 7]);
+        assert_eq!(chunks.concat(), samples);
+    }
+
+    #[test]
+    fn skips_torn_lines() {
+        let mut spool = spool("torn");
+        spool.append(&[sample("A", 10, 1.0)]).unwrap();
+        let mut file = OpenOptions::new().append(true).open(spool.path()).unwrap();
+        file.write_all(b"A\t20\n\t\tnot\nA\t15\t1.5\nA\t3").unwrap();
+        assert_eq!(read_all(&spool), [sample("A", 10, 1.0), sample("A", 15, 1.5)]);
+    }
+
+    #[test]
+    fn stops_growing_at_the_cap_until_cleared() {
+        let mut spool = spool("cap");
+        spool.max_bytes = 10;
+        spool.append(&[sample("A", 10, 1.0), sample("A", 11, 1.0)]).unwrap();
+        let capped = fs::metadata(spool.path()).unwrap().len();
+        assert!(capped >= 10);
+
+        // Fails once, then drops samples without a word
+        assert!(spool.append(&[sample("A", 12, 1.0)]).is_err());
+        assert!(spool.append(&[sample("A", 13, 1.0)]).is_ok());
+        assert_eq!(fs::metadata(spool.path()).unwrap().len(), capped);
+        assert_eq!(read_all(&spool).len(), 2);
+
+        spool.clear().unwrap();
+        spool.append(&[sample("A", 14, 1.0)]).unwrap();
+        assert_eq!(read_all(&spool), [sample("A", 14, 1.0)]);
+    }
+}
@@ -0,0 +1,128 @@
+use crate::metrics::RECONNECTS;
+use rand::Rng;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+const BASE_DELAY: Duration = Duration::from_secs(1);
+const MAX_DELAY: Duration = Duration::from_secs(60);
+/// A connection that stayed up this long counts as healthy and resets the backoff.
+const STABLE_AFTER: Duration = Duration::from_secs(60);
+
+/// Reconnect bookkeeping for one feed: the backoff state, how many times it
+/// has reconnected and why it last dropped.
+pub(crate) struct Supervisor {
+    exchange: &'static str,
+    attempt: u32,
+    connected_at: Option<Instant>,
+    reconnects: u64,
+    last_error: Option<String>,
+}
+
+impl Supervisor {
+    pub fn new(exchange: &'static str) -> Self {
+        Supervisor { exchange, attempt: 0, connected_at: None, reconnects: 0, last_error: None }
+    }
+
+    pub fn connected(&mut self) {
+        self.connected_at = Some(Instant::now());
+        if let Some(last_error) = &self.last_error {
+            info!(
+                event = "reconnected",
+                exchange = self.exchange,
+                reconnects = self.reconnects,
+                last_error = %last_error,
+                "Reconnected"
+            );
+        }
+    }
+
+    /// Records why the connection dropped, or failed to open, then waits out
+    /// the backoff before the caller tries again.
+    pub async fn disconnected(&mut self, error: String) {
+        self.reset_if_stable(Instant::now());
+        self.reconnects += 1;
+        RECONNECTS.with_label_values(&[self.exchange]).inc();
+        let delay = self.next_delay();
+        warn!(event = "disconnected", exchange = self.exchange, error = %error, ?delay, "Disconnected, reconnecting");
+        self.last_error = Some(error);
+        tokio::time::sleep(delay).await;
+    }
+
+    /// Starts the backoff over if the connection that just dropped had been
+    /// up for [`STABLE_AFTER`] by `now`.
+    fn reset_if_stable(&mut self, now: Instant) {
+        if self.connected_at.take().is_some_and(|since| now.saturating_duration_since(since) >= STABLE_AFTER) {
+            self.attempt = 0;
+        }
+    }
+
+    /// Exponential backoff capped at [`MAX_DELAY`], with the upper half
+    /// randomised so feeds that dropped together don't reconnect in lockstep.
+    fn next_delay(&mut self) -> Duration {
+        let ceiling = ceiling(self.attempt);
+        self.attempt = self.attempt.saturating_add(1);
+        let half = ceiling / 2;
+        half + rand::thread_rng().gen_range(Duration::ZERO..=half)
+    }
+}
+
+/// The longest wait before the `attempt`th reconnect in a row, counting from 0.
+fn ceiling(attempt: u32) -> Duration {
+    BASE_DELAY.saturating_mul(2_u32.saturating_pow(attempt)).min(MAX_DELAY)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_up_to_the_cap() {
+        let ceilings: Vec<u64> = (0..9).map(|attempt| ceiling(attempt).as_secs()).collect();
+        assert_eq!(ceilings, [1, 2, 4, 8, 16, 32, 60, 60, 60]);
+        // Far past the point 2^attempt overflows
+        assert_eq!(ceiling(u32::MAX), MAX_DELAY);
+    }
+
+    #[test]
+    fn delays_stay_within_the_upper_half_of_each_ceiling() {
+        let mut supervisor = Supervisor::new("TEST");
+        for attempt in 0..12 {
+            let ceiling = ceiling(attempt);
+            for _ in 0..50 {
+                supervisor.attempt = attempt;
+                let delay = supervisor.next_delay();
+                assert!(delay >= ceiling / 2 && delay <= ceiling, "{:?} outside {:?}", delay, ceiling);
+                assert_eq!(supervisor.attempt, attempt + 1);
+            }
+        }
+    }
+
+    #[test]
+    fn a_stable_connection_resets_the_backoff() {
+        let mut supervisor = Supervisor::new("TEST");
+        for _ in 0..5 {
+            supervisor.next_delay();
+        }
+        let connected = Instant::now();
+        supervisor.connected_at = Some(connected);
+        supervisor.reset_if_stable(connected + STABLE_AFTER);
+        assert_eq!(supervisor.attempt, 0);
+        assert!(supervisor.connected_at.is_none());
+    }
+
+    #[test]
+    fn a_short_lived_connection_keeps_backing_off() {
+        let mut supervisor = Supervisor::new("TEST");
+        for _ in 0..5 {
+            supervisor.next_delay();
+        }
+        let connected = Instant::now();
+        supervisor.connected_at = Some(connected);
+        supervisor.reset_if_stable(connected + STABLE_AFTER - Duration::from_millis(1));
+        assert_eq!(supervisor.attempt, 5);
+        // Failing to connect at all never resets it either
+        supervisor.reset_if_stable(connected + STABLE_AFTER * 10);
+        assert_eq!(supervisor.attempt, 5);
+        assert!(supervisor.next_delay() >= ceiling(5) / 2);
+    }
+}
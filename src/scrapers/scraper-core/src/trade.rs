@@ -0,0 +1,65 @@
+use crate::error::FeedError;
+use serde::{de, Deserialize, Deserializer, Serialize};
+
+/// Which side took liquidity in a trade. Deserializes from `buy` or `sell` in
+/// any case, the way most venues spell the aggressor.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Side {
+    /// A buyer lifted an offer.
+    Buy,
+    /// A seller hit a bid.
+    Sell,
+}
+
+impl Side {
+    /// `+1` for buys and `-1` for sells, for signed volume.
+    pub fn sign(&self) -> f64 {
+        match self {
+            Side::Buy => 1.0,
+            Side::Sell => -1.0,
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Side {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        if name.eq_ignore_ascii_case("buy") {
+            Ok(Side::Buy)
+        } else if name.eq_ignore_ascii_case("sell") {
+            Ok(Side::Sell)
+        } else {
+            Err(de::Error::custom(format!("unknown side {:?}", name)))
+        }
+    }
+}
+
+/// One execution on a venue's public tape.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Trade {
+    /// Instrument as the venue names it, as in [`crate::Quote::symbol`].
+    pub symbol: String,
+    pub price: f64,
+    /// In the base currency, or contracts for derivatives quoted that way.
+    pub size: f64,
+    /// The aggressor, whichever way round the venue reports it.
+    pub side: Side,
+    /// The venue's id for the trade. `None` for venues that don't send one.
+    pub trade_id: Option<String>,
+    /// When the trade happened, in epoch millis.
+    pub exchange_ts: Option<u64>,
+}
+
+impl Trade {
+    /// A trade needs a positive price and a positive size, both finite.
+    pub fn validate(&self) -> Result<(), FeedError> {
+        if !(self.price.is_finite() && self.price > 0.0) {
+            return Err(FeedError::validation(format!("price {} for {} is not a positive price", self.price, self.symbol)));
+        }
+        if !(self.size.is_finite() && self.size > 0.0) {
+            return Err(FeedError::validation(format!("size {} for {} is not a positive size", self.size, self.symbol)));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,82 @@
+use chrono::DateTime;
+use serde::de;
+use serde::{Deserialize, Deserializer};
+use std::env;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub fn de_float_from_str<'a, D>(deserializer: D) -> Result<f64, D::Error>
+where
+    D: Deserializer<'a>,
+{
+    let str_val = String::deserialize(deserializer)?;
+    if str_val.is_empty() {
+        return Err(de::Error::custom("cannot parse float from empty string"));
+    }
+    str_val.parse::<f64>().map_err(de::Error::custom)
+}
+
+/// [`de_float_from_str`] for fields a venue leaves out of some messages, e.g.
+/// deltas carrying only what changed. Pair it with `#[serde(default)]`.
+pub fn de_opt_float_from_str<'a, D>(deserializer: D) -> Result<Option<f64>, D::Error>
+where
+    D: Deserializer<'a>,
+{
+    de_float_from_str(deserializer).map(Some)
+}
+
+/// Epoch millis sent as a string, e.g. OKX's `"ts": "1597026383085"`.
+pub fn de_millis_from_str<'a, D>(deserializer: D) -> Result<u64, D::Error>
+where
+    D: Deserializer<'a>,
+{
+    let str_val = String::deserialize(deserializer)?;
+    str_val.parse::<u64>().map_err(de::Error::custom)
+}
+
+/// Epoch millis of an RFC 3339 time such as `2024-01-03T00:09:50.444Z`.
+pub fn rfc3339_millis(timestamp: &str) -> Option<u64> {
+    DateTime::parse_from_rfc3339(timestamp)
+        .ok()
+        .and_then(|time| u64::try_from(time.timestamp_millis()).ok())
+}
+
+pub fn get_current_timestamp() -> u64 {
+    let start = SystemTime::now();
+    let since_the_epoch = start
+        .duration_since(UNIX_EPOCH)
+        .expect("Time went backwards");
+    since_the_epoch.as_millis() as u64
+}
+
+/// Comma separated `$SYMBOLS`, e.g. `BTCUSDT,ETHUSDT,SOLUSDT`. `None` when unset
+/// or empty so callers fall back to their default instrument.
+pub fn symbols_from_env() -> Option<Vec<String>> {
+    list_from_env("SYMBOLS")
+}
+
+/// Comma separated `$SWAPS`, perpetual swaps to take funding and mark figures
+/// for on venues that list them apart from spot, e.g. `BTC-USDT-SWAP` on OKX.
+/// `None` when unset or empty.
+pub fn swaps_from_env() -> Option<Vec<String>> {
+    list_from_env("SWAPS")
+}
+
+fn list_from_env(name: &str) -> Option<Vec<String>> {
+    let items: Vec<String> = env::var(name)
+        .ok()?
+        .split(',')
+        .map(|item| item.trim().to_string())
+        .filter(|item| !item.is_empty())
+        .collect();
+    if items.is_empty() {
+        None
+    } else {
+        Some(items)
+    }
+}
+
+/// `$BOOK_LEVELS`, how many levels of each side of a local order book to
+/// write. `None` when unset, empty or zero, which leaves books off.
+pub fn book_levels_from_env() -> Option<usize> {
+    env::var("BOOK_LEVELS").ok()?.trim().parse().ok().filter(|levels| *levels > 0)
+}